@@ -0,0 +1,332 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! FROST ciphersuites selectable at runtime.
+//!
+//! The validator network runs the key generation and signing of the [`Ciphersuite`] selected when
+//! it is set up. [`CiphersuiteDkg`] holds the [`FrostDkg`] of that ciphersuite, and the keys and
+//! signatures it produces are returned as [`AnyVerifyingKey`] and [`AnySignature`].
+//!
+//! # On-chain verification
+//!
+//! The task pallet verifies keys and signatures with `ed25519-consensus`, so it only accepts those
+//! of [`Ciphersuite::Ed25519`]. The other ciphersuites produce keys and signatures for off-chain
+//! verifiers, and must not be registered or submitted on chain.
+
+use crate::{crypto::FrostDkg, DkgSignature, DkgVerifyingKey, Identifier};
+use anyhow::{anyhow, Result};
+use frost_core as frost;
+use frost_ed25519::Ed25519Sha512;
+use frost_ristretto255::Ristretto255Sha512;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A FROST ciphersuite the validator network can run.
+///
+/// Every participant of a key generation must run the same ciphersuite: packages of another one
+/// are rejected when they are decoded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Ciphersuite {
+	/// FROST(Ed25519, SHA-512), the only ciphersuite verified by the task pallet.
+	#[default]
+	Ed25519,
+	/// FROST(ristretto255, SHA-512).
+	Ristretto255,
+}
+
+impl Ciphersuite {
+	/// Every ciphersuite the validator network can run.
+	pub const SUPPORTED: &[Ciphersuite] = &[Ciphersuite::Ed25519, Ciphersuite::Ristretto255];
+
+	/// Returns the context string that identifies the ciphersuite in the FROST specification,
+	/// such as `FROST-ED25519-SHA512-v1`.
+	pub fn id(&self) -> &'static str {
+		match self {
+			Ciphersuite::Ed25519 => <Ed25519Sha512 as frost::Ciphersuite>::ID,
+			Ciphersuite::Ristretto255 => <Ristretto255Sha512 as frost::Ciphersuite>::ID,
+		}
+	}
+
+	/// Returns whether the task pallet verifies keys and signatures of this ciphersuite.
+	pub fn is_verified_on_chain(&self) -> bool {
+		matches!(self, Ciphersuite::Ed25519)
+	}
+}
+
+impl fmt::Display for Ciphersuite {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(self.id())
+	}
+}
+
+/// A FROST ciphersuite listed in [`Ciphersuite`], which code generic over the ciphersuite uses to
+/// reach the `FrostDkg` of a [`CiphersuiteDkg`] and to return what it produces.
+pub trait SupportedCiphersuite: frost::Ciphersuite + 'static {
+	/// The `Ciphersuite` naming this ciphersuite.
+	const CIPHERSUITE: Ciphersuite;
+
+	/// Returns the key generation of `dkg`, or `None` if it runs another ciphersuite.
+	fn frost_dkg(dkg: &mut CiphersuiteDkg) -> Option<&mut FrostDkg<Self>>;
+
+	/// Converts an identifier of this ciphersuite into an Ed25519 one.
+	///
+	/// Both ciphersuites use the scalar field of Curve25519, so the conversion keeps the value.
+	fn identifier(id: frost::Identifier<Self>) -> Identifier;
+
+	/// Wraps a verifying key of this ciphersuite.
+	fn verifying_key(key: frost::VerifyingKey<Self>) -> AnyVerifyingKey;
+
+	/// Wraps a signature of this ciphersuite.
+	fn signature(signature: frost::Signature<Self>) -> AnySignature;
+}
+
+impl SupportedCiphersuite for Ed25519Sha512 {
+	const CIPHERSUITE: Ciphersuite = Ciphersuite::Ed25519;
+
+	fn frost_dkg(dkg: &mut CiphersuiteDkg) -> Option<&mut FrostDkg<Self>> {
+		match dkg {
+			CiphersuiteDkg::Ed25519(dkg) => Some(dkg),
+			_ => None,
+		}
+	}
+
+	fn identifier(id: frost::Identifier<Self>) -> Identifier {
+		id
+	}
+
+	fn verifying_key(key: frost::VerifyingKey<Self>) -> AnyVerifyingKey {
+		AnyVerifyingKey::Ed25519(key)
+	}
+
+	fn signature(signature: frost::Signature<Self>) -> AnySignature {
+		AnySignature::Ed25519(signature)
+	}
+}
+
+impl SupportedCiphersuite for Ristretto255Sha512 {
+	const CIPHERSUITE: Ciphersuite = Ciphersuite::Ristretto255;
+
+	fn frost_dkg(dkg: &mut CiphersuiteDkg) -> Option<&mut FrostDkg<Self>> {
+		match dkg {
+			CiphersuiteDkg::Ristretto255(dkg) => Some(dkg),
+			_ => None,
+		}
+	}
+
+	fn identifier(id: frost::Identifier<Self>) -> Identifier {
+		Identifier::deserialize(&id.serialize())
+			.expect("ristretto255 and Ed25519 share the scalar field of Curve25519")
+	}
+
+	fn verifying_key(key: frost::VerifyingKey<Self>) -> AnyVerifyingKey {
+		AnyVerifyingKey::Ristretto255(key)
+	}
+
+	fn signature(signature: frost::Signature<Self>) -> AnySignature {
+		AnySignature::Ristretto255(signature)
+	}
+}
+
+// Evaluates `$body` with `$dkg` bound to the `FrostDkg` of `$self`, whatever its ciphersuite.
+macro_rules! dispatch {
+	($self:expr, $dkg:ident => $body:expr) => {
+		match $self {
+			CiphersuiteDkg::Ed25519($dkg) => $body,
+			CiphersuiteDkg::Ristretto255($dkg) => $body,
+		}
+	};
+}
+
+/// The `FrostDkg` of a ciphersuite selected at runtime.
+///
+/// Methods that do not depend on the ciphersuite are available directly. Packages are exchanged
+/// with the `FrostDkg` returned by [`SupportedCiphersuite::frost_dkg`].
+pub enum CiphersuiteDkg {
+	Ed25519(FrostDkg<Ed25519Sha512>),
+	Ristretto255(FrostDkg<Ristretto255Sha512>),
+}
+
+impl CiphersuiteDkg {
+	/// Creates the key generation of `ciphersuite` for the participant whose identifier is
+	/// derived from `id`.
+	pub fn new(ciphersuite: Ciphersuite, id: &[u8]) -> Result<Self> {
+		Ok(match ciphersuite {
+			Ciphersuite::Ed25519 => Self::Ed25519(FrostDkg::new(frost::Identifier::derive(id)?)),
+			Ciphersuite::Ristretto255 =>
+				Self::Ristretto255(FrostDkg::new(frost::Identifier::derive(id)?)),
+		})
+	}
+
+	/// Returns the ciphersuite of the key generation.
+	pub fn ciphersuite(&self) -> Ciphersuite {
+		match self {
+			CiphersuiteDkg::Ed25519(_) => Ciphersuite::Ed25519,
+			CiphersuiteDkg::Ristretto255(_) => Ciphersuite::Ristretto255,
+		}
+	}
+
+	/// Returns the identifier of this participant, converted into an Ed25519 one.
+	pub fn identifier(&self) -> Identifier {
+		match self {
+			CiphersuiteDkg::Ed25519(dkg) => dkg.identifier(),
+			CiphersuiteDkg::Ristretto255(dkg) => Ristretto255Sha512::identifier(dkg.identifier()),
+		}
+	}
+
+	/// See [`FrostDkg::set_nt`].
+	pub fn set_nt(&mut self, n: u16, t: u16) -> Result<()> {
+		dispatch!(self, dkg => dkg.set_nt(n, t))
+	}
+
+	/// See [`FrostDkg::threshold`].
+	pub fn threshold(&self) -> u16 {
+		dispatch!(self, dkg => dkg.threshold())
+	}
+
+	/// See [`FrostDkg::participants`].
+	pub fn participants(&self) -> u16 {
+		dispatch!(self, dkg => dkg.participants())
+	}
+
+	/// See [`FrostDkg::sign_message`].
+	pub fn sign_message(&self) -> &[u8] {
+		dispatch!(self, dkg => dkg.sign_message())
+	}
+
+	/// See [`FrostDkg::abort_sign`].
+	pub fn abort_sign(&mut self) {
+		dispatch!(self, dkg => dkg.abort_sign())
+	}
+
+	/// See [`FrostDkg::abort_dkg`].
+	pub fn abort_dkg(&mut self) {
+		dispatch!(self, dkg => dkg.abort_dkg())
+	}
+}
+
+/// A group verifying key of any supported ciphersuite.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnyVerifyingKey {
+	Ed25519(DkgVerifyingKey),
+	Ristretto255(frost_ristretto255::VerifyingKey),
+}
+
+impl AnyVerifyingKey {
+	/// Returns the ciphersuite of the key.
+	pub fn ciphersuite(&self) -> Ciphersuite {
+		match self {
+			AnyVerifyingKey::Ed25519(_) => Ciphersuite::Ed25519,
+			AnyVerifyingKey::Ristretto255(_) => Ciphersuite::Ristretto255,
+		}
+	}
+
+	/// Serializes the key in the encoding of its ciphersuite.
+	pub fn serialize(&self) -> [u8; 32] {
+		match self {
+			AnyVerifyingKey::Ed25519(key) => key.serialize(),
+			AnyVerifyingKey::Ristretto255(key) => key.serialize(),
+		}
+	}
+
+	/// Returns the Ed25519 key the task pallet verifies, or an error for another ciphersuite.
+	pub fn into_ed25519(self) -> Result<DkgVerifyingKey> {
+		match self {
+			AnyVerifyingKey::Ed25519(key) => Ok(key),
+			key => Err(anyhow!("A {} key is not verified on chain", key.ciphersuite())),
+		}
+	}
+}
+
+impl From<DkgVerifyingKey> for AnyVerifyingKey {
+	fn from(key: DkgVerifyingKey) -> Self {
+		AnyVerifyingKey::Ed25519(key)
+	}
+}
+
+/// A group signature of any supported ciphersuite.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnySignature {
+	Ed25519(DkgSignature),
+	Ristretto255(frost_ristretto255::Signature),
+}
+
+impl AnySignature {
+	/// Returns the ciphersuite of the signature.
+	pub fn ciphersuite(&self) -> Ciphersuite {
+		match self {
+			AnySignature::Ed25519(_) => Ciphersuite::Ed25519,
+			AnySignature::Ristretto255(_) => Ciphersuite::Ristretto255,
+		}
+	}
+
+	/// Serializes the signature in the encoding of its ciphersuite.
+	pub fn serialize(&self) -> [u8; 64] {
+		match self {
+			AnySignature::Ed25519(signature) => signature.serialize(),
+			AnySignature::Ristretto255(signature) => signature.serialize(),
+		}
+	}
+
+	/// Returns the Ed25519 signature the task pallet verifies, or an error for another
+	/// ciphersuite.
+	pub fn into_ed25519(self) -> Result<DkgSignature> {
+		match self {
+			AnySignature::Ed25519(signature) => Ok(signature),
+			signature => {
+				Err(anyhow!("A {} signature is not verified on chain", signature.ciphersuite()))
+			},
+		}
+	}
+}
+
+impl From<DkgSignature> for AnySignature {
+	fn from(signature: DkgSignature) -> Self {
+		AnySignature::Ed25519(signature)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn only_ed25519_is_verified_on_chain() {
+		let verified: Vec<_> =
+			Ciphersuite::SUPPORTED.iter().filter(|c| c.is_verified_on_chain()).collect();
+		assert_eq!(verified, [&Ciphersuite::Ed25519]);
+		assert_eq!(Ciphersuite::default(), Ciphersuite::Ed25519);
+	}
+
+	#[test]
+	fn dkg_runs_the_selected_ciphersuite() {
+		for ciphersuite in Ciphersuite::SUPPORTED {
+			let mut dkg = CiphersuiteDkg::new(*ciphersuite, b"validator").unwrap();
+			assert_eq!(dkg.ciphersuite(), *ciphersuite);
+			assert_eq!(
+				Ed25519Sha512::frost_dkg(&mut dkg).is_some(),
+				*ciphersuite == Ciphersuite::Ed25519
+			);
+			assert_eq!(
+				Ristretto255Sha512::frost_dkg(&mut dkg).is_some(),
+				*ciphersuite == Ciphersuite::Ristretto255
+			);
+		}
+	}
+
+	#[test]
+	fn ristretto255_identifiers_keep_their_value() {
+		let id = frost::Identifier::<Ristretto255Sha512>::try_from(7).unwrap();
+		assert_eq!(Ristretto255Sha512::identifier(id), Identifier::try_from(7).unwrap());
+	}
+}
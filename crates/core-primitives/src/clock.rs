@@ -0,0 +1,94 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Time sources for time-dependent code.
+//!
+//! Timeouts, deadlines and timestamps read the time through a [`Clock`], so that tests can drive
+//! them with a [`ManualClock`] instead of sleeping.
+
+use std::{
+	sync::{Arc, Mutex},
+	time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// A source of the current time.
+pub trait Clock: Send + Sync {
+	/// Returns the current monotonic instant, for deadlines and durations.
+	fn now(&self) -> Instant;
+
+	/// Returns the current wall-clock time, in seconds since the Unix epoch.
+	fn unix(&self) -> u64;
+}
+
+/// The clock of the operating system.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+	fn now(&self) -> Instant {
+		Instant::now()
+	}
+
+	fn unix(&self) -> u64 {
+		SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+	}
+}
+
+/// A clock that only moves when advanced, for tests.
+///
+/// Clones share the same time, so a test can keep one and hand the other to the code under test.
+#[derive(Clone, Debug)]
+pub struct ManualClock(Arc<Mutex<(Instant, Duration)>>);
+
+impl ManualClock {
+	/// Creates a clock stopped at the current instant, reading `unix` seconds since the Unix
+	/// epoch.
+	pub fn new(unix: u64) -> Self {
+		ManualClock(Arc::new(Mutex::new((Instant::now(), Duration::from_secs(unix)))))
+	}
+
+	/// Moves the clock forward by `by`.
+	pub fn advance(&self, by: Duration) {
+		let mut time = self.0.lock().unwrap_or_else(|e| e.into_inner());
+		time.0 += by;
+		time.1 += by;
+	}
+}
+
+impl Clock for ManualClock {
+	fn now(&self) -> Instant {
+		self.0.lock().unwrap_or_else(|e| e.into_inner()).0
+	}
+
+	fn unix(&self) -> u64 {
+		self.0.lock().unwrap_or_else(|e| e.into_inner()).1.as_secs()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn manual_clock_moves_only_when_advanced() {
+		let clock = ManualClock::new(1_000);
+		let shared = clock.clone();
+		let start = clock.now();
+		assert_eq!(clock.now(), start);
+
+		shared.advance(Duration::from_millis(1_500));
+		assert_eq!(clock.now() - start, Duration::from_millis(1_500));
+		assert_eq!(clock.unix(), 1_001);
+	}
+}
@@ -28,10 +28,13 @@ use frost::keys::dkg::round1::{Package as Round1Package, SecretPackage as Round1
 use frost::keys::dkg::round2::{Package as Round2Package, SecretPackage as Round2Secret};
 use frost::round1::{SigningCommitments, SigningNonces};
 use frost::round2::SignatureShare;
-use frost_ed25519::{self as frost, Identifier};
+use frost::{Ciphersuite, Identifier};
+use frost_core as frost;
+use frost_ed25519::Ed25519Sha512;
+use rand::{CryptoRng, RngCore};
 use scale_info::{build::Fields, Path, Type, TypeInfo};
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 /// Wrapper for DkgVerifyingKey, facilitating usage in different encoding contexts
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -75,59 +78,103 @@ impl Decode for WrapVerifyingKey {
 
 impl EncodeLike for WrapVerifyingKey {}
 
+/// A participant sent two different packages for the same part of a key generation round.
+///
+/// The second package is dropped, since processing whichever arrives could corrupt the result.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DkgEquivocation<C: Ciphersuite = Ed25519Sha512> {
+	/// The identifier of the equivocating participant.
+	pub from: Identifier<C>,
+	/// The part of the round, `1` or `2`, the packages belong to.
+	pub part: u8,
+}
+
+impl<C: Ciphersuite> std::fmt::Display for DkgEquivocation<C> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "Participant {:?} sent conflicting DKG Part{} packages", self.from, self.part)
+	}
+}
+
+impl<C: Ciphersuite> std::error::Error for DkgEquivocation<C> {}
+
 /// Two-round messages for key generation negotiation
 #[derive(Serialize, Deserialize, Clone)]
-pub enum DkgMessage {
-	DkgPart1(DkgPart1Message),
-	DkgPart2(DkgPart2Message),
+#[serde(bound = "")]
+pub enum DkgMessage<C: Ciphersuite = Ed25519Sha512> {
+	DkgPart1(DkgPart1Message<C>),
+	DkgPart2(DkgPart2Message<C>),
+	/// Part-1 package of a rehearsal, which participants answer with a `RehearsalReply`.
+	///
+	/// Rehearsal packages only test that participants can exchange part-1 messages, they are
+	/// never used to generate a key.
+	RehearsalPart1(DkgRehearsalMessage<C>),
+	/// Answer of a participant to a rehearsal, carrying the nonce of the rehearsal it answers.
+	RehearsalReply(DkgRehearsalMessage<C>),
 }
 
 /// Two-round messages for signature negotiation
 #[derive(Serialize, Deserialize, Clone)]
-pub enum SignMessage {
-	SignPart1(SignPart1Message),
-	SignPart2(SignPart2Message),
+#[serde(bound = "")]
+pub enum SignMessage<C: Ciphersuite = Ed25519Sha512> {
+	SignPart1(SignPart1Message<C>),
+	SignPart2(SignPart2Message<C>),
 }
 
 /// First-round message for key generation negotiation
 #[derive(Serialize, Deserialize, Clone)]
-pub struct DkgPart1Message {
+#[serde(bound = "")]
+pub struct DkgPart1Message<C: Ciphersuite = Ed25519Sha512> {
 	/// Identity identifier generated by the sender
-	pub id: Identifier,
+	pub id: Identifier<C>,
 	/// Generated Package
-	pub part1: Round1Package,
+	pub part1: Round1Package<C>,
+}
+
+/// Rehearsal message for key generation negotiation
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(bound = "")]
+pub struct DkgRehearsalMessage<C: Ciphersuite = Ed25519Sha512> {
+	/// Random value identifying the rehearsal, so that answers to other rehearsals are told apart
+	pub nonce: u64,
+	/// Identity identifier generated by the sender
+	pub id: Identifier<C>,
+	/// Generated Package
+	pub part1: Round1Package<C>,
 }
 
 /// Second-round message for key generation negotiation
 #[derive(Serialize, Deserialize, Clone)]
-pub struct DkgPart2Message {
+#[serde(bound = "")]
+pub struct DkgPart2Message<C: Ciphersuite = Ed25519Sha512> {
 	/// Identity identifier generated by the sender
-	pub id: Identifier,
+	pub id: Identifier<C>,
 	/// Generated Package, containing the packages generated by the creator for all participants except themselves
-	pub part2: BTreeMap<Identifier, Round2Package>,
+	pub part2: BTreeMap<Identifier<C>, Round2Package<C>>,
 }
 
 /// First-round message for signature negotiation
 #[derive(Serialize, Deserialize, Clone)]
-pub struct SignPart1Message {
+#[serde(bound = "")]
+pub struct SignPart1Message<C: Ciphersuite = Ed25519Sha512> {
 	/// Identity identifier generated by the sender
-	pub id: Identifier,
+	pub id: Identifier<C>,
 	/// Generated Commitment
-	pub part1: SigningCommitments,
+	pub part1: SigningCommitments<C>,
 }
 
 /// Second-round message for signature negotiation
 #[derive(Serialize, Deserialize, Clone)]
-pub struct SignPart2Message {
+#[serde(bound = "")]
+pub struct SignPart2Message<C: Ciphersuite = Ed25519Sha512> {
 	/// Identity identifier generated by the sender
-	pub id: Identifier,
+	pub id: Identifier<C>,
 	/// Generated SignatureShare
-	pub part2: SignatureShare,
+	pub part2: SignatureShare<C>,
 }
 
-pub struct DkgKeypair {
-	pub key: frost::keys::KeyPackage,
-	pub public: frost::keys::PublicKeyPackage,
+pub struct DkgKeypair<C: Ciphersuite = Ed25519Sha512> {
+	pub key: frost::keys::KeyPackage<C>,
+	pub public: frost::keys::PublicKeyPackage<C>,
 }
 
 /// Represents the state and functionality of a participant in the Frost Distributed Key Generation (DKG) protocol.
@@ -135,41 +182,62 @@ pub struct DkgKeypair {
 /// The `FrostDkg` struct encapsulates all necessary data and methods for a participant to engage in both the DKG process
 /// for generating a shared public key and the threshold signing process. It handles two-phase commit-reveal schemes for
 /// both key generation and signing, ensuring that the processes are carried out correctly and securely.
-pub struct FrostDkg {
+///
+/// `FrostDkg` runs any FROST ciphersuite `C`, and defaults to FROST(Ed25519, SHA-512), the one the
+/// validator network uses. The task pallet verifies signatures with `ed25519-consensus`, so it
+/// only accepts keys and signatures of the Ed25519 ciphersuite. Running another ciphersuite, such
+/// as Ristretto255 or secp256k1, also needs a matching verifier in the runtime.
+pub struct FrostDkg<C: Ciphersuite = Ed25519Sha512> {
 	// The current DKG keypair, if already generated
-	dkg_keypair: Option<DkgKeypair>,
+	dkg_keypair: Option<DkgKeypair<C>>,
 	// Stores round 2 packages from other participants in the DKG process
-	round2_packages: BTreeMap<Identifier, Round2Package>,
+	round2_packages: BTreeMap<Identifier<C>, Round2Package<C>>,
 	// TODO: Add coordinator support
 	#[allow(dead_code)]
 	is_coordinator: bool,
 	// Stores the round 1 secret, to be used in the next round
-	round1_secret: Option<Round1Secret>,
+	round1_secret: Option<Round1Secret<C>>,
 	// Stores the round 1 package, to be used in the next round
-	round1_package: Option<Round1Package>,
+	round1_package: Option<Round1Package<C>>,
 	// Stores round 1 packages from other participants
-	round1_packages: BTreeMap<Identifier, Round1Package>,
+	round1_packages: BTreeMap<Identifier<C>, Round1Package<C>>,
 	// Stores the round 2 secret, necessary to generate the final result
-	round2_secret: Option<Round2Secret>,
+	round2_secret: Option<Round2Secret<C>>,
 	// Threshold value 't' for the DKG and signing process
 	t: u16,
 	// Number of participants 'n' in the DKG and signing process
 	n: u16,
 	// Stores commitments for the signing negotiation
-	signing_commitments: BTreeMap<Identifier, SigningCommitments>,
+	signing_commitments: BTreeMap<Identifier<C>, SigningCommitments<C>>,
 	// Stores nonces for the first round of the signing process
-	sign_round1_nonce: Option<SigningNonces>,
+	sign_round1_nonce: Option<SigningNonces<C>>,
 	// Stores the package for the signing negotiation
-	signing_package: Option<frost::SigningPackage>,
+	signing_package: Option<frost::SigningPackage<C>>,
 	// Stores signature shares for the second round of the signing process
-	sign_round2_signature_shares: BTreeMap<Identifier, SignatureShare>,
+	sign_round2_signature_shares: BTreeMap<Identifier<C>, SignatureShare<C>>,
 	// Stores the message to be signed
 	sign_message: Vec<u8>,
 	// The identifier of the participant
-	id: Identifier,
+	id: Identifier<C>,
+	// The source of randomness for key generation and signing nonces
+	rng: Box<dyn DkgRng>,
+	// Participants caught sending conflicting DKG packages
+	equivocators: BTreeSet<Identifier<C>>,
 }
 
-impl FrostDkg {
+/// A cryptographically secure random number generator usable by `FrostDkg`.
+pub trait DkgRng: RngCore + CryptoRng + Send + Sync {}
+
+impl<R: RngCore + CryptoRng + Send + Sync> DkgRng for R {}
+
+// The errors of the FROST library and of `FrostDkg` are reported through `anyhow`, which needs
+// them to be `Send` and `Sync`.
+impl<C> FrostDkg<C>
+where
+	C: Ciphersuite + 'static,
+	frost::Error<C>: Send + Sync,
+	Identifier<C>: Send + Sync,
+{
 	/// Initializes a new `FrostDkg` instance with a given identifier.
 	///
 	/// The identifier uniquely represents a participant in the DKG and signing processes.
@@ -177,7 +245,21 @@ impl FrostDkg {
 	/// # Arguments
 	///
 	/// * `id` - The unique identifier for this participant.
-	pub fn new(id: Identifier) -> Self {
+	pub fn new(id: Identifier<C>) -> Self {
+		Self::new_with_rng(id, rand::rngs::OsRng)
+	}
+
+	/// Initializes a new `FrostDkg` instance drawing its randomness from `rng`.
+	///
+	/// This is meant for tests, where a seeded generator such as `StdRng::seed_from_u64` makes
+	/// key generation and signing reproducible. Nodes should use `new`, which uses the operating
+	/// system's generator.
+	///
+	/// # Arguments
+	///
+	/// * `id` - The unique identifier for this participant.
+	/// * `rng` - The random number generator to use.
+	pub fn new_with_rng(id: Identifier<C>, rng: impl DkgRng + 'static) -> Self {
 		Self {
 			dkg_keypair: None,
 			round2_packages: BTreeMap::new(),
@@ -194,9 +276,23 @@ impl FrostDkg {
 			sign_round2_signature_shares: BTreeMap::new(),
 			sign_message: vec![],
 			id,
+			rng: Box::new(rng),
+			equivocators: BTreeSet::new(),
 		}
 	}
 
+	/// Returns the identifier of this participant.
+	pub fn identifier(&self) -> Identifier<C> {
+		self.id
+	}
+
+	/// Returns the participants caught sending conflicting DKG packages.
+	///
+	/// They are candidates for removal from the validator set.
+	pub fn equivocators(&self) -> &BTreeSet<Identifier<C>> {
+		&self.equivocators
+	}
+
 	/// Sets the 'n' (number of participants) and 't' (threshold) values for the DKG instance.
 	///
 	/// This should be agreed upon by all participants before starting the DKG process.
@@ -218,6 +314,22 @@ impl FrostDkg {
 		Ok(())
 	}
 
+	/// Returns the context string that identifies the ciphersuite in the FROST specification,
+	/// such as `FROST-ED25519-SHA512-v1`.
+	pub fn ciphersuite(&self) -> &'static str {
+		C::ID
+	}
+
+	/// Returns the threshold `t` required to produce a signature.
+	pub fn threshold(&self) -> u16 {
+		self.t
+	}
+
+	/// Returns the number `n` of participants in the key generation.
+	pub fn participants(&self) -> u16 {
+		self.n
+	}
+
 	/// Prepares for signing by generating the first round of signing commitments.
 	///
 	/// This process should be triggered by some signal to ensure all participants start the process,
@@ -230,11 +342,11 @@ impl FrostDkg {
 	/// # Returns
 	///
 	/// Returns the first-round message of the signing negotiation, which should be sent only to the coordinator.
-	pub fn start_sign(&mut self, msg: &[u8]) -> Result<SignMessage> {
+	pub fn start_sign(&mut self, msg: &[u8]) -> Result<SignMessage<C>> {
 		if let Some(dkg_keypair) = &self.dkg_keypair {
 			self.sign_message = msg.to_vec();
 			let (nonce, commitment) =
-				frost::round1::commit(&dkg_keypair.key.signing_share(), &mut rand::rngs::OsRng);
+				frost::round1::commit(&dkg_keypair.key.signing_share(), &mut self.rng);
 
 			self.sign_round1_nonce = Some(nonce);
 
@@ -248,6 +360,20 @@ impl FrostDkg {
 		}
 	}
 
+	/// Returns the message of the current signing negotiation, or an empty slice if there is none.
+	pub fn sign_message(&self) -> &[u8] {
+		&self.sign_message
+	}
+
+	/// Discards the state of an in-progress signing negotiation.
+	pub fn abort_sign(&mut self) {
+		self.signing_commitments.clear();
+		self.sign_round1_nonce = None;
+		self.signing_package = None;
+		self.sign_round2_signature_shares.clear();
+		self.sign_message.clear();
+	}
+
 	/// Used by the coordinator to collect first-round messages from other participants, generate the second round
 	/// of signing messages, and broadcast them to other participants.
 	///
@@ -260,8 +386,8 @@ impl FrostDkg {
 	/// Returns the second-round message of the signing negotiation, which needs to be broadcast to all other participants.
 	pub fn sign_part1(
 		&mut self,
-		sign_part1_message: SignPart1Message,
-	) -> Result<Option<SignMessage>> {
+		sign_part1_message: SignPart1Message<C>,
+	) -> Result<Option<SignMessage<C>>> {
 		self.signing_commitments.insert(sign_part1_message.id, sign_part1_message.part1);
 
 		if self.signing_commitments.len() == self.n as usize {
@@ -300,8 +426,8 @@ impl FrostDkg {
 	/// penalties should be applied, along with their removal from the participant list.
 	pub fn sign_part2(
 		&mut self,
-		sign_part2_message: SignPart2Message,
-	) -> Result<Option<frost::Signature>> {
+		sign_part2_message: SignPart2Message<C>,
+	) -> Result<Option<frost::Signature<C>>> {
 		self.sign_round2_signature_shares
 			.insert(sign_part2_message.id, sign_part2_message.part2);
 
@@ -328,9 +454,18 @@ impl FrostDkg {
 	/// # Note
 	///
 	/// Care should be taken to ensure the security of the random numbers used.
-	pub fn start_dkg(&mut self) -> Result<DkgMessage> {
+	///
+	/// A key generation started earlier and left incomplete, for example because it was
+	/// superseded or a part-2 package never arrived, is discarded first, so that its packages are
+	/// not mistaken for equivocations of the new one. Part-1 packages received before any round
+	/// was started are kept, since they belong to the round being started.
+	pub fn start_dkg(&mut self) -> Result<DkgMessage<C>> {
+		if self.round1_secret.is_some() || self.round2_secret.is_some() {
+			self.abort_dkg();
+		}
+
 		let (round1_secret, round1_package) =
-			frost::keys::dkg::part1(self.id, self.n, self.t, &mut rand::rngs::OsRng)
+			frost::keys::dkg::part1(self.id, self.n, self.t, &mut *self.rng)
 				.context("Failed to generate DKG Part1 data")?;
 
 		self.round1_secret = Some(round1_secret);
@@ -339,19 +474,58 @@ impl FrostDkg {
 		Ok(DkgMessage::DkgPart1(DkgPart1Message { id: self.id, part1: round1_package }))
 	}
 
+	/// Generates a part-1 package for a new rehearsal of the key generation, tagged with a fresh
+	/// random nonce.
+	///
+	/// The package is generated like the one of `start_dkg`, but its secret is discarded and the
+	/// state of any key generation in progress is left untouched.
+	pub fn rehearsal_part1(&mut self) -> Result<DkgRehearsalMessage<C>> {
+		let nonce = self.rng.next_u64();
+		self.rehearsal_reply(nonce)
+	}
+
+	/// Generates a part-1 package answering the rehearsal tagged with `nonce`.
+	pub fn rehearsal_reply(&mut self, nonce: u64) -> Result<DkgRehearsalMessage<C>> {
+		let (_, round1_package) = frost::keys::dkg::part1(self.id, self.n, self.t, &mut *self.rng)
+			.context("Failed to generate DKG rehearsal data")?;
+
+		Ok(DkgRehearsalMessage { nonce, id: self.id, part1: round1_package })
+	}
+
+	/// Discards the state of an in-progress key generation.
+	///
+	/// The current keypair, if any, is kept so that signing keeps working with the previous key.
+	pub fn abort_dkg(&mut self) {
+		self.round1_secret = None;
+		self.round1_package = None;
+		self.round1_packages.clear();
+		self.round2_secret = None;
+		self.round2_packages.clear();
+	}
+
 	/// Processes the second round of key generation after receiving DKG Part2 messages from other participants.
 	/// Generates a DKG VerifyingKey and returns it.
 	pub fn dkg_part2(
 		&mut self,
-		dkg_part2_message: DkgPart2Message,
-	) -> Result<Option<DkgVerifyingKey>> {
+		dkg_part2_message: DkgPart2Message<C>,
+	) -> Result<Option<frost::VerifyingKey<C>>> {
 		if let Some(round2_secret) = &self.round2_secret {
 			let my_package = dkg_part2_message.part2.get(&self.id);
 
-			if let Some(my_package) = my_package {
-				self.round2_packages.insert(dkg_part2_message.id, my_package.clone());
-			} else {
+			let Some(my_package) = my_package else {
 				return Err(anyhow!("Missing own package in DKG Part2 processing"));
+			};
+
+			match self.round2_packages.get(&dkg_part2_message.id) {
+				// The same package delivered again.
+				Some(package) if package == my_package => return Ok(None),
+				Some(_) => {
+					self.equivocators.insert(dkg_part2_message.id);
+					return Err(DkgEquivocation { from: dkg_part2_message.id, part: 2 }.into())
+				},
+				None => {
+					self.round2_packages.insert(dkg_part2_message.id, my_package.clone());
+				},
 			}
 
 			if self.round2_packages.len() + 1 == self.n as usize {
@@ -364,6 +538,12 @@ impl FrostDkg {
 				self.dkg_keypair =
 					Some(DkgKeypair { key: key_package, public: public_key_package.clone() });
 
+				// The round is over, so packages of the next one are not mistaken for
+				// equivocations.
+				self.round2_secret = None;
+				self.round1_packages.clear();
+				self.round2_packages.clear();
+
 				return Ok(Some(public_key_package.verifying_key().clone()));
 			}
 
@@ -375,12 +555,25 @@ impl FrostDkg {
 
 	/// Processes the first round of key generation after receiving messages generated in `start_dkg()`.
 	/// Generates a DKG Part2 message and broadcasts it to participants.
-	pub fn dkg_part1(&mut self, dkg_part1_message: DkgPart1Message) -> Result<Option<DkgMessage>> {
+	pub fn dkg_part1(
+		&mut self,
+		dkg_part1_message: DkgPart1Message<C>,
+	) -> Result<Option<DkgMessage<C>>> {
 		if dkg_part1_message.id == self.id {
 			return Ok(None);
 		}
 
-		self.round1_packages.insert(dkg_part1_message.id, dkg_part1_message.part1);
+		match self.round1_packages.get(&dkg_part1_message.id) {
+			// The same package delivered again.
+			Some(package) if *package == dkg_part1_message.part1 => return Ok(None),
+			Some(_) => {
+				self.equivocators.insert(dkg_part1_message.id);
+				return Err(DkgEquivocation { from: dkg_part1_message.id, part: 1 }.into())
+			},
+			None => {
+				self.round1_packages.insert(dkg_part1_message.id, dkg_part1_message.part1);
+			},
+		}
 
 		if self.round1_packages.len() + 1 == self.n as usize {
 			if let Some(round1_secret) = &self.round1_secret {
@@ -408,6 +601,9 @@ impl FrostDkg {
 mod tests {
 	use super::*;
 	use ed25519_consensus::{Signature, VerificationKey};
+	use frost_ed25519::Identifier;
+	use frost_ristretto255::Ristretto255Sha512;
+	use rand::{rngs::StdRng, SeedableRng};
 	use std::collections::HashMap;
 
 	// Helper function to create a FrostDkg instance
@@ -430,7 +626,103 @@ mod tests {
 			sign_round2_signature_shares: BTreeMap::new(),
 			sign_message: vec![],
 			id,
+			rng: Box::new(rand::rngs::OsRng),
+			equivocators: BTreeSet::new(),
+		}
+	}
+
+	// Runs a key generation between `n` participants seeded from `seed` and returns the
+	// resulting group verifying key.
+	fn run_seeded_dkg(seed: u64, t: u16, n: u16) -> Result<DkgVerifyingKey> {
+		let ids = generate_identifiers(n);
+		let mut participants: Vec<FrostDkg> = ids
+			.iter()
+			.enumerate()
+			.map(|(i, &id)| {
+				let mut dkg = FrostDkg::new_with_rng(id, StdRng::seed_from_u64(seed + i as u64));
+				dkg.set_nt(n, t).unwrap();
+				dkg
+			})
+			.collect();
+
+		let mut part1_messages = Vec::new();
+		for participant in participants.iter_mut() {
+			if let DkgMessage::DkgPart1(msg) = participant.start_dkg()? {
+				part1_messages.push(msg);
+			}
+		}
+
+		let mut part2_messages = Vec::new();
+		for participant in participants.iter_mut() {
+			for msg in part1_messages.iter().filter(|msg| msg.id != participant.id) {
+				if let Some(DkgMessage::DkgPart2(msg)) = participant.dkg_part1(msg.clone())? {
+					part2_messages.push(msg);
+				}
+			}
+		}
+
+		for participant in participants.iter_mut() {
+			for msg in part2_messages.iter().filter(|msg| msg.id != participant.id) {
+				participant.dkg_part2(msg.clone())?;
+			}
+		}
+
+		let dkg_keypair = participants[0].dkg_keypair.as_ref().unwrap();
+		Ok(dkg_keypair.public.verifying_key().clone())
+	}
+
+	#[test]
+	fn dkg_completes_after_an_incomplete_round() -> Result<()> {
+		let ids = generate_identifiers(3);
+		let mut participants: Vec<FrostDkg> =
+			ids.iter().map(|&id| create_frost_dkg(id, 2, 3)).collect();
+
+		// The first round stops after part 1, so nobody receives any part-2 package.
+		let mut part1_messages = Vec::new();
+		for participant in participants.iter_mut() {
+			if let DkgMessage::DkgPart1(msg) = participant.start_dkg()? {
+				part1_messages.push(msg);
+			}
+		}
+		for participant in participants.iter_mut() {
+			for msg in part1_messages.iter().filter(|msg| msg.id != participant.id) {
+				participant.dkg_part1(msg.clone())?;
+			}
+		}
+
+		// The second round goes through, none of its packages being taken for an equivocation.
+		let mut part1_messages = Vec::new();
+		for participant in participants.iter_mut() {
+			if let DkgMessage::DkgPart1(msg) = participant.start_dkg()? {
+				part1_messages.push(msg);
+			}
+		}
+		let mut part2_messages = Vec::new();
+		for participant in participants.iter_mut() {
+			for msg in part1_messages.iter().filter(|msg| msg.id != participant.id) {
+				if let Some(DkgMessage::DkgPart2(msg)) = participant.dkg_part1(msg.clone())? {
+					part2_messages.push(msg);
+				}
+			}
 		}
+		let mut keys = Vec::new();
+		for participant in participants.iter_mut() {
+			for msg in part2_messages.iter().filter(|msg| msg.id != participant.id) {
+				keys.extend(participant.dkg_part2(msg.clone())?);
+			}
+			assert!(participant.equivocators().is_empty());
+		}
+
+		assert_eq!(keys.len(), 3);
+		assert!(keys.iter().all(|key| *key == keys[0]));
+		Ok(())
+	}
+
+	#[test]
+	fn seeded_dkg_is_reproducible() -> Result<()> {
+		assert_eq!(run_seeded_dkg(7, 2, 3)?, run_seeded_dkg(7, 2, 3)?);
+		assert_ne!(run_seeded_dkg(7, 2, 3)?, run_seeded_dkg(8, 2, 3)?);
+		Ok(())
 	}
 
 	#[test]
@@ -564,6 +856,200 @@ mod tests {
 		Ok(())
 	}
 
+	// Runs a key generation and then a signing round of `message` between `n` participants with
+	// the ciphersuite `C`, and returns the group verifying key with the signature of each
+	// participant.
+	fn dkg_and_sign<C>(
+		t: u16,
+		n: u16,
+		message: &[u8],
+	) -> Result<(frost::VerifyingKey<C>, Vec<frost::Signature<C>>)>
+	where
+		C: Ciphersuite + 'static,
+		frost::Error<C>: Send + Sync,
+		frost::Identifier<C>: Send + Sync,
+	{
+		let mut participants = (1..=n)
+			.map(|i| {
+				let mut dkg = FrostDkg::<C>::new(frost::Identifier::<C>::try_from(i)?);
+				dkg.set_nt(n, t)?;
+				Ok(dkg)
+			})
+			.collect::<Result<Vec<_>>>()?;
+
+		let mut part1_messages = Vec::new();
+		for participant in participants.iter_mut() {
+			if let DkgMessage::DkgPart1(msg) = participant.start_dkg()? {
+				part1_messages.push(msg);
+			}
+		}
+		let mut part2_messages = Vec::new();
+		for participant in participants.iter_mut() {
+			for msg in part1_messages.iter().filter(|msg| msg.id != participant.id) {
+				if let Some(DkgMessage::DkgPart2(msg)) = participant.dkg_part1(msg.clone())? {
+					part2_messages.push(msg);
+				}
+			}
+		}
+		let mut keys = Vec::new();
+		for participant in participants.iter_mut() {
+			for msg in part2_messages.iter().filter(|msg| msg.id != participant.id) {
+				keys.extend(participant.dkg_part2(msg.clone())?);
+			}
+		}
+		assert_eq!(keys.len(), n as usize);
+
+		let mut sign_part1_messages = Vec::new();
+		for participant in participants.iter_mut() {
+			if let SignMessage::SignPart1(msg) = participant.start_sign(message)? {
+				sign_part1_messages.push(msg);
+			}
+		}
+		let mut sign_part2_messages = Vec::new();
+		for participant in participants.iter_mut() {
+			for msg in sign_part1_messages.iter().filter(|msg| msg.id != participant.id) {
+				if let Some(SignMessage::SignPart2(msg)) = participant.sign_part1(msg.clone())? {
+					sign_part2_messages.push(msg);
+				}
+			}
+		}
+		let mut signatures = Vec::new();
+		for participant in participants.iter_mut() {
+			for msg in sign_part2_messages.iter().filter(|msg| msg.id != participant.id) {
+				signatures.extend(participant.sign_part2(msg.clone())?);
+			}
+		}
+
+		Ok((keys.remove(0), signatures))
+	}
+
+	#[test]
+	fn dkg_and_signing_run_with_another_ciphersuite() -> Result<()> {
+		let message = b"Test message";
+		let (key, signatures) = dkg_and_sign::<Ristretto255Sha512>(2, 3, message)?;
+
+		assert_eq!(signatures.len(), 3);
+		for signature in signatures {
+			key.verify(message, &signature)?;
+		}
+
+		// Each participant reports the ciphersuite it runs.
+		let id = frost::Identifier::<Ristretto255Sha512>::try_from(1)?;
+		assert_eq!(FrostDkg::new(id).ciphersuite(), "FROST-RISTRETTO255-SHA512-v1");
+		let ed25519 = create_frost_dkg(generate_identifiers(1)[0], 1, 1);
+		assert_eq!(ed25519.ciphersuite(), "FROST-ED25519-SHA512-v1");
+
+		Ok(())
+	}
+
+	#[test]
+	fn dkg_part2_yields_key_only_after_all_packages() -> Result<()> {
+		const T: u16 = 2;
+		const N: u16 = 3;
+
+		let ids = generate_identifiers(N);
+		let mut participants: HashMap<Identifier, FrostDkg> =
+			ids.iter().map(|&id| (id, create_frost_dkg(id, T, N))).collect();
+
+		let mut part1_messages = Vec::new();
+		for id in ids.iter() {
+			if let DkgMessage::DkgPart1(msg) = participants.get_mut(id).unwrap().start_dkg()? {
+				part1_messages.push(msg);
+			}
+		}
+
+		let mut part2_messages = Vec::new();
+		for msg in part1_messages.iter() {
+			for id in ids.iter() {
+				if let Some(DkgMessage::DkgPart2(part2)) =
+					participants.get_mut(id).unwrap().dkg_part1(msg.clone())?
+				{
+					part2_messages.push(part2);
+				}
+			}
+		}
+		assert_eq!(part2_messages.len(), N as usize);
+
+		let receiver = participants.get_mut(&ids[0]).unwrap();
+		let mut results = part2_messages
+			.into_iter()
+			.filter(|msg| msg.id != ids[0])
+			.map(|msg| receiver.dkg_part2(msg))
+			.collect::<Result<Vec<_>>>()?;
+
+		// The first package only advances the round, the last one completes it.
+		let last = results.pop().unwrap();
+		assert!(results.iter().all(|r| r.is_none()));
+		assert!(last.is_some());
+		assert_eq!(
+			last.unwrap(),
+			receiver.dkg_keypair.as_ref().unwrap().public.verifying_key().clone()
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn conflicting_dkg_part1_is_dropped_and_flagged() -> Result<()> {
+		let ids = generate_identifiers(3);
+		let mut receiver = create_frost_dkg(ids[0], 2, 3);
+		receiver.start_dkg()?;
+
+		let part1 = |id: Identifier| -> Result<DkgPart1Message> {
+			match create_frost_dkg(id, 2, 3).start_dkg()? {
+				DkgMessage::DkgPart1(msg) => Ok(msg),
+				_ => unreachable!(),
+			}
+		};
+		let first = part1(ids[1])?;
+		let conflicting = part1(ids[1])?;
+
+		assert!(receiver.dkg_part1(first.clone())?.is_none());
+		// Delivering the same package again is not an equivocation.
+		assert!(receiver.dkg_part1(first)?.is_none());
+
+		let error = receiver.dkg_part1(conflicting).unwrap_err();
+		assert_eq!(
+			error.downcast_ref::<DkgEquivocation>(),
+			Some(&DkgEquivocation { from: ids[1], part: 1 })
+		);
+		assert!(receiver.equivocators().contains(&ids[1]));
+		assert_eq!(receiver.round1_packages.len(), 1);
+
+		Ok(())
+	}
+
+	#[test]
+	fn rehearsal_leaves_dkg_in_progress_untouched() -> Result<()> {
+		let ids = generate_identifiers(3);
+		let mut dkg = create_frost_dkg(ids[0], 2, 3);
+		let DkgMessage::DkgPart1(started) = dkg.start_dkg()? else { unreachable!() };
+
+		let rehearsal = dkg.rehearsal_part1()?;
+		assert_eq!(rehearsal.id, ids[0]);
+		assert!(rehearsal.part1 != started.part1);
+		assert!(dkg.round1_package == Some(started.part1));
+		assert!(dkg.round1_secret.is_some());
+		assert!(dkg.round1_packages.is_empty());
+
+		Ok(())
+	}
+
+	#[test]
+	fn rehearsal_replies_carry_the_rehearsal_nonce() -> Result<()> {
+		let ids = generate_identifiers(2);
+		let mut initiator = create_frost_dkg(ids[0], 2, 2);
+		let mut participant = create_frost_dkg(ids[1], 2, 2);
+
+		let rehearsal = initiator.rehearsal_part1()?;
+		let reply = participant.rehearsal_reply(rehearsal.nonce)?;
+		assert_eq!(reply.nonce, rehearsal.nonce);
+		assert_eq!(reply.id, ids[1]);
+		assert!(initiator.rehearsal_part1()?.nonce != rehearsal.nonce);
+
+		Ok(())
+	}
+
 	// Helper function to generate identifiers
 	// This function generates a unique Identifier for each participant, based on a simple numeric sequence.
 	fn generate_identifiers(n: u16) -> Vec<Identifier> {
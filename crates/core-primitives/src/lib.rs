@@ -22,6 +22,12 @@ pub use frost_ed25519::Identifier;
 #[cfg(feature = "std")]
 pub use frost_ed25519::{Signature as DkgSignature, SigningKey, VerifyingKey as DkgVerifyingKey};
 
+#[cfg(feature = "std")]
+pub mod ciphersuite;
+
+#[cfg(feature = "std")]
+pub mod clock;
+
 #[cfg(feature = "std")]
 pub mod crypto;
 
@@ -12,7 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use codec::{Decode, Encode};
+use codec::{Compact, Decode, Encode};
+#[cfg(feature = "std")]
+use crate::{RelaySource, RuntimeSource, ValidatorsSource};
 use cumulus_primitives_core::relay_chain::ValidatorId;
 #[cfg(feature = "std")]
 use cumulus_primitives_core::BlockT;
@@ -43,6 +45,11 @@ impl ValidatorsInfo {
 		Self { set: set.to_vec() }
 	}
 
+	/// Returns the validators held by this instance.
+	pub fn validators(&self) -> &[ValidatorId] {
+		&self.set
+	}
+
 	/// Retrieves the set of validators from the database.
 	///
 	/// # Arguments
@@ -70,6 +77,58 @@ impl ValidatorsInfo {
 			.map(|set| Self { set })
 	}
 
+	/// Returns the number of validators stored in the database.
+	///
+	/// Only the length prefix of the stored set is decoded.
+	///
+	/// # Arguments
+	/// * `db` - A mutable reference to an object implementing the `DasKv` trait,
+	///          typically representing a database.
+	///
+	/// # Returns
+	/// The number of stored validators, `0` if no set is stored, or an error if the stored
+	/// data cannot be decoded.
+	pub fn len<DB>(&self, db: &mut DB) -> Result<usize, codec::Error>
+	where
+		DB: DasKv,
+	{
+		match db.get(STORE_KEY) {
+			Some(data) => Ok(Compact::<u32>::decode(&mut &data[..])?.0 as usize),
+			None => Ok(0),
+		}
+	}
+
+	/// Checks whether a validator is part of the set stored in the database.
+	///
+	/// The stored set is scanned in its encoded form, without decoding every validator.
+	///
+	/// # Arguments
+	/// * `db` - A mutable reference to an object implementing the `DasKv` trait,
+	///          typically representing a database.
+	/// * `id` - The validator to look for.
+	///
+	/// # Returns
+	/// `true` if the validator is stored, `false` if it is not or if no set is stored, or an
+	/// error if the stored data cannot be decoded.
+	pub fn contains<DB>(&self, db: &mut DB, id: &ValidatorId) -> Result<bool, codec::Error>
+	where
+		DB: DasKv,
+	{
+		let data = match db.get(STORE_KEY) {
+			Some(data) => data,
+			None => return Ok(false),
+		};
+
+		let mut input = &data[..];
+		let len = Compact::<u32>::decode(&mut input)?.0 as usize;
+		let needle = id.encode();
+		if input.len() != len * needle.len() {
+			return Err("Invalid validators set encoding".into())
+		}
+
+		Ok(input.chunks_exact(needle.len()).any(|encoded| encoded == &needle[..]))
+	}
+
 	/// Saves the current set of validators into the database.
 	///
 	/// # Arguments
@@ -138,6 +197,77 @@ impl ValidatorsInfo {
 		}
 	}
 
+	/// Updates the set of validators from the relay chain, falling back to the runtime.
+	///
+	/// The relay chain is queried at its best block first. If that fails, the set is read from
+	/// the runtime at `block_hash` instead. The source that succeeded is logged.
+	///
+	/// # Arguments
+	/// * `db` - A mutable reference to an object implementing the `DasKv` trait.
+	/// * `relay` - A shared reference to an object implementing `RelayChainInterface`.
+	/// * `runtime` - A shared reference to an object implementing `GetValidatorsFromRuntime`.
+	/// * `block_hash` - The hash of the block the runtime is read at.
+	///
+	/// # Returns
+	/// `true` if the set was updated from either source, `false` if both failed.
+	#[cfg(feature = "std")]
+	pub async fn update_best_effort<RCC, Runtime, DB, Block>(
+		&mut self,
+		db: &mut DB,
+		relay: &Arc<RCC>,
+		runtime: &Arc<Runtime>,
+		block_hash: Block::Hash,
+	) -> bool
+	where
+		RCC: RelayChainInterface + 'static,
+		Runtime: GetValidatorsFromRuntime<Block, ValidatorId> + Send + Sync + 'static,
+		DB: DasKv,
+		Block: BlockT,
+	{
+		let relay = RelaySource(relay.clone());
+		let runtime = RuntimeSource::<_, Block, _>::new(runtime.clone(), move || block_hash);
+		self.update_with_fallback(db, &relay, &runtime).await
+	}
+
+	// Updates the set from `relay`, or from `runtime` if that fails, and saves it.
+	#[cfg(feature = "std")]
+	async fn update_with_fallback<DB>(
+		&mut self,
+		db: &mut DB,
+		relay: &impl ValidatorsSource,
+		runtime: &impl ValidatorsSource,
+	) -> bool
+	where
+		DB: DasKv,
+	{
+		let validators = match relay.fetch().await {
+			Ok(validators) => {
+				log::debug!("Fetched the validator set from the relay chain");
+				validators
+			},
+			Err(e) => {
+				log::warn!(
+					"Failed to fetch validators from the relay chain: {}, trying the runtime",
+					e
+				);
+				match runtime.fetch().await {
+					Ok(validators) => {
+						log::debug!("Fetched the validator set from the runtime");
+						validators
+					},
+					Err(e) => {
+						log::warn!("Failed to fetch validators from the runtime: {}", e);
+						return false
+					},
+				}
+			},
+		};
+
+		self.set = validators;
+		self.save(db);
+		true
+	}
+
 	/// Gets a list of validators that have been removed compared to a previous set.
 	///
 	/// This method compares the current set of validators with a provided set,
@@ -176,3 +306,75 @@ impl ValidatorsInfo {
 			.collect::<Vec<_>>()
 	}
 }
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+	use super::*;
+	use crate::mock::{validator, MemoryDb, ScriptedSource};
+
+	fn stored(set: &[ValidatorId]) -> MemoryDb {
+		let mut db = MemoryDb::default();
+		ValidatorsInfo::new(set).save(&mut db);
+		db
+	}
+
+	#[test]
+	fn empty_db_has_no_validators() {
+		let info = ValidatorsInfo::new(&[]);
+		let mut db = MemoryDb::default();
+		assert_eq!(info.len(&mut db).unwrap(), 0);
+		assert!(!info.contains(&mut db, &validator(1)).unwrap());
+	}
+
+	#[test]
+	fn stored_validators_are_counted_and_found() {
+		let info = ValidatorsInfo::new(&[]);
+		let mut db = stored(&[validator(1), validator(2), validator(3)]);
+		assert_eq!(info.len(&mut db).unwrap(), 3);
+		assert!(info.contains(&mut db, &validator(2)).unwrap());
+		assert!(!info.contains(&mut db, &validator(4)).unwrap());
+	}
+
+	#[test]
+	fn corrupted_set_is_an_error() {
+		let info = ValidatorsInfo::new(&[]);
+
+		// A length prefix whose bytes are missing.
+		let mut db = MemoryDb::default();
+		db.set(STORE_KEY, &[0xff]);
+		assert!(info.len(&mut db).is_err());
+		assert!(info.contains(&mut db, &validator(1)).is_err());
+
+		// Two validators announced, but only part of one stored.
+		let mut data = Compact(2u32).encode();
+		data.extend_from_slice(&[1; 40]);
+		db.set(STORE_KEY, &data);
+		assert_eq!(info.len(&mut db).unwrap(), 2);
+		assert!(info.contains(&mut db, &validator(1)).is_err());
+	}
+
+	#[tokio::test]
+	async fn runtime_is_used_when_the_relay_chain_fails() {
+		let set = vec![validator(1), validator(2)];
+		let relay = ScriptedSource::new([Err("relay chain unavailable")]);
+		let runtime = ScriptedSource::new([Ok(set.clone())]);
+		let mut info = ValidatorsInfo::new(&[validator(9)]);
+		let mut db = MemoryDb::default();
+
+		assert!(info.update_with_fallback(&mut db, &relay, &runtime).await);
+		assert_eq!(info.validators(), &set[..]);
+		assert_eq!(info.get(&mut db), Some(set));
+	}
+
+	#[tokio::test]
+	async fn set_is_kept_when_both_sources_fail() {
+		let relay = ScriptedSource::new([Err("relay chain unavailable")]);
+		let runtime = ScriptedSource::new([Err("runtime unavailable")]);
+		let mut info = ValidatorsInfo::new(&[validator(9)]);
+		let mut db = MemoryDb::default();
+
+		assert!(!info.update_with_fallback(&mut db, &relay, &runtime).await);
+		assert_eq!(info.validators(), &[validator(9)]);
+		assert_eq!(info.get(&mut db), None);
+	}
+}
@@ -47,9 +47,28 @@
 //! let validators = validators_info.get(&mut db).unwrap();
 //! validators_info.save(&mut db);
 //! ```
+//!
+//! To keep the stored set up to date without a polling loop of your own, spawn a refresher. It
+//! sends a `ValidatorsChange` every time the set changes:
+//!
+//! ```rust,ignore
+//! let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+//! let handle = spawn_refresher(db, RelaySource(relay_client), Duration::from_secs(60), tx);
+//! while let Some(change) = rx.recv().await {
+//!     // Handle `change.added` and `change.removed`.
+//! }
+//! ```
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
 mod info;
+#[cfg(all(test, feature = "std"))]
+mod mock;
+#[cfg(feature = "std")]
+mod refresh;
 
-pub use info::ValidatorsInfo;
\ No newline at end of file
+pub use info::ValidatorsInfo;
+#[cfg(feature = "std")]
+pub use refresh::{
+	spawn_refresher, RelaySource, RuntimeSource, SourceError, ValidatorsChange, ValidatorsSource,
+};
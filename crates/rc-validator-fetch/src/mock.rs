@@ -0,0 +1,78 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Database and validator set sources shared by the tests.
+
+use crate::{SourceError, ValidatorsSource};
+use codec::Decode;
+use cumulus_primitives_core::relay_chain::ValidatorId;
+use melo_das_db::traits::DasKv;
+use std::{
+	collections::{HashMap, VecDeque},
+	sync::{Arc, Mutex},
+};
+
+// An in-memory database, shared by its clones.
+#[derive(Clone, Default)]
+pub(crate) struct MemoryDb(Arc<Mutex<HashMap<Vec<u8>, Vec<u8>>>>);
+
+impl DasKv for MemoryDb {
+	fn get(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+		self.0.lock().unwrap().get(key).cloned()
+	}
+
+	fn set(&mut self, key: &[u8], value: &[u8]) {
+		self.0.lock().unwrap().insert(key.to_vec(), value.to_vec());
+	}
+
+	fn remove(&mut self, key: &[u8]) {
+		self.0.lock().unwrap().remove(key);
+	}
+
+	fn contains(&mut self, key: &[u8]) -> bool {
+		self.0.lock().unwrap().contains_key(key)
+	}
+
+	fn compare_and_set(&mut self, key: &[u8], old: Option<&[u8]>, new: &[u8]) -> bool {
+		let mut values = self.0.lock().unwrap();
+		if values.get(key).map(|value| value.as_slice()) != old {
+			return false
+		}
+		values.insert(key.to_vec(), new.to_vec());
+		true
+	}
+}
+
+// A source returning its queued results in order, then the last one on every fetch.
+pub(crate) struct ScriptedSource(Mutex<VecDeque<Result<Vec<ValidatorId>, &'static str>>>);
+
+impl ScriptedSource {
+	pub fn new(results: impl IntoIterator<Item = Result<Vec<ValidatorId>, &'static str>>) -> Self {
+		Self(Mutex::new(results.into_iter().collect()))
+	}
+}
+
+#[async_trait::async_trait]
+impl ValidatorsSource for ScriptedSource {
+	async fn fetch(&self) -> Result<Vec<ValidatorId>, SourceError> {
+		let mut results = self.0.lock().unwrap();
+		let result =
+			if results.len() > 1 { results.pop_front() } else { results.front().cloned() };
+		result.expect("no result queued").map_err(Into::into)
+	}
+}
+
+pub(crate) fn validator(seed: u8) -> ValidatorId {
+	ValidatorId::decode(&mut &[seed; 32][..]).unwrap()
+}
@@ -0,0 +1,169 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::ValidatorsInfo;
+use cumulus_primitives_core::{relay_chain::ValidatorId, BlockT};
+use cumulus_relay_chain_interface::RelayChainInterface;
+use melo_das_db::traits::DasKv;
+use redot_core_primitives::GetValidatorsFromRuntime;
+use std::{marker::PhantomData, sync::Arc, time::Duration};
+use tokio::{sync::mpsc::UnboundedSender, task::JoinHandle, time::MissedTickBehavior};
+
+/// Error returned by a [`ValidatorsSource`].
+pub type SourceError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A source the validator set can be fetched from.
+#[async_trait::async_trait]
+pub trait ValidatorsSource: Send + Sync + 'static {
+	/// Fetches the current validator set.
+	async fn fetch(&self) -> Result<Vec<ValidatorId>, SourceError>;
+}
+
+/// Fetches the validator set at the best block of the relay chain.
+pub struct RelaySource<RCC>(pub Arc<RCC>);
+
+#[async_trait::async_trait]
+impl<RCC> ValidatorsSource for RelaySource<RCC>
+where
+	RCC: RelayChainInterface + 'static,
+{
+	async fn fetch(&self) -> Result<Vec<ValidatorId>, SourceError> {
+		let hash = self.0.best_block_hash().await?;
+		Ok(self.0.validators(hash).await?)
+	}
+}
+
+/// Fetches the validator set from the runtime, at the block hash returned by `best_hash`.
+pub struct RuntimeSource<Runtime, Block, F> {
+	runtime: Arc<Runtime>,
+	best_hash: F,
+	_block: PhantomData<fn() -> Block>,
+}
+
+impl<Runtime, Block, F> RuntimeSource<Runtime, Block, F> {
+	/// Creates a source reading the runtime at the block picked by `best_hash` on every fetch.
+	pub fn new(runtime: Arc<Runtime>, best_hash: F) -> Self {
+		Self { runtime, best_hash, _block: PhantomData }
+	}
+}
+
+#[async_trait::async_trait]
+impl<Runtime, Block, F> ValidatorsSource for RuntimeSource<Runtime, Block, F>
+where
+	Runtime: GetValidatorsFromRuntime<Block, ValidatorId> + Send + Sync + 'static,
+	Block: BlockT,
+	F: Fn() -> Block::Hash + Send + Sync + 'static,
+{
+	async fn fetch(&self) -> Result<Vec<ValidatorId>, SourceError> {
+		Ok(self.runtime.validators((self.best_hash)())?)
+	}
+}
+
+/// Changes between two consecutive validator sets.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ValidatorsChange {
+	/// Validators that joined the set.
+	pub added: Vec<ValidatorId>,
+	/// Validators that left the set.
+	pub removed: Vec<ValidatorId>,
+}
+
+impl ValidatorsChange {
+	/// Computes the change from the `old` set to the `new` one.
+	pub fn between(old: &[ValidatorId], new: &[ValidatorId]) -> Self {
+		let info = ValidatorsInfo::new(new);
+		Self { added: info.get_new_validators(old), removed: info.get_removed_validators(old) }
+	}
+
+	/// Whether the sets are the same.
+	pub fn is_empty(&self) -> bool {
+		self.added.is_empty() && self.removed.is_empty()
+	}
+}
+
+/// Spawns a task that re-fetches the validator set from `source` every `interval`.
+///
+/// The task starts from the set stored in `db`, saves every set that differs from the last one
+/// and sends the difference to `changes`. Failed fetches are logged and retried at the next tick.
+/// The task ends once the receiving side of `changes` is dropped.
+///
+/// Must be called from within a Tokio runtime.
+pub fn spawn_refresher<DB, S>(
+	mut db: DB,
+	source: S,
+	interval: Duration,
+	changes: UnboundedSender<ValidatorsChange>,
+) -> JoinHandle<()>
+where
+	DB: DasKv + Send + 'static,
+	S: ValidatorsSource,
+{
+	tokio::spawn(async move {
+		let mut info = ValidatorsInfo::from_db(&mut db).unwrap_or_else(|| ValidatorsInfo::new(&[]));
+		let mut ticker = tokio::time::interval(interval);
+		ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+		loop {
+			ticker.tick().await;
+			if changes.is_closed() {
+				break
+			}
+
+			let set = match source.fetch().await {
+				Ok(set) => set,
+				Err(e) => {
+					log::warn!("Failed to fetch the validator set: {}", e);
+					continue
+				},
+			};
+
+			let change = ValidatorsChange::between(info.validators(), &set);
+			if change.is_empty() {
+				continue
+			}
+
+			info = ValidatorsInfo::new(&set);
+			info.save(&mut db);
+			if changes.send(change).is_err() {
+				break
+			}
+		}
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::{validator, MemoryDb, ScriptedSource};
+	use tokio::sync::mpsc;
+
+	#[tokio::test(start_paused = true)]
+	async fn refresher_keeps_running_after_a_failed_fetch() {
+		let set = vec![validator(1), validator(2)];
+		let source = ScriptedSource::new([Err("relay chain unavailable"), Ok(set.clone())]);
+		let mut db = MemoryDb::default();
+		let (changes_tx, mut changes) = mpsc::unbounded_channel();
+		let handle = spawn_refresher(db.clone(), source, Duration::from_secs(60), changes_tx);
+
+		// The first tick fails, and the next one emits the new set.
+		let change = changes.recv().await.unwrap();
+		assert_eq!(change, ValidatorsChange { added: set.clone(), removed: vec![] });
+		assert_eq!(ValidatorsInfo::from_db(&mut db).unwrap().validators(), &set[..]);
+		assert!(!handle.is_finished());
+
+		// Once the receiver is dropped, the task ends at its next tick.
+		drop(changes);
+		handle.await.unwrap();
+	}
+}
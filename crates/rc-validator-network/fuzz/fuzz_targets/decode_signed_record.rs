@@ -0,0 +1,30 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Feeds arbitrary bytes to the validator record decoder, and checks that every record it accepts
+//! holds only valid addresses.
+//!
+//! Run with `cargo fuzz run decode_signed_record` from `crates/rc-validator-network`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rc_validator_network::SignedValidatorRecord;
+
+fuzz_target!(|data: &[u8]| {
+	if let Ok(record) = SignedValidatorRecord::decode_checked(data) {
+		record.addresses().expect("accepted a record holding an invalid address");
+		let _ = record.verify_signature();
+	}
+});
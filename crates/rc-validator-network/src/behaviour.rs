@@ -39,6 +39,8 @@ pub struct BehaviorConfig {
 	pub kademlia: KademliaConfig,
 	/// The configuration for the [`kad_store`] behaviour.
 	pub kad_store: MemoryStore,
+	/// The configuration for the [`Gossipsub`] behaviour.
+	pub gossipsub: GossipsubConfig,
 }
 
 /// The [`NetworkBehaviour`] of the Validator Network.
@@ -65,10 +67,9 @@ impl Behavior {
 
 		let kademlia = Kademlia::with_config(config.peer_id, config.kad_store, config.kademlia);
 
-		let gossipsub_config = GossipsubConfig::default();
 		let gossipsub =
-			Gossipsub::new(MessageAuthenticity::Signed(keypair.clone()), gossipsub_config)
-				.expect("Correct Gossipsub configuration");
+			Gossipsub::new(MessageAuthenticity::Signed(keypair.clone()), config.gossipsub)
+				.map_err(|e| anyhow::anyhow!("Failed to create Gossipsub: {}", e))?;
 
 		Ok(Self {
 			identify: Identify::new(config.identify),
@@ -0,0 +1,169 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Bounded queue of outbound validator dials.
+//!
+//! When a large validator set is added at once, dialing every validator immediately causes a
+//! connection storm. The `DialQueue` keeps at most `limit` dials in flight and releases the next
+//! ones as earlier dials succeed or fail.
+
+use libp2p::{Multiaddr, PeerId};
+use std::collections::{HashSet, VecDeque};
+
+/// A queue that caps the number of simultaneous outbound dials.
+#[derive(Debug)]
+pub(crate) struct DialQueue {
+	limit: usize,
+	pending: VecDeque<(PeerId, Vec<Multiaddr>)>,
+	in_flight: HashSet<PeerId>,
+}
+
+impl DialQueue {
+	/// Creates a queue allowing at most `limit` dials in flight. A limit of zero is treated as one.
+	pub fn new(limit: usize) -> Self {
+		Self { limit: limit.max(1), pending: VecDeque::new(), in_flight: HashSet::new() }
+	}
+
+	/// Queues a dial to `peer_id`. Peers that are already queued or being dialed are ignored.
+	pub fn push(&mut self, peer_id: PeerId, addresses: Vec<Multiaddr>) {
+		if self.in_flight.contains(&peer_id) || self.pending.iter().any(|(p, _)| *p == peer_id) {
+			return
+		}
+		self.pending.push_back((peer_id, addresses));
+	}
+
+	/// Returns the dials that can start now and marks them as in flight.
+	pub fn next_dials(&mut self) -> Vec<(PeerId, Vec<Multiaddr>)> {
+		let mut dials = Vec::new();
+		while self.in_flight.len() < self.limit {
+			match self.pending.pop_front() {
+				Some((peer_id, addresses)) => {
+					self.in_flight.insert(peer_id);
+					dials.push((peer_id, addresses));
+				},
+				None => break,
+			}
+		}
+		dials
+	}
+
+	/// Marks the dial to `peer_id` as finished, whether it succeeded or failed.
+	///
+	/// Returns `true` if the dial was in flight.
+	pub fn finish(&mut self, peer_id: &PeerId) -> bool {
+		self.in_flight.remove(peer_id)
+	}
+
+	/// Drops the queued dial to `peer_id`, if any.
+	///
+	/// A dial already in flight keeps counting towards the limit until it finishes.
+	pub fn remove(&mut self, peer_id: &PeerId) {
+		self.pending.retain(|(p, _)| p != peer_id);
+	}
+
+	/// Drops every queued dial.
+	///
+	/// Dials already in flight keep counting towards the limit until they finish.
+	pub fn clear(&mut self) {
+		self.pending.clear();
+	}
+
+	/// Returns the number of dials in flight.
+	pub fn in_flight(&self) -> usize {
+		self.in_flight.len()
+	}
+
+	/// Returns the number of queued dials waiting for a free slot.
+	pub fn pending(&self) -> usize {
+		self.pending.len()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn in_flight_dials_never_exceed_limit() {
+		let limit = 3;
+		let mut queue = DialQueue::new(limit);
+		let peers = (0..10).map(|_| PeerId::random()).collect::<Vec<_>>();
+		for peer_id in peers.iter() {
+			queue.push(*peer_id, vec![]);
+		}
+
+		let mut dialed = Vec::new();
+		let mut started = queue.next_dials();
+		while !started.is_empty() {
+			assert!(queue.in_flight() <= limit);
+			assert!(started.len() <= limit);
+
+			// Complete one dial at a time and release the next ones.
+			let (peer_id, _) = started.remove(0);
+			assert!(queue.finish(&peer_id));
+			dialed.push(peer_id);
+
+			started.extend(queue.next_dials());
+			assert!(queue.in_flight() <= limit);
+		}
+
+		assert_eq!(dialed.len(), peers.len());
+		assert_eq!(queue.in_flight(), 0);
+	}
+
+	#[test]
+	fn duplicate_peers_are_dialed_once() {
+		let mut queue = DialQueue::new(2);
+		let peer_id = PeerId::random();
+
+		queue.push(peer_id, vec![]);
+		queue.push(peer_id, vec![]);
+		assert_eq!(queue.next_dials().len(), 1);
+
+		queue.push(peer_id, vec![]);
+		assert!(queue.next_dials().is_empty());
+	}
+
+	#[test]
+	fn removed_peers_are_not_dialed() {
+		let mut queue = DialQueue::new(1);
+		let (first, second) = (PeerId::random(), PeerId::random());
+
+		queue.push(first, vec![]);
+		queue.push(second, vec![]);
+		assert_eq!(queue.next_dials().len(), 1);
+
+		queue.remove(&first);
+		queue.remove(&second);
+		assert_eq!(queue.in_flight(), 1);
+		assert!(queue.finish(&first));
+		assert!(queue.next_dials().is_empty());
+	}
+
+	#[test]
+	fn clear_drops_queued_dials_only() {
+		let mut queue = DialQueue::new(1);
+		let (first, second) = (PeerId::random(), PeerId::random());
+
+		queue.push(first, vec![]);
+		queue.push(second, vec![]);
+		assert_eq!(queue.next_dials().len(), 1);
+
+		queue.clear();
+		assert_eq!(queue.pending(), 0);
+		assert_eq!(queue.in_flight(), 1);
+		assert!(queue.finish(&first));
+		assert!(queue.next_dials().is_empty());
+	}
+}
@@ -19,14 +19,34 @@
 //! maintaining a cache of validator addresses, and associating peer IDs with validators.
 
 use crate::KademliaKey;
-use anyhow::{anyhow, Result};
-use codec::{Decode, Encode};
+use anyhow::{anyhow, ensure, Result};
+use codec::{Decode, DecodeAll, Encode};
 use cumulus_primitives_core::relay_chain::ValidatorId;
 use libp2p::{multiaddr::Protocol, multihash::MultihashDigest, Multiaddr, PeerId};
+use redot_core_primitives::clock::{Clock, SystemClock};
 use sp_authority_discovery::{AuthorityId, AuthorityPair, AuthoritySignature};
 use sp_core::crypto::{key_types, ByteArray, Pair};
 use sp_keystore::Keystore;
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Maximum size of an encoded `SignedValidatorRecord` accepted from the network.
+pub const MAX_ENCODED_RECORD_LEN: usize = 16 * 1024;
+/// Maximum number of addresses in a `SignedValidatorRecord`.
+pub const MAX_RECORD_ADDRESSES: usize = 32;
+/// Maximum length of a single encoded address in a `SignedValidatorRecord`.
+pub const MAX_ADDRESS_LEN: usize = 256;
+/// Length of an encoded sr25519 signature.
+const SIGNATURE_LEN: usize = 64;
+/// Default maximum number of validators held by an `AddrCache`.
+pub const DEFAULT_ADDR_CACHE_CAPACITY: usize = 1024;
+/// Default time after which the addresses of an untracked validator expire, the default time to
+/// live of Kademlia records.
+pub const DEFAULT_ADDR_CACHE_TTL: Duration = Duration::from_secs(36 * 60 * 60);
 
 /// A signed record containing information about a validator.
 ///
@@ -51,6 +71,79 @@ impl SignedValidatorRecord {
         KademliaKey::new(&libp2p::multihash::Code::Sha2_256.digest(validator_id.as_ref()).digest())
     }
 
+    /// Decodes a record received from the network, enforcing size bounds.
+    ///
+    /// The input length is checked before decoding so that malformed length prefixes cannot
+    /// cause large allocations, and the decoded record is rejected if it holds too many
+    /// addresses, an oversized address, an address that is not a valid `Multiaddr` or a
+    /// signature of the wrong length. Trailing bytes are not allowed.
+    ///
+    /// # Arguments
+    /// * `bytes` - The encoded record.
+    ///
+    /// # Returns
+    /// The decoded record, or an error describing which bound was violated.
+    pub fn decode_checked(bytes: &[u8]) -> Result<Self> {
+        ensure!(
+            bytes.len() <= MAX_ENCODED_RECORD_LEN,
+            "Record too large: {} bytes, max {}",
+            bytes.len(),
+            MAX_ENCODED_RECORD_LEN
+        );
+
+        let record = Self::decode_all(&mut &bytes[..])
+            .map_err(|e| anyhow!("Failed to decode validator record: {}", e))?;
+
+        ensure!(
+            record.record.len() <= MAX_RECORD_ADDRESSES,
+            "Too many addresses in record: {}, max {}",
+            record.record.len(),
+            MAX_RECORD_ADDRESSES
+        );
+        ensure!(
+            record.record.iter().all(|address| address.len() <= MAX_ADDRESS_LEN),
+            "Address in record exceeds {} bytes",
+            MAX_ADDRESS_LEN
+        );
+        record.addresses()?;
+        ensure!(
+            record.auth_signature.len() == SIGNATURE_LEN,
+            "Invalid signature length: {}, expected {}",
+            record.auth_signature.len(),
+            SIGNATURE_LEN
+        );
+
+        Ok(record)
+    }
+
+    /// Parses the addresses held by the record.
+    ///
+    /// # Returns
+    /// The addresses of the record, or an error if any of them is not a valid `Multiaddr`.
+    pub fn addresses(&self) -> Result<Vec<Multiaddr>> {
+        self.record
+            .iter()
+            .map(|bytes| {
+                Multiaddr::try_from(bytes.clone())
+                    .map_err(|e| anyhow!("Invalid address in record: {}", e))
+            })
+            .collect()
+    }
+
+    /// Checks whether the record is stored under the key derived from its own validator ID.
+    ///
+    /// Records found under any other key must be rejected, otherwise a peer could publish a
+    /// record for one validator under the key of another.
+    ///
+    /// # Arguments
+    /// * `key` - The `KademliaKey` the record was fetched under.
+    ///
+    /// # Returns
+    /// `true` if the key matches the record's validator ID, `false` otherwise.
+    pub fn key_matches(&self, key: &KademliaKey) -> bool {
+        Self::key(&self.validator_id) == *key
+    }
+
     /// Verifies the signature of the record.
     ///
     /// This method checks if the stored signature is valid for the serialized record
@@ -59,10 +152,14 @@ impl SignedValidatorRecord {
     /// # Returns
     /// `true` if the signature is valid, `false` otherwise.
     pub fn verify_signature(&self) -> bool {
-        let signature = AuthoritySignature::decode(&mut self.auth_signature.as_slice())
-            .expect("Decode signature failed");
-        let public_key = AuthorityId::from_slice(self.validator_id.as_slice())
-            .expect("Decode public key failed");
+        let signature = match AuthoritySignature::decode(&mut self.auth_signature.as_slice()) {
+            Ok(signature) => signature,
+            Err(_) => return false,
+        };
+        let public_key = match AuthorityId::from_slice(self.validator_id.as_slice()) {
+            Ok(public_key) => public_key,
+            Err(_) => return false,
+        };
 
         let message = self.record.iter().flat_map(|v| v.iter()).cloned().collect::<Vec<u8>>();
 
@@ -77,12 +174,16 @@ impl SignedValidatorRecord {
     ///
     /// # Returns
     /// A `Result` containing a vector of tuples, each consisting of a `SignedValidatorRecord` and its corresponding Kademlia key,
-    /// or an error if the signing fails.
+    /// or an error if the signing fails or the keystore holds no authority discovery key.
     pub fn sign_record(
         key_store: &dyn Keystore,
         serialized_record: Vec<Vec<u8>>,
     ) -> Result<Vec<(Self, Vec<u8>)>> {
         let keys = key_store.sr25519_public_keys(key_types::AUTHORITY_DISCOVERY);
+        ensure!(
+            !keys.is_empty(),
+            "No authority discovery key in the keystore, no address record can be signed"
+        );
 
         let mut signed_records = Vec::new();
 
@@ -114,28 +215,118 @@ impl SignedValidatorRecord {
 ///
 /// This structure maintains mappings between validators' IDs and their associated network addresses,
 /// as well as the reverse mapping from peer IDs to validators.
-#[derive(Clone, Debug)]
+///
+/// The cache holds at most `capacity` validators. Beyond that, the least recently updated
+/// validator that is not tracked is evicted. Validators that are not tracked also expire once
+/// their addresses were not updated for `ttl`, according to the clock of the cache. Tracked
+/// validators, the ones the network was asked to connect to, are never evicted, so the cache may
+/// exceed its capacity if all of them are.
+#[derive(Clone)]
 pub struct AddrCache {
     authority_id_to_addresses: HashMap<ValidatorId, HashSet<Multiaddr>>,
     peer_id_to_authority_ids: HashMap<PeerId, HashSet<ValidatorId>>,
+    tracked: HashSet<ValidatorId>,
+    // Update counter and time of the last update of each validator.
+    last_updated: HashMap<ValidatorId, (u64, Instant)>,
+    next_update: u64,
+    capacity: usize,
+    ttl: Option<Duration>,
+    clock: Arc<dyn Clock>,
+}
+
+impl fmt::Debug for AddrCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AddrCache")
+            .field("authority_id_to_addresses", &self.authority_id_to_addresses)
+            .field("peer_id_to_authority_ids", &self.peer_id_to_authority_ids)
+            .field("tracked", &self.tracked)
+            .field("capacity", &self.capacity)
+            .field("ttl", &self.ttl)
+            .finish_non_exhaustive()
+    }
 }
 
 impl AddrCache {
-    /// Creates a new empty `AddrCache`.
+    /// Creates a new empty `AddrCache` holding up to `DEFAULT_ADDR_CACHE_CAPACITY` validators.
     ///
     /// # Returns
     /// A new instance of `AddrCache`.
     pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_ADDR_CACHE_CAPACITY)
+    }
+
+    /// Creates a new empty `AddrCache` holding up to `capacity` validators.
+    ///
+    /// # Arguments
+    /// * `capacity` - The maximum number of untracked validators kept in the cache.
+    ///
+    /// # Returns
+    /// A new instance of `AddrCache`.
+    pub fn with_capacity(capacity: usize) -> Self {
         AddrCache {
             authority_id_to_addresses: HashMap::new(),
             peer_id_to_authority_ids: HashMap::new(),
+            tracked: HashSet::new(),
+            last_updated: HashMap::new(),
+            next_update: 0,
+            capacity,
+            ttl: Some(DEFAULT_ADDR_CACHE_TTL),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Changes the capacity of the cache, evicting validators if it is now exceeded.
+    ///
+    /// # Arguments
+    /// * `capacity` - The maximum number of untracked validators kept in the cache.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict();
+    }
+
+    /// Changes the time after which untracked validators expire, evicting the expired ones.
+    ///
+    /// # Arguments
+    /// * `ttl` - How long the addresses of an untracked validator are kept after their last
+    ///   update, or `None` to keep them until they are evicted for capacity.
+    pub fn set_ttl(&mut self, ttl: Option<Duration>) {
+        self.ttl = ttl;
+        self.evict();
+    }
+
+    /// Replaces the clock used to expire validators.
+    ///
+    /// Caches use the system clock by default. Tests can pass a `ManualClock` to expire
+    /// validators without waiting.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Marks validators as tracked, so that they are never evicted.
+    ///
+    /// # Arguments
+    /// * `validators` - The IDs of the validators to track.
+    pub fn track_validators(&mut self, validators: &[ValidatorId]) {
+        self.tracked.extend(validators.iter().cloned());
+    }
+
+    /// Stops tracking validators, making them candidates for eviction again.
+    ///
+    /// # Arguments
+    /// * `validators` - The IDs of the validators to stop tracking.
+    pub fn untrack_validators(&mut self, validators: &[ValidatorId]) {
+        for validator_id in validators {
+            self.tracked.remove(validator_id);
         }
+        self.evict();
     }
 
     /// Adds a validator's addresses to the cache.
     ///
-    /// This method updates the cache with the addresses associated with a given validator ID.
-    /// It also updates the reverse mapping from new peer IDs to the validator ID.
+    /// This method replaces the addresses associated with a given validator ID. It also updates
+    /// the reverse mapping: peers the validator no longer advertises are unmapped from it, and
+    /// peers left with no validator are dropped, before new peers are mapped to it. A peer that
+    /// appears in both the old and the new addresses therefore stays mapped throughout.
     ///
     /// # Arguments
     /// * `validator_id` - The ID of the validator.
@@ -153,6 +344,10 @@ impl AddrCache {
 
         self.authority_id_to_addresses.insert(validator_id.clone(), addresses_set);
 
+        for peer_id in old_peer_ids.difference(&new_peer_ids) {
+            self.unmap_peer(peer_id, &validator_id);
+        }
+
         for peer_id in new_peer_ids {
             if !old_peer_ids.contains(&peer_id) {
                 self.peer_id_to_authority_ids
@@ -161,6 +356,132 @@ impl AddrCache {
                     .insert(validator_id.clone());
             }
         }
+
+        self.last_updated.insert(validator_id, (self.next_update, self.clock.now()));
+        self.next_update += 1;
+        self.evict();
+    }
+
+    /// Removes the untracked validators whose addresses were not updated for the time to live of
+    /// the cache.
+    ///
+    /// Expired validators are also removed whenever the cache is updated.
+    ///
+    /// # Returns
+    /// The number of validators that were removed.
+    pub fn expire(&mut self) -> usize {
+        let Some(ttl) = self.ttl else {
+            return 0
+        };
+        let now = self.clock.now();
+        let expired = self
+            .last_updated
+            .iter()
+            .filter(|(validator_id, _)| !self.tracked.contains(*validator_id))
+            .filter(|(_, (_, updated_at))| now.saturating_duration_since(*updated_at) >= ttl)
+            .map(|(validator_id, _)| validator_id.clone())
+            .collect::<Vec<_>>();
+        for validator_id in expired.iter() {
+            self.remove_validator(validator_id);
+        }
+        expired.len()
+    }
+
+    // Evicts the expired validators, then the least recently updated untracked validators until
+    // the cache fits its capacity.
+    fn evict(&mut self) {
+        self.expire();
+        while self.authority_id_to_addresses.len() > self.capacity {
+            let oldest = self
+                .last_updated
+                .iter()
+                .filter(|(validator_id, _)| !self.tracked.contains(*validator_id))
+                .min_by_key(|(_, (update, _))| *update)
+                .map(|(validator_id, _)| validator_id.clone());
+            match oldest {
+                Some(validator_id) => self.remove_validator(&validator_id),
+                None => break,
+            }
+        }
+    }
+
+    // Removes a validator and its reverse mappings from the cache.
+    fn remove_validator(&mut self, validator_id: &ValidatorId) {
+        self.last_updated.remove(validator_id);
+        let Some(addresses) = self.authority_id_to_addresses.remove(validator_id) else {
+            return
+        };
+        for peer_id in addresses_to_peer_ids(&addresses) {
+            self.unmap_peer(&peer_id, validator_id);
+        }
+    }
+
+    // Removes the association of a peer with a validator, dropping the peer once it is
+    // associated with no validator.
+    fn unmap_peer(&mut self, peer_id: &PeerId, validator_id: &ValidatorId) {
+        if let Some(validator_ids) = self.peer_id_to_authority_ids.get_mut(peer_id) {
+            validator_ids.remove(validator_id);
+            if validator_ids.is_empty() {
+                self.peer_id_to_authority_ids.remove(peer_id);
+            }
+        }
+    }
+
+    /// Returns the number of validators with cached addresses.
+    pub fn len(&self) -> usize {
+        self.authority_id_to_addresses.len()
+    }
+
+    /// Returns `true` if no validator addresses are cached.
+    pub fn is_empty(&self) -> bool {
+        self.authority_id_to_addresses.is_empty()
+    }
+
+    /// Returns the peer IDs of all cached validators.
+    pub fn peer_ids(&self) -> impl Iterator<Item = &PeerId> {
+        self.peer_id_to_authority_ids.keys()
+    }
+
+    /// Removes every cached validator.
+    ///
+    /// # Returns
+    /// The number of validators that were removed.
+    pub fn clear(&mut self) -> usize {
+        let count = self.authority_id_to_addresses.len();
+        self.authority_id_to_addresses.clear();
+        self.peer_id_to_authority_ids.clear();
+        self.tracked.clear();
+        self.last_updated.clear();
+        count
+    }
+
+    /// Returns the IDs of the validators in the cache.
+    pub fn validator_ids(&self) -> impl Iterator<Item = &ValidatorId> {
+        self.authority_id_to_addresses.keys()
+    }
+
+    /// Checks whether a peer belongs to a tracked validator, using the reverse mapping.
+    ///
+    /// # Arguments
+    /// * `peer_id` - The `PeerId` to look up.
+    ///
+    /// # Returns
+    /// `true` if one of the validators the peer is associated with is tracked.
+    pub fn is_tracked_peer(&self, peer_id: &PeerId) -> bool {
+        self.peer_id_to_authority_ids
+            .get(peer_id)
+            .map_or(false, |validator_ids| validator_ids.iter().any(|id| self.tracked.contains(id)))
+    }
+
+    /// Retrieves the multiaddresses cached for a given validator ID.
+    ///
+    /// # Arguments
+    /// * `validator_id` - The `ValidatorId` for which multiaddresses are to be retrieved.
+    ///
+    /// # Returns
+    /// The multiaddresses of the validator, if any are cached.
+    pub fn validator_multiaddrs(&self, validator_id: &ValidatorId) -> Option<&HashSet<Multiaddr>> {
+        self.authority_id_to_addresses.get(validator_id)
     }
 
     /// Retrieves the addresses associated with a given validator ID.
@@ -198,3 +519,138 @@ fn peer_id_from_multiaddr(addr: &Multiaddr) -> Option<PeerId> {
 fn addresses_to_peer_ids(addresses: &HashSet<Multiaddr>) -> HashSet<PeerId> {
     addresses.iter().filter_map(peer_id_from_multiaddr).collect::<HashSet<_>>()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use redot_core_primitives::clock::ManualClock;
+
+    fn validator(seed: u8) -> ValidatorId {
+        ValidatorId::from_slice(&[seed; 32]).unwrap()
+    }
+
+    fn addresses() -> Vec<Multiaddr> {
+        let address = format!("/ip4/127.0.0.1/tcp/30333/p2p/{}", PeerId::random());
+        vec![address.parse().unwrap()]
+    }
+
+    #[test]
+    fn signing_without_authority_discovery_key_fails() {
+        let key_store = sp_keystore::testing::MemoryKeystore::new();
+        let record = addresses().iter().map(|a| a.to_vec()).collect::<Vec<_>>();
+        assert!(SignedValidatorRecord::sign_record(&key_store, record.clone()).is_err());
+
+        key_store.sr25519_generate_new(key_types::AUTHORITY_DISCOVERY, None).unwrap();
+        let signed = SignedValidatorRecord::sign_record(&key_store, record).unwrap();
+        assert_eq!(signed.len(), 1);
+        assert!(signed[0].0.verify_signature());
+    }
+
+    #[test]
+    fn records_with_invalid_addresses_are_rejected() {
+        let valid = SignedValidatorRecord {
+            record: addresses().iter().map(|a| a.to_vec()).collect(),
+            validator_id: validator(1),
+            auth_signature: vec![0; SIGNATURE_LEN],
+        };
+        assert!(SignedValidatorRecord::decode_checked(&valid.encode()).is_ok());
+
+        let invalid = SignedValidatorRecord { record: vec![vec![0xff; 8]], ..valid };
+        assert!(invalid.addresses().is_err());
+        assert!(SignedValidatorRecord::decode_checked(&invalid.encode()).is_err());
+    }
+
+    #[test]
+    fn exceeding_capacity_evicts_untracked_validators_first() {
+        let mut cache = AddrCache::with_capacity(3);
+        cache.track_validators(&[validator(1)]);
+
+        cache.add_validator(validator(1), addresses());
+        cache.add_validator(validator(2), addresses());
+        cache.add_validator(validator(3), addresses());
+        cache.add_validator(validator(4), addresses());
+
+        // The tracked validator is the oldest, so the oldest untracked one is evicted instead.
+        assert_eq!(cache.len(), 3);
+        assert!(cache.validator_addresses(&validator(1)).is_some());
+        assert!(cache.validator_addresses(&validator(2)).is_none());
+        assert_eq!(cache.peer_ids().count(), 3);
+
+        // Updating a validator makes it the most recent one.
+        cache.add_validator(validator(3), addresses());
+        cache.add_validator(validator(5), addresses());
+        assert!(cache.validator_addresses(&validator(3)).is_some());
+        assert!(cache.validator_addresses(&validator(4)).is_none());
+
+        // Tracked validators are kept even when they alone exceed the capacity.
+        cache.track_validators(&[validator(3), validator(5), validator(6)]);
+        cache.add_validator(validator(6), addresses());
+        assert_eq!(cache.len(), 4);
+    }
+
+    #[test]
+    fn untracked_validators_expire_after_the_ttl() {
+        let clock = ManualClock::new(0);
+        let mut cache = AddrCache::new();
+        cache.set_clock(Arc::new(clock.clone()));
+        cache.set_ttl(Some(Duration::from_secs(60)));
+        cache.track_validators(&[validator(1)]);
+        cache.add_validator(validator(1), addresses());
+        cache.add_validator(validator(2), addresses());
+
+        clock.advance(Duration::from_secs(30));
+        cache.add_validator(validator(3), addresses());
+        assert_eq!(cache.expire(), 0);
+
+        // Validator 2 expires, while validator 3 is more recent and validator 1 is tracked.
+        clock.advance(Duration::from_secs(30));
+        assert_eq!(cache.expire(), 1);
+        assert!(cache.validator_addresses(&validator(2)).is_none());
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.peer_ids().count(), 2);
+
+        // Updating a validator renews its addresses.
+        cache.add_validator(validator(3), addresses());
+        clock.advance(Duration::from_secs(30));
+        assert_eq!(cache.expire(), 0);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn changing_peer_id_drops_the_old_reverse_mapping() {
+        let mut cache = AddrCache::new();
+        let old = addresses();
+        let new = addresses();
+        let old_peer_id = peer_id_from_multiaddr(&old[0]).unwrap();
+        let new_peer_id = peer_id_from_multiaddr(&new[0]).unwrap();
+        cache.track_validators(&[validator(1)]);
+
+        cache.add_validator(validator(1), old.clone());
+        assert!(cache.is_tracked_peer(&old_peer_id));
+
+        cache.add_validator(validator(1), new.clone());
+        assert!(!cache.is_tracked_peer(&old_peer_id));
+        assert!(cache.is_tracked_peer(&new_peer_id));
+        assert_eq!(cache.peer_ids().collect::<Vec<_>>(), vec![&new_peer_id]);
+
+        // A peer kept across updates stays mapped.
+        cache.add_validator(validator(1), [new.clone(), old].concat());
+        cache.add_validator(validator(1), new);
+        assert!(cache.is_tracked_peer(&new_peer_id));
+        assert!(!cache.is_tracked_peer(&old_peer_id));
+    }
+
+    #[test]
+    fn only_peers_of_tracked_validators_are_tracked_peers() {
+        let mut cache = AddrCache::new();
+        let tracked = addresses();
+        let untracked = addresses();
+        cache.track_validators(&[validator(1)]);
+        cache.add_validator(validator(1), tracked.clone());
+        cache.add_validator(validator(2), untracked.clone());
+
+        assert!(cache.is_tracked_peer(&peer_id_from_multiaddr(&tracked[0]).unwrap()));
+        assert!(!cache.is_tracked_peer(&peer_id_from_multiaddr(&untracked[0]).unwrap()));
+        assert!(!cache.is_tracked_peer(&PeerId::random()));
+    }
+}
@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use anyhow::{Ok, Result};
+use anyhow::{Context, Ok, Result};
 use futures::channel::mpsc;
 use libp2p::{
 	core::{
@@ -39,17 +39,20 @@ pub(crate) use libp2p::kad::record::Key as KademliaKey;
 pub use log::warn;
 pub use std::sync::Arc;
 
-use std::time::Duration;
+use std::{io::Write, path::Path, time::Duration};
 
 pub use behaviour::{Behavior, BehaviorConfig, BehaviourEvent};
+pub use discovery::SignedValidatorRecord;
 pub use service::{Service, ValidatorNetworkConfig};
-pub use shared::Command;
+pub use shared::{Command, ConnectionStatus, NetworkHealth, TopicTraffic};
 pub use worker::ValidatorNetwork;
 
+pub(crate) use dial::DialQueue;
 pub(crate) use discovery::AddrCache;
 pub(crate) use shared::CreatedSubscription;
 
 mod behaviour;
+mod dial;
 mod discovery;
 mod service;
 mod shared;
@@ -78,6 +81,7 @@ pub fn create(
 		identify,
 		kademlia: KademliaConfig::default(),
 		kad_store: MemoryStore::new(local_peer_id),
+		gossipsub: config.gossipsub_config()?,
 	},&keypair)?;
 
 	let mut swarm = SwarmBuilder::with_tokio_executor(transport, behaviour, local_peer_id)
@@ -100,6 +104,34 @@ pub fn create(
 	))
 }
 
+/// Loads the node keypair stored at `path`, or generates an ed25519 keypair and stores it there.
+///
+/// Keeping the keypair across restarts keeps the `PeerId` of the node, and with it the
+/// addresses other validators have cached.
+pub fn load_or_generate_keypair(path: &Path) -> Result<identity::Keypair> {
+	if path.exists() {
+		let encoded = std::fs::read(path)
+			.with_context(|| format!("Failed to read the node key {}", path.display()))?;
+		return identity::Keypair::from_protobuf_encoding(&encoded)
+			.with_context(|| format!("Invalid node key {}", path.display()))
+	}
+
+	let keypair = identity::Keypair::generate_ed25519();
+	let encoded = keypair.to_protobuf_encoding().context("Failed to encode the node key")?;
+	write_secret(path, &encoded)
+		.with_context(|| format!("Failed to write the node key {}", path.display()))?;
+	Ok(keypair)
+}
+
+// Writes `content` to a file only readable by the current user.
+fn write_secret(path: &Path, content: &[u8]) -> std::io::Result<()> {
+	let mut options = std::fs::OpenOptions::new();
+	options.write(true).create_new(true);
+	#[cfg(unix)]
+	std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+	options.open(path)?.write_all(content)
+}
+
 /// Creates a new [`ValidatorNetwork`] instance with default configuration.
 pub fn default(
 	config: Option<ValidatorNetworkConfig>,
@@ -12,14 +12,22 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{AddrCache, Command, shared::CreatedSubscription};
-use anyhow::Context;
+use crate::{
+    discovery::{DEFAULT_ADDR_CACHE_CAPACITY, DEFAULT_ADDR_CACHE_TTL},
+    AddrCache, Command, NetworkHealth,
+    shared::CreatedSubscription,
+};
+use anyhow::{anyhow, ensure, Context};
 use cumulus_primitives_core::relay_chain::ValidatorId;
 use futures::{
     channel::{mpsc, oneshot},
     SinkExt,
 };
-use libp2p::{futures, gossipsub::Sha256Topic, Multiaddr, PeerId};
+use libp2p::{
+    futures,
+    gossipsub::{GossipsubConfig, GossipsubConfigBuilder, Sha256Topic},
+    Multiaddr, PeerId,
+};
 use sp_keystore::KeystorePtr;
 use std::{fmt::Debug, time::Duration};
 
@@ -105,6 +113,22 @@ impl Service {
         Ok(())
     }
 
+    /// Connects to validators ahead of time, without adding them to the gossip mesh.
+    ///
+    /// Addresses are taken from the address cache, or looked up in the DHT for validators that
+    /// are not cached, and the validators are dialed. Calling `new_validators` for them later,
+    /// for example before a DKG, then finds the connections already established.
+    ///
+    /// # Arguments
+    /// * `validators` - The `ValidatorId`s to connect to.
+    ///
+    /// # Returns
+    /// A result indicating success.
+    pub async fn preconnect(&self, validators: Vec<ValidatorId>) -> anyhow::Result<()> {
+        self.to_worker.clone().send(Command::Preconnect { validators }).await?;
+        Ok(())
+    }
+
     /// Removes validators from the worker's awareness.
     ///
     /// # Arguments
@@ -117,6 +141,19 @@ impl Service {
         Ok(())
     }
 
+    /// Removes all tracked validators.
+    ///
+    /// Their peers are dropped from the gossipsub explicit peers and the routing table, and the
+    /// address cache is emptied.
+    ///
+    /// # Returns
+    /// A result containing the number of validators that were cleared.
+    pub async fn clear_validators(&self) -> anyhow::Result<usize> {
+        let (sender, receiver) = oneshot::channel();
+        self.to_worker.clone().send(Command::ClearValidators { sender }).await?;
+        receiver.await.context("Failed receiving clear validators response")?
+    }
+
     /// Subscribes to a topic.
     ///
     /// # Arguments
@@ -125,9 +162,37 @@ impl Service {
     /// # Returns
     /// A result containing a `CreatedSubscription` on success, or an error if the operation fails.
     pub async fn subscribe(&self, topic_name: &str) -> anyhow::Result<CreatedSubscription> {
+        self.subscribe_with(topic_name, false).await
+    }
+
+    /// Subscribes to a topic, receiving only messages authored by tracked validators.
+    ///
+    /// Messages whose source peer does not map to a tracked validator in the address cache are
+    /// dropped and logged, so that outside peers cannot inject messages into the topic.
+    ///
+    /// # Arguments
+    /// * `topic_name` - The name of the topic to subscribe to.
+    ///
+    /// # Returns
+    /// A result containing a `CreatedSubscription` on success, or an error if the operation fails.
+    pub async fn subscribe_validators_only(
+        &self,
+        topic_name: &str,
+    ) -> anyhow::Result<CreatedSubscription> {
+        self.subscribe_with(topic_name, true).await
+    }
+
+    async fn subscribe_with(
+        &self,
+        topic_name: &str,
+        validators_only: bool,
+    ) -> anyhow::Result<CreatedSubscription> {
         let topic = Sha256Topic::new(topic_name);
         let (result_sender, result_receiver) = oneshot::channel();
-        self.to_worker.clone().send(Command::Subscribe { topic, result_sender }).await?;
+        self.to_worker
+            .clone()
+            .send(Command::Subscribe { topic, validators_only, result_sender })
+            .await?;
 
         match result_receiver.await.context("Failed receiving subscribe response") {
             Ok(result) => result.map_err(Into::into),
@@ -149,6 +214,29 @@ impl Service {
         self.to_worker.clone().send(Command::Publish { topic, message, sender }).await?;
         receiver.await.context("Failed receiving publish response")?
     }
+
+    /// Returns a snapshot of the network's connectivity.
+    ///
+    /// # Returns
+    /// A result containing the current `NetworkHealth`, or an error if the worker is unreachable.
+    pub async fn health(&self) -> anyhow::Result<NetworkHealth> {
+        let (sender, receiver) = oneshot::channel();
+        self.to_worker.clone().send(Command::Health { sender }).await?;
+        receiver.await.context("Failed receiving health response")
+    }
+
+    /// Lists the topics the worker is currently subscribed to.
+    ///
+    /// A worker missing a topic, for example after a failed resubscription, does not receive
+    /// the messages of the rounds using it.
+    ///
+    /// # Returns
+    /// A result containing the sorted topic names, or an error if the worker is unreachable.
+    pub async fn list_subscriptions(&self) -> anyhow::Result<Vec<String>> {
+        let (sender, receiver) = oneshot::channel();
+        self.to_worker.clone().send(Command::ListSubscriptions { sender }).await?;
+        receiver.await.context("Failed receiving subscriptions response")
+    }
 }
 
 /// Configuration for the Validator Network service.
@@ -169,12 +257,52 @@ pub struct ValidatorNetworkConfig {
     pub retry_delay: Duration,
     /// Timeout for bootstrapping the network.
     pub bootstrap_timeout: Duration,
-    /// Maximum number of parallel connections to maintain.
+    /// Maximum number of outbound validator dials in flight at the same time.
     pub parallel_limit: usize,
     /// The keypair of the node.
     pub key_ptr: Option<KeystorePtr>,
     /// The address cache of validators.
     pub address_cache: AddrCache,
+    /// Maximum number of validators kept in the address cache. Validators the network was
+    /// asked to connect to are kept beyond this bound.
+    pub addr_cache_capacity: usize,
+    /// Time after which the addresses of a validator the network was not asked to connect to
+    /// expire if no newer record was received, or `None` to keep them until evicted for
+    /// capacity.
+    pub addr_cache_ttl: Option<Duration>,
+    /// Whether messages published by the node are sent to every peer subscribed to the topic,
+    /// rather than to its mesh peers only.
+    ///
+    /// Flood publishing gets DKG and signing messages to all participants in a single hop, at
+    /// the cost of sending each message once per peer. On large validator sets, turning it off
+    /// leaves propagation to the mesh, which saves bandwidth but adds hops, and so latency to
+    /// every round.
+    pub gossip_flood_publish: bool,
+    /// How long the ids of received messages are remembered to drop duplicates.
+    ///
+    /// A message received again after this time is accepted and forwarded as a new one. A
+    /// shorter time bounds the memory used by the cache, but it must stay above the time a
+    /// message takes to propagate through the network, or duplicates circulate again. It cannot
+    /// be shorter than the gossipsub heartbeat interval.
+    pub gossip_duplicate_cache_time: Duration,
+}
+
+impl ValidatorNetworkConfig {
+    /// Builds the gossipsub configuration from the gossip settings.
+    pub(crate) fn gossipsub_config(&self) -> anyhow::Result<GossipsubConfig> {
+        let defaults = GossipsubConfig::default();
+        ensure!(
+            self.gossip_duplicate_cache_time >= defaults.heartbeat_interval(),
+            "Gossip duplicate cache time {:?} is shorter than the heartbeat interval {:?}",
+            self.gossip_duplicate_cache_time,
+            defaults.heartbeat_interval()
+        );
+        GossipsubConfigBuilder::default()
+            .flood_publish(self.gossip_flood_publish)
+            .duplicate_cache_time(self.gossip_duplicate_cache_time)
+            .build()
+            .map_err(|e| anyhow!("Invalid gossipsub configuration: {}", e))
+    }
 }
 
 impl Default for ValidatorNetworkConfig {
@@ -189,6 +317,141 @@ impl Default for ValidatorNetworkConfig {
             parallel_limit: 10,
             key_ptr: None,
             address_cache: AddrCache::new(),
+            addr_cache_capacity: DEFAULT_ADDR_CACHE_CAPACITY,
+            addr_cache_ttl: Some(DEFAULT_ADDR_CACHE_TTL),
+            gossip_flood_publish: true,
+            gossip_duplicate_cache_time: Duration::from_secs(60),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::{
+        core::{multiaddr::Protocol, transport::MemoryTransport, upgrade::Version},
+        futures::StreamExt,
+        gossipsub::{
+            Gossipsub, GossipsubEvent, GossipsubMessage, IdentTopic, MessageAuthenticity, MessageId,
+        },
+        identity,
+        noise::NoiseAuthenticated,
+        swarm::{Swarm, SwarmBuilder, SwarmEvent},
+        yamux::YamuxConfig,
+        Transport,
+    };
+
+    // Builds a gossipsub node over an in-memory transport. Message ids are derived from the data
+    // only, so that the same message published by two peers is a duplicate.
+    fn gossip_node(config: &ValidatorNetworkConfig) -> Swarm<Gossipsub> {
+        let keypair = identity::Keypair::generate_ed25519();
+        let peer_id = PeerId::from(keypair.public());
+        let transport = MemoryTransport::default()
+            .upgrade(Version::V1)
+            .authenticate(NoiseAuthenticated::xx(&keypair).unwrap())
+            .multiplex(YamuxConfig::default())
+            .boxed();
+        let gossipsub = GossipsubConfigBuilder::from(config.gossipsub_config().unwrap())
+            .message_id_fn(|message: &GossipsubMessage| MessageId::from(message.data.clone()))
+            .build()
+            .unwrap();
+        let behaviour = Gossipsub::new(MessageAuthenticity::Signed(keypair), gossipsub).unwrap();
+        SwarmBuilder::with_tokio_executor(transport, behaviour, peer_id).build()
+    }
+
+    // Connects a new peer to `receiver`, publishes `data` from it once it knows the subscription
+    // of `receiver`, and keeps it running in the background.
+    async fn publish_from_new_peer(
+        config: &ValidatorNetworkConfig,
+        receiver: &mut Swarm<Gossipsub>,
+        address: &Multiaddr,
+        topic: &IdentTopic,
+        data: &[u8],
+    ) {
+        let mut publisher = gossip_node(config);
+        publisher.dial(address.clone()).unwrap();
+        loop {
+            tokio::select! {
+                event = publisher.select_next_some() => {
+                    if let SwarmEvent::Behaviour(GossipsubEvent::Subscribed { .. }) = event {
+                        break
+                    }
+                },
+                _ = receiver.select_next_some() => {},
+            }
+        }
+        publisher.behaviour_mut().publish(topic.clone(), data.to_vec()).unwrap();
+        tokio::spawn(async move {
+            loop {
+                publisher.select_next_some().await;
+            }
+        });
+    }
+
+    // Returns the data of the next message delivered to `receiver` within `timeout`.
+    async fn next_message(receiver: &mut Swarm<Gossipsub>, timeout: Duration) -> Option<Vec<u8>> {
+        let message = async {
+            loop {
+                if let SwarmEvent::Behaviour(GossipsubEvent::Message { message, .. }) =
+                    receiver.select_next_some().await
+                {
+                    return message.data
+                }
+            }
+        };
+        tokio::time::timeout(timeout, message).await.ok()
+    }
+
+    #[test]
+    fn gossip_settings_reach_gossipsub() {
+        let config = ValidatorNetworkConfig::default();
+        let gossipsub = config.gossipsub_config().unwrap();
+        assert!(gossipsub.flood_publish());
+        assert_eq!(gossipsub.duplicate_cache_time(), Duration::from_secs(60));
+
+        let config = ValidatorNetworkConfig {
+            gossip_flood_publish: false,
+            gossip_duplicate_cache_time: Duration::from_secs(5),
+            ..Default::default()
+        };
+        let gossipsub = config.gossipsub_config().unwrap();
+        assert!(!gossipsub.flood_publish());
+        assert_eq!(gossipsub.duplicate_cache_time(), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn duplicate_cache_shorter_than_heartbeat_is_rejected() {
+        let config = ValidatorNetworkConfig {
+            gossip_duplicate_cache_time: Duration::from_millis(100),
+            ..Default::default()
+        };
+        assert!(config.gossipsub_config().is_err());
+    }
+
+    #[tokio::test]
+    async fn duplicates_are_accepted_again_after_the_cache_time() {
+        let config = ValidatorNetworkConfig {
+            gossip_duplicate_cache_time: Duration::from_secs(1),
+            ..Default::default()
+        };
+        let topic = IdentTopic::new("duplicates");
+        let mut receiver = gossip_node(&config);
+        receiver.behaviour_mut().subscribe(&topic).unwrap();
+        let address: Multiaddr = Protocol::Memory(rand::random::<u64>()).into();
+        receiver.listen_on(address.clone()).unwrap();
+
+        publish_from_new_peer(&config, &mut receiver, &address, &topic, b"message").await;
+        let delivered = next_message(&mut receiver, Duration::from_secs(5)).await;
+        assert_eq!(delivered, Some(b"message".to_vec()));
+
+        // Within the cache time, the same message from another peer is dropped.
+        publish_from_new_peer(&config, &mut receiver, &address, &topic, b"message").await;
+        assert_eq!(next_message(&mut receiver, Duration::from_millis(300)).await, None);
+
+        // Once it has elapsed, the message is delivered again.
+        tokio::time::sleep(config.gossip_duplicate_cache_time).await;
+        publish_from_new_peer(&config, &mut receiver, &address, &topic, b"message").await;
+        let delivered = next_message(&mut receiver, Duration::from_secs(5)).await;
+        assert_eq!(delivered, Some(b"message".to_vec()));
+    }
+}
@@ -21,6 +21,7 @@ use libp2p::{
 	Multiaddr, PeerId,
 };
 use bytes::Bytes;
+use std::collections::HashMap;
 
 #[derive(Debug)]
 pub struct CreatedSubscription {
@@ -30,6 +31,55 @@ pub struct CreatedSubscription {
 	pub receiver: mpsc::UnboundedReceiver<Bytes>,
 }
 
+/// Whether a validator can currently be reached.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectionStatus {
+	/// At least one of the validator's peers has an established connection.
+	Connected,
+	/// The validator's addresses are known, but none of its peers is connected.
+	Disconnected,
+}
+
+/// Gossip traffic of a topic since the worker started.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TopicTraffic {
+	/// Number of messages published by the node.
+	pub published_messages: u64,
+	/// Total size in bytes of the messages published by the node.
+	pub published_bytes: u64,
+	/// Number of messages received from the network.
+	pub received_messages: u64,
+	/// Total size in bytes of the messages received from the network.
+	pub received_bytes: u64,
+}
+
+impl TopicTraffic {
+	/// Accounts for a published message of `size` bytes.
+	pub fn record_published(&mut self, size: usize) {
+		self.published_messages += 1;
+		self.published_bytes += size as u64;
+	}
+
+	/// Accounts for a received message of `size` bytes.
+	pub fn record_received(&mut self, size: usize) {
+		self.received_messages += 1;
+		self.received_bytes += size as u64;
+	}
+}
+
+/// A snapshot of the validator network's connectivity.
+#[derive(Clone, Debug, Default)]
+pub struct NetworkHealth {
+	/// Number of peers with at least one established connection.
+	pub connected_peers: usize,
+	/// Number of validators whose addresses are known.
+	pub known_validators: usize,
+	/// Connection status of each validator whose addresses are known.
+	pub validators: HashMap<ValidatorId, ConnectionStatus>,
+	/// Gossip traffic of each topic that carried messages, by topic name.
+	pub gossip: HashMap<String, TopicTraffic>,
+}
+
 #[derive(Debug)]
 pub enum Command {
 	StartListening {
@@ -54,11 +104,18 @@ pub enum Command {
 	NewValidators {
 		validators: Vec<ValidatorId>,
 	},
+	Preconnect {
+		validators: Vec<ValidatorId>,
+	},
 	RemoveValidators {
 		validators: Vec<ValidatorId>,
 	},
+	ClearValidators {
+		sender: oneshot::Sender<Result<usize>>,
+	},
 	Subscribe {
 		topic: Sha256Topic,
+		validators_only: bool,
 		#[allow(private_interfaces)]
 		result_sender: oneshot::Sender<Result<CreatedSubscription, SubscriptionError>>,
 	},
@@ -71,4 +128,10 @@ pub enum Command {
         topic: Sha256Topic,
         subscription_id: usize,
     },
+	Health {
+		sender: oneshot::Sender<NetworkHealth>,
+	},
+	ListSubscriptions {
+		sender: oneshot::Sender<Vec<String>>,
+	},
 }
@@ -13,10 +13,12 @@
 // limitations under the License.
 use crate::{
 	discovery::SignedValidatorRecord, AddrCache, Behavior, BehaviourEvent, Command,
-	CreatedSubscription, KademliaKey, ValidatorNetworkConfig,
+	ConnectionStatus, CreatedSubscription, DialQueue, KademliaKey, NetworkHealth, TopicTraffic,
+	ValidatorNetworkConfig,
 };
 use bytes::Bytes;
-use codec::{Decode, Encode};
+use codec::Encode;
+use cumulus_primitives_core::relay_chain::ValidatorId;
 use futures::{
 	channel::{mpsc, oneshot},
 	stream::StreamExt,
@@ -32,15 +34,21 @@ use libp2p::{
 	mdns::Event as MdnsEvent,
 	multiaddr::{self, Protocol},
 	multihash::Multihash,
-	swarm::{ConnectionError, Swarm, SwarmEvent},
+	swarm::{dial_opts::DialOpts, ConnectionError, Swarm, SwarmEvent},
 	Multiaddr, PeerId,
 };
 use log::{debug, error, info, trace, warn};
 use nohash_hasher::IntMap;
-use prometheus_endpoint::{register, Counter, CounterVec, Gauge, Opts, U64};
+use prometheus_endpoint::{
+	exponential_buckets, register, Counter, CounterVec, Gauge, HistogramOpts, HistogramVec, Opts,
+	U64,
+};
 use sp_keystore::KeystorePtr;
 use std::collections::hash_map::Entry;
-use std::{collections::HashMap, fmt::Debug};
+use std::{
+	collections::{HashMap, HashSet},
+	fmt::Debug,
+};
 use tracing::field::debug;
 
 // The maximum number of connection retries.
@@ -75,6 +83,13 @@ pub struct ValidatorNetwork {
 	address_cache: AddrCache,
 	topic_subscription_senders: HashMap<TopicHash, IntMap<usize, mpsc::UnboundedSender<Bytes>>>,
 	next_subscription_id: usize,
+	topic_names: HashMap<TopicHash, String>,
+	validators_only_subscriptions: HashSet<usize>,
+	preconnecting: HashSet<ValidatorId>,
+	// Validator peers that join gossip as explicit peers once their dial connects.
+	pending_gossip_peers: HashSet<PeerId>,
+	topic_traffic: HashMap<String, TopicTraffic>,
+	dial_queue: DialQueue,
 }
 
 impl ValidatorNetwork {
@@ -128,6 +143,10 @@ impl ValidatorNetwork {
 			None => None,
 		};
 
+		let mut address_cache = config.address_cache.clone();
+		address_cache.set_capacity(config.addr_cache_capacity);
+		address_cache.set_ttl(config.addr_cache_ttl);
+
 		Self {
 			swarm,
 			command_receiver,
@@ -138,9 +157,15 @@ impl ValidatorNetwork {
 			metrics,
 			known_addresses,
 			key_ptr: config.key_ptr.clone(),
-			address_cache: config.address_cache.clone(),
+			address_cache,
 			topic_subscription_senders: HashMap::new(),
 			next_subscription_id: 0,
+			topic_names: HashMap::new(),
+			validators_only_subscriptions: HashSet::new(),
+			preconnecting: HashSet::new(),
+			pending_gossip_peers: HashSet::new(),
+			topic_traffic: HashMap::new(),
+			dial_queue: DialQueue::new(config.parallel_limit),
 		}
 	}
 
@@ -270,18 +295,75 @@ impl ValidatorNetwork {
 					}
 				}
 			},
+			SwarmEvent::ConnectionEstablished { peer_id, .. } => {
+				if self.pending_gossip_peers.remove(&peer_id) {
+					self.swarm.behaviour_mut().gossipsub.add_explicit_peer(&peer_id);
+				}
+				if self.dial_queue.finish(&peer_id) {
+					self.start_dials();
+				}
+			},
+			SwarmEvent::OutgoingConnectionError { peer_id: Some(peer_id), error } => {
+				debug!("Failed to dial peer {:?}: {:?}", peer_id, error);
+				// Gossipsub keeps dialing its explicit peers, so a validator whose queued dial
+				// failed is left to it rather than never joining gossip.
+				if self.pending_gossip_peers.remove(&peer_id) {
+					self.swarm.behaviour_mut().gossipsub.add_explicit_peer(&peer_id);
+				}
+				if self.dial_queue.finish(&peer_id) {
+					self.start_dials();
+				}
+			},
 			SwarmEvent::Dialing(peer_id) => debug!("Dialing {}", peer_id),
 			_ => trace!("Unhandled Swarm event: {:?}", event),
 		}
 	}
 
+	// Accounts for a gossip message of `size` bytes, `direction` being "published" or
+	// "received".
+	fn record_gossip(&mut self, topic: String, direction: &'static str, size: usize) {
+		if let Some(metrics) = &self.metrics {
+			metrics.gossip_messages.with_label_values(&[&topic, direction]).inc();
+			metrics
+				.gossip_message_bytes
+				.with_label_values(&[&topic, direction])
+				.observe(size as f64);
+		}
+		let traffic = self.topic_traffic.entry(topic).or_default();
+		if direction == "published" {
+			traffic.record_published(size);
+		} else {
+			traffic.record_received(size);
+		}
+	}
+
 	async fn handle_gossipsub_event(&mut self, event: GossipsubEvent) {
 		match event {
 			GossipsubEvent::Message { message, .. } => {
+				let topic_name = self
+					.topic_names
+					.get(&message.topic)
+					.cloned()
+					.unwrap_or_else(|| message.topic.to_string());
+				self.record_gossip(topic_name, "received", message.data.len());
+
 				if let Some(senders) = self.topic_subscription_senders.get(&message.topic) {
+					// Messages are signed, so the source is the authenticated author.
+					let from_validator = message
+						.source
+						.map_or(false, |peer_id| self.address_cache.is_tracked_peer(&peer_id));
 					let bytes = Bytes::from(message.data);
 
-					for sender in senders.values() {
+					for (subscription_id, sender) in senders.iter() {
+						if !from_validator &&
+							self.validators_only_subscriptions.contains(subscription_id)
+						{
+							warn!(
+								"Rejected message on topic {} from {:?}, not a known validator",
+								message.topic, message.source
+							);
+							continue
+						}
 						let _ = sender.unbounded_send(bytes.clone());
 					}
 				}
@@ -374,35 +456,83 @@ impl ValidatorNetwork {
 	}
 
 	fn handle_record(&mut self, record: &Record) {
+		let key = &record.key;
 		let record = record.value.clone();
 		let local_peer_id = *self.swarm.local_peer_id();
-		match SignedValidatorRecord::decode(&mut record.as_ref()) {
+		match SignedValidatorRecord::decode_checked(&record) {
 			Ok(signed_record) => {
-				if signed_record.verify_signature() {
-					let addresses: Vec<Multiaddr> =
-						deserialize_addresses(signed_record.record).unwrap();
+				if !signed_record.key_matches(key) {
+					warn!(
+						"Rejected validator record for {:?} stored under a mismatched key",
+						signed_record.validator_id
+					);
+				} else if signed_record.verify_signature() {
+					// `decode_checked` already rejects records holding invalid addresses.
+					let Ok(addresses) = signed_record.addresses() else {
+						debug!("Rejected validator record holding an invalid address");
+						return
+					};
 
 					let validator_id = signed_record.validator_id;
+					// Validators looked up for `Preconnect` are dialed but kept out of gossip.
+					let join_gossip = !self.preconnecting.remove(&validator_id);
 
 					let addresses: Vec<Multiaddr> = addresses
 						.into_iter()
 						.filter(|a| get_peer_id(a).filter(|p| *p != local_peer_id).is_some())
 						.collect();
 
+					let mut peer_addresses: HashMap<PeerId, Vec<Multiaddr>> = HashMap::new();
 					for address in addresses.clone() {
 						if let Some(peer_id) = get_peer_id(&address) {
-							let behaviour = self.swarm.behaviour_mut();
-							behaviour.gossipsub.add_explicit_peer(&peer_id);
-							behaviour.kademlia.add_address(&peer_id, address);
+							self.swarm
+								.behaviour_mut()
+								.kademlia
+								.add_address(&peer_id, address.clone());
+							peer_addresses.entry(peer_id).or_default().push(address);
 						}
 					}
 
 					self.address_cache.add_validator(validator_id, addresses);
+
+					// Gossipsub dials its explicit peers itself, so peers only join gossip once
+					// connected, or once their dial failed, and all dials go through the
+					// `DialQueue`.
+					for (peer_id, addresses) in peer_addresses {
+						if self.swarm.is_connected(&peer_id) {
+							if join_gossip {
+								self.swarm.behaviour_mut().gossipsub.add_explicit_peer(&peer_id);
+							}
+						} else {
+							if join_gossip {
+								self.pending_gossip_peers.insert(peer_id);
+							}
+							self.dial_queue.push(peer_id, addresses);
+						}
+					}
+					self.start_dials();
 				} else {
 					debug!("Failed to verify validator record");
 				}
 			},
-			Err(_) => {},
+			Err(e) => debug!("Rejected malformed validator record: {:?}", e),
+		}
+	}
+
+	// Starts queued validator dials, up to the configured parallel limit.
+	fn start_dials(&mut self) {
+		loop {
+			let dials = self.dial_queue.next_dials();
+			if dials.is_empty() {
+				break
+			}
+			for (peer_id, addresses) in dials {
+				let opts = DialOpts::peer_id(peer_id).addresses(addresses).build();
+				if let Err(e) = self.swarm.dial(opts) {
+					debug!("Failed to start dialing peer {:?}: {:?}", peer_id, e);
+					self.dial_queue.finish(&peer_id);
+				}
+			}
 		}
 	}
 
@@ -422,7 +552,7 @@ impl ValidatorNetwork {
 					}
 				},
 				Err(e) => {
-					debug!("Failed to sign record: {:?}", e);
+					warn!("Not announcing the node addresses: {:?}", e);
 				},
 			}
 		}
@@ -501,25 +631,75 @@ impl ValidatorNetwork {
 				});
 			},
 			Command::NewValidators { validators } => {
+				for validator in validators.iter() {
+					self.preconnecting.remove(validator);
+				}
+				self.address_cache.track_validators(&validators);
 				let keys = validators.iter().map(|validator| SignedValidatorRecord::key(validator));
 
 				for key in keys {
 					self.swarm.behaviour_mut().kademlia.get_record(KademliaKey::from(key));
 				}
 			},
+			Command::Preconnect { validators } => {
+				for validator in validators {
+					match self.address_cache.validator_multiaddrs(&validator) {
+						Some(addresses) => {
+							let mut peer_addresses: HashMap<PeerId, Vec<Multiaddr>> =
+								HashMap::new();
+							for address in addresses {
+								if let Some(peer_id) = get_peer_id(address) {
+									let entry = peer_addresses.entry(peer_id).or_default();
+									entry.push(address.clone());
+								}
+							}
+							for (peer_id, addresses) in peer_addresses {
+								if !self.swarm.is_connected(&peer_id) {
+									self.dial_queue.push(peer_id, addresses);
+								}
+							}
+						},
+						None => {
+							let key = SignedValidatorRecord::key(&validator);
+							self.preconnecting.insert(validator);
+							self.swarm.behaviour_mut().kademlia.get_record(KademliaKey::from(key));
+						},
+					}
+				}
+				self.start_dials();
+			},
 			Command::RemoveValidators { validators } => {
 				let peer_ids = validators
 					.iter()
 					.filter_map(|validator| self.address_cache.validator_addresses(validator))
 					.flatten()
 					.collect::<Vec<_>>();
+				self.address_cache.untrack_validators(&validators);
+
+				for peer_id in peer_ids.iter() {
+					self.pending_gossip_peers.remove(peer_id);
+					self.dial_queue.remove(peer_id);
+					self.swarm.behaviour_mut().gossipsub.remove_explicit_peer(peer_id);
+					self.swarm.behaviour_mut().kademlia.remove_peer(peer_id);
+				}
+			},
+			Command::ClearValidators { sender } => {
+				let peer_ids = self.address_cache.peer_ids().cloned().collect::<Vec<_>>();
 
 				for peer_id in peer_ids.iter() {
 					self.swarm.behaviour_mut().gossipsub.remove_explicit_peer(peer_id);
 					self.swarm.behaviour_mut().kademlia.remove_peer(peer_id);
 				}
+				self.pending_gossip_peers.clear();
+				self.preconnecting.clear();
+				self.dial_queue.clear();
+
+				let cleared = self.address_cache.clear();
+				if sender.send(Ok(cleared)).is_err() {
+					debug!("Failed to send result for ClearValidators command");
+				}
 			},
-			Command::Subscribe { topic, result_sender } => {
+			Command::Subscribe { topic, validators_only, result_sender } => {
 				let topic_hash = topic.hash();
 
 				let (sender, receiver) = mpsc::unbounded();
@@ -533,6 +713,9 @@ impl ValidatorNetwork {
 					Entry::Occupied(mut entry) => {
 						if result_sender.send(Ok(created_subscription)).is_ok() {
 							entry.get_mut().insert(subscription_id, sender);
+							if validators_only {
+								self.validators_only_subscriptions.insert(subscription_id);
+							}
 						}
 					},
 					Entry::Vacant(entry) => {
@@ -540,7 +723,11 @@ impl ValidatorNetwork {
 							Ok(true) => {
 								if result_sender.send(Ok(created_subscription)).is_ok() {
 									entry.insert(IntMap::from_iter([(subscription_id, sender)]));
+									if validators_only {
+										self.validators_only_subscriptions.insert(subscription_id);
+									}
 								}
+								self.topic_names.insert(topic.hash(), topic.to_string());
 							},
 							Ok(false) => {
 								panic!(
@@ -556,16 +743,29 @@ impl ValidatorNetwork {
 				}
 			},
 			Command::Publish { topic, message, sender } => {
-				let _ = self.swarm.behaviour_mut().gossipsub.publish(topic, message);
-				let _ = sender.send(Ok(()));
+				let topic_name = topic.to_string();
+				let size = message.len();
+				// Failures such as `InsufficientPeers`, while the mesh is still forming, are
+				// reported so that the caller can retry.
+				let result = match self.swarm.behaviour_mut().gossipsub.publish(topic, message) {
+					Ok(_) => {
+						self.record_gossip(topic_name, "published", size);
+						Ok(())
+					},
+					Err(e) => Err(anyhow::Error::new(e)
+						.context(format!("Failed to publish to topic {}", topic_name))),
+				};
+				let _ = sender.send(result);
 			},
 			Command::Unsubscribe { topic, subscription_id } => {
 				if let Entry::Occupied(mut entry) =
 					self.topic_subscription_senders.entry(topic.hash())
 				{
 					entry.get_mut().remove(&subscription_id);
+					self.validators_only_subscriptions.remove(&subscription_id);
 					if entry.get().is_empty() {
 						entry.remove_entry();
+						self.topic_names.remove(&topic.hash());
 
 						if let Err(error) = self.swarm.behaviour_mut().gossipsub.unsubscribe(&topic)
 						{
@@ -579,6 +779,51 @@ impl ValidatorNetwork {
 					);
 				}
 			},
+			Command::Health { sender } => {
+				let validators = self
+					.address_cache
+					.validator_ids()
+					.map(|validator_id| {
+						let connected = self
+							.address_cache
+							.validator_addresses(validator_id)
+							.unwrap_or_default()
+							.iter()
+							.any(|peer_id| self.swarm.is_connected(peer_id));
+						let status = if connected {
+							ConnectionStatus::Connected
+						} else {
+							ConnectionStatus::Disconnected
+						};
+						(validator_id.clone(), status)
+					})
+					.collect();
+				let health = NetworkHealth {
+					connected_peers: self.swarm.connected_peers().count(),
+					known_validators: self.address_cache.len(),
+					validators,
+					gossip: self.topic_traffic.clone(),
+				};
+				if sender.send(health).is_err() {
+					debug!("Failed to send health result");
+				}
+			},
+			Command::ListSubscriptions { sender } => {
+				// Report what gossipsub is subscribed to, rather than what was requested.
+				let mut topics: Vec<String> = self
+					.swarm
+					.behaviour()
+					.gossipsub
+					.topics()
+					.map(|hash| {
+						self.topic_names.get(hash).cloned().unwrap_or_else(|| hash.to_string())
+					})
+					.collect();
+				topics.sort();
+				if sender.send(topics).is_err() {
+					debug!("Failed to send subscriptions result");
+				}
+			},
 		}
 	}
 }
@@ -594,19 +839,14 @@ fn serialize_addresses(addresses: impl Iterator<Item = Multiaddr>) -> Vec<Vec<u8
 	addresses.map(|a| a.to_vec()).collect()
 }
 
-fn deserialize_addresses(encoded_addresses: Vec<Vec<u8>>) -> Result<Vec<Multiaddr>, String> {
-	encoded_addresses
-		.into_iter()
-		.map(|bytes| Multiaddr::try_from(bytes).map_err(|e| e.to_string()))
-		.collect()
-}
-
 #[derive(Clone)]
 pub(crate) struct Metrics {
 	requests: Counter<U64>,
 	requests_total: CounterVec<U64>,
 	requests_pending: Gauge<U64>,
 	dht_event_received: CounterVec<U64>,
+	gossip_messages: CounterVec<U64>,
+	gossip_message_bytes: HistogramVec,
 }
 
 impl Metrics {
@@ -648,6 +888,128 @@ impl Metrics {
 				)?,
 				registry,
 			)?,
+			gossip_messages: register(
+				CounterVec::new(
+					Opts::new(
+						"redot_validator_network_gossip_messages_total",
+						"Total number of gossip messages in the validator network",
+					),
+					&["topic", "direction"],
+				)?,
+				registry,
+			)?,
+			gossip_message_bytes: register(
+				HistogramVec::new(
+					HistogramOpts::new(
+						"redot_validator_network_gossip_message_bytes",
+						"Size in bytes of the gossip messages in the validator network",
+					)
+					.buckets(exponential_buckets(64.0, 4.0, 8)?),
+					&["topic", "direction"],
+				)?,
+				registry,
+			)?,
 		})
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::ValidatorNetworkConfig;
+	use codec::Decode;
+	use sp_core::crypto::key_types;
+	use sp_keystore::{testing::MemoryKeystore, Keystore};
+
+	// Signs a record for a new validator listing a single unreachable address.
+	fn validator_record(key_store: &MemoryKeystore) -> Record {
+		let public = key_store.sr25519_generate_new(key_types::AUTHORITY_DISCOVERY, None).unwrap();
+		let address: Multiaddr =
+			format!("/ip4/127.0.0.1/tcp/1/p2p/{}", PeerId::random()).parse().unwrap();
+		let signed = SignedValidatorRecord {
+			record: vec![address.to_vec()],
+			validator_id: public.into(),
+			auth_signature: key_store
+				.sr25519_sign(key_types::AUTHORITY_DISCOVERY, &public, &address.to_vec())
+				.unwrap()
+				.unwrap()
+				.encode(),
+		};
+		Record::new(SignedValidatorRecord::key(&signed.validator_id), signed.encode())
+	}
+
+	#[tokio::test]
+	async fn discovered_validators_are_dialed_within_the_parallel_limit() {
+		let parallel_limit = 3;
+		let config = ValidatorNetworkConfig {
+			listen_addr: "127.0.0.1".to_string(),
+			listen_port: 0,
+			parallel_limit,
+			..Default::default()
+		};
+		let (_service, mut network) = crate::default(Some(config), None).unwrap();
+		let key_store = MemoryKeystore::new();
+
+		for _ in 0..10 {
+			network.handle_record(&validator_record(&key_store));
+			assert!(network.dial_queue.in_flight() <= parallel_limit);
+		}
+
+		assert_eq!(network.dial_queue.in_flight(), parallel_limit);
+		// None of them joins gossip before its dial connects.
+		assert_eq!(network.pending_gossip_peers.len(), 10);
+	}
+
+	#[tokio::test]
+	async fn removed_validators_are_not_dialed() {
+		let config = ValidatorNetworkConfig {
+			listen_addr: "127.0.0.1".to_string(),
+			listen_port: 0,
+			parallel_limit: 1,
+			..Default::default()
+		};
+		let (_service, mut network) = crate::default(Some(config), None).unwrap();
+		let key_store = MemoryKeystore::new();
+
+		let mut validators = Vec::new();
+		for _ in 0..3 {
+			let record = validator_record(&key_store);
+			network.handle_record(&record);
+			let signed = SignedValidatorRecord::decode(&mut &record.value[..]).unwrap();
+			validators.push(signed.validator_id);
+		}
+		assert_eq!(network.dial_queue.pending(), 2);
+
+		network.handle_command(Command::RemoveValidators { validators }).await;
+		assert_eq!(network.dial_queue.pending(), 0);
+		assert!(network.pending_gossip_peers.is_empty());
+	}
+
+	#[tokio::test]
+	async fn clearing_validators_drops_preconnects_and_queued_dials() {
+		let config = ValidatorNetworkConfig {
+			listen_addr: "127.0.0.1".to_string(),
+			listen_port: 0,
+			parallel_limit: 1,
+			..Default::default()
+		};
+		let (_service, mut network) = crate::default(Some(config), None).unwrap();
+		let key_store = MemoryKeystore::new();
+
+		for _ in 0..3 {
+			network.handle_record(&validator_record(&key_store));
+		}
+		let unknown: ValidatorId =
+			key_store.sr25519_generate_new(key_types::AUTHORITY_DISCOVERY, None).unwrap().into();
+		network.handle_command(Command::Preconnect { validators: vec![unknown] }).await;
+		assert_eq!(network.preconnecting.len(), 1);
+		assert_eq!(network.dial_queue.pending(), 2);
+
+		let (sender, receiver) = oneshot::channel();
+		network.handle_command(Command::ClearValidators { sender }).await;
+		assert_eq!(receiver.await.unwrap().unwrap(), 3);
+		assert!(network.preconnecting.is_empty());
+		assert_eq!(network.dial_queue.pending(), 0);
+		assert_eq!(network.dial_queue.in_flight(), 1);
+	}
+}
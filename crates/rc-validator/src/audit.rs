@@ -0,0 +1,150 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Append-only audit log of the key rotations and signatures completed by the worker.
+//!
+//! The log is a file with one JSON entry per line, so it can also be inspected with standard
+//! tools.
+
+use crate::Identifier;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::BTreeSet,
+	fs::OpenOptions,
+	io::Write,
+	path::{Path, PathBuf},
+};
+
+/// What an audit entry records.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AuditRecord {
+	/// A key rotation completed.
+	KeyRotated {
+		/// The new group verifying key, hex encoded.
+		verifying_key: String,
+	},
+	/// A signing round completed.
+	Signed {
+		/// The signed message, hex encoded.
+		message: String,
+		/// The aggregated signature, hex encoded.
+		signature: String,
+	},
+}
+
+/// An entry of the audit log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+	/// When the round completed, in seconds since the Unix epoch.
+	pub at: u64,
+	/// Identifiers of the participants of the round, including the local node, hex encoded.
+	pub participants: Vec<String>,
+	/// What the round produced.
+	#[serde(flatten)]
+	pub record: AuditRecord,
+}
+
+impl AuditEntry {
+	/// Creates an entry for a round completed at `at` by `participants`.
+	pub fn new(at: u64, participants: &BTreeSet<Identifier>, record: AuditRecord) -> Self {
+		let participants = participants.iter().map(|id| to_hex(&id.serialize())).collect();
+		AuditEntry { at, participants, record }
+	}
+}
+
+/// Encodes `bytes` as lowercase hex, as stored in the audit entries.
+pub fn to_hex(bytes: &[u8]) -> String {
+	bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// An audit log stored in a file.
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+	path: PathBuf,
+}
+
+impl AuditLog {
+	/// Opens the audit log at `path`, creating the file if it does not exist.
+	pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+		let path = path.as_ref().to_path_buf();
+		OpenOptions::new()
+			.create(true)
+			.append(true)
+			.open(&path)
+			.with_context(|| format!("Failed to open audit log {}", path.display()))?;
+		Ok(AuditLog { path })
+	}
+
+	/// Appends `entry` to the log.
+	pub fn append(&self, entry: &AuditEntry) -> Result<()> {
+		let mut line = serde_json::to_vec(entry)?;
+		line.push(b'\n');
+		let mut file = OpenOptions::new()
+			.append(true)
+			.open(&self.path)
+			.with_context(|| format!("Failed to open audit log {}", self.path.display()))?;
+		// A single write keeps the entry on one line even if another process appends too.
+		file.write_all(&line)?;
+		Ok(())
+	}
+
+	/// Returns the last `n` entries of the log, oldest first.
+	///
+	/// The whole file is read, so this is meant for occasional inspection by operators.
+	/// Lines that cannot be parsed, such as one cut short by a crash, are skipped.
+	pub fn tail(&self, n: usize) -> Result<Vec<AuditEntry>> {
+		let content = std::fs::read_to_string(&self.path)
+			.with_context(|| format!("Failed to read audit log {}", self.path.display()))?;
+		let entries = content
+			.lines()
+			.filter_map(|line| serde_json::from_str::<AuditEntry>(line).ok())
+			.collect::<Vec<_>>();
+		let skip = entries.len().saturating_sub(n);
+		Ok(entries.into_iter().skip(skip).collect())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn tail_returns_the_last_entries_in_order() {
+		let path = std::env::temp_dir().join(format!("rc-validator-audit-{}", std::process::id()));
+		let _ = std::fs::remove_file(&path);
+		let log = AuditLog::open(&path).unwrap();
+		let participants = [1u16, 2]
+			.iter()
+			.map(|id| Identifier::try_from(*id).unwrap())
+			.collect::<BTreeSet<_>>();
+
+		for at in 0..3 {
+			let message = to_hex(&[at as u8]);
+			let record = AuditRecord::Signed { message, signature: "00".into() };
+			log.append(&AuditEntry::new(at, &participants, record)).unwrap();
+		}
+		let key = AuditRecord::KeyRotated { verifying_key: "ab".into() };
+		log.append(&AuditEntry::new(3, &participants, key.clone())).unwrap();
+
+		let tail = log.tail(2).unwrap();
+		assert_eq!(tail.iter().map(|entry| entry.at).collect::<Vec<_>>(), vec![2, 3]);
+		assert_eq!(tail[1].record, key);
+		assert_eq!(tail[1].participants.len(), 2);
+		assert_eq!(log.tail(10).unwrap().len(), 4);
+
+		std::fs::remove_file(&path).unwrap();
+	}
+}
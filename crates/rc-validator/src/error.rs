@@ -0,0 +1,51 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+/// Errors returned by the `Worker` that callers may want to handle explicitly.
+///
+/// They are carried inside `anyhow::Error` and can be recovered with `downcast_ref::<Error>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+	/// Not enough validators are connected to reach the signing threshold.
+	InsufficientParticipants { have: usize, need: usize },
+	/// The key rotation was aborted before it completed.
+	DkgAborted,
+	/// The signing round was aborted before it completed.
+	SignAborted,
+	/// The request was replaced by a newer one before it completed.
+	Superseded,
+	/// The worker is shutting down and no longer starts new rounds.
+	ShuttingDown,
+	/// The audit log is not enabled in the worker configuration.
+	AuditLogDisabled,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Error::InsufficientParticipants { have, need } => {
+				write!(f, "Insufficient participants: have {}, need {}", have, need)
+			},
+			Error::DkgAborted => write!(f, "DKG aborted"),
+			Error::SignAborted => write!(f, "Signing aborted"),
+			Error::Superseded => write!(f, "Superseded by a newer request"),
+			Error::ShuttingDown => write!(f, "Worker is shutting down"),
+			Error::AuditLogDisabled => write!(f, "Audit log is disabled"),
+		}
+	}
+}
+
+impl std::error::Error for Error {}
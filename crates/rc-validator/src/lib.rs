@@ -21,15 +21,27 @@ use futures::channel::mpsc;
 use rc_validator_network::Service as ValidatorNetworkService;
 use std::sync::Arc;
 
-pub(crate) use redot_core_primitives::{DkgSignature, DkgVerifyingKey, Identifier};
+pub use redot_core_primitives::{
+    ciphersuite::{AnySignature, AnyVerifyingKey, Ciphersuite},
+    DkgSignature, DkgVerifyingKey, Identifier,
+};
 pub(crate) use shared::Command;
 
+mod audit;
+mod error;
 mod service;
 mod shared;
 mod worker;
 
+pub use audit::{AuditEntry, AuditLog, AuditRecord};
+pub use error::Error;
+pub use redot_core_primitives::clock::{Clock, ManualClock, SystemClock};
+pub use shared::{
+    DkgProgress, DkgRehearsalReport, KeyRotated, RoundDuration, RoundKind, RoundOutcome,
+    SigningReadiness,
+};
 pub use service::Service;
-pub use worker::Worker;
+pub use worker::{Worker, WorkerConfig};
 
 /// Creates a new validator network service and a worker to handle requests.
 /// 
@@ -52,9 +64,20 @@ pub use worker::Worker;
 pub fn new_validator_network_service(
     validator_id: ValidatorId,
     network: Arc<ValidatorNetworkService>,
+) -> Result<(Service, Worker)> {
+    new_validator_network_service_with_config(validator_id, network, WorkerConfig::default())
+}
+
+/// Creates a new validator network service and a worker configured with `config`.
+///
+/// See [`new_validator_network_service`] for details.
+pub fn new_validator_network_service_with_config(
+    validator_id: ValidatorId,
+    network: Arc<ValidatorNetworkService>,
+    config: WorkerConfig,
 ) -> Result<(Service, Worker)> {
     let (to_worker, from_service) = mpsc::channel(8);
-    let service = Service::new(to_worker.clone());
-    let worker = Worker::new(network, validator_id, from_service)?;
+    let worker = Worker::with_config(network, validator_id, from_service, config)?;
+    let service = Service::new(to_worker, worker.key_rotations(), worker.round_durations());
     Ok((service, worker))
 }
@@ -12,7 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{Command, DkgSignature, DkgVerifyingKey};
+use crate::{
+    AnySignature, AnyVerifyingKey, AuditEntry, Ciphersuite, Command, DkgProgress,
+    DkgRehearsalReport, Identifier, KeyRotated, RoundDuration, SigningReadiness,
+};
 use anyhow::{Context, Result};
 use cumulus_primitives_core::relay_chain::ValidatorId;
 use futures::{
@@ -20,7 +23,8 @@ use futures::{
     SinkExt,
 };
 
-use std::fmt::Debug;
+use std::{fmt::Debug, time::Duration};
+use tokio::sync::broadcast;
 
 /// `Service` acts as an intermediary for interacting with a Worker. It handles requests and
 /// facilitates communication between the service and the worker through a message-passing mechanism.
@@ -31,6 +35,10 @@ use std::fmt::Debug;
 pub struct Service {
     // Channel sender used to send commands to the worker.
     to_worker: mpsc::Sender<Command>,
+    // Broadcast channel on which the worker announces completed key rotations.
+    key_rotations: broadcast::Sender<KeyRotated>,
+    // Broadcast channel on which the worker announces how long each round took.
+    round_durations: broadcast::Sender<RoundDuration>,
 }
 
 impl Debug for Service {
@@ -48,8 +56,36 @@ impl Service {
     /// # Arguments
     ///
     /// * `to_worker` - A sender channel used for sending commands to the worker.
-    pub(crate) fn new(to_worker: mpsc::Sender<Command>) -> Self {
-        Self { to_worker }
+    /// * `key_rotations` - The broadcast channel of completed key rotations.
+    /// * `round_durations` - The broadcast channel of round durations.
+    pub(crate) fn new(
+        to_worker: mpsc::Sender<Command>,
+        key_rotations: broadcast::Sender<KeyRotated>,
+        round_durations: broadcast::Sender<RoundDuration>,
+    ) -> Self {
+        Self { to_worker, key_rotations, round_durations }
+    }
+
+    /// Subscribes to completed key rotations.
+    ///
+    /// A `KeyRotated` notification is received each time a rotation finishes, whoever requested
+    /// it. Only rotations completing after the subscription are received.
+    pub fn subscribe_key_rotations(&self) -> broadcast::Receiver<KeyRotated> {
+        self.key_rotations.subscribe()
+    }
+
+    /// Subscribes to the durations of DKG and signing rounds.
+    ///
+    /// A `RoundDuration` is received each time a round ends, whether it completed, failed or was
+    /// aborted. The durations of aborted rounds show how long rounds ran before callers gave up,
+    /// which helps choosing timeouts that fit the network.
+    pub fn subscribe_round_durations(&self) -> broadcast::Receiver<RoundDuration> {
+        self.round_durations.subscribe()
+    }
+
+    /// Returns the ciphersuites `setup` accepts.
+    pub fn supported_ciphersuites() -> &'static [Ciphersuite] {
+        Ciphersuite::SUPPORTED
     }
 
     /// Initiates a key rotation process, resulting in a new verifier public key.
@@ -58,12 +94,78 @@ impl Service {
     ///
     /// # Returns
     ///
-    /// A `Result` which, on success, contains the new `DkgVerifyingKey`.
-    pub async fn rotate_key(&self) -> Result<DkgVerifyingKey> {
+    /// A `Result` which, on success, contains the new key, of the ciphersuite selected with
+    /// `setup`.
+    pub async fn rotate_key(&self) -> Result<AnyVerifyingKey> {
+        let (sender, receiver) = oneshot::channel();
+        self.to_worker
+            .clone()
+            .send(Command::RotateKey { progress: None, sender })
+            .await
+            .context("Failed to send command to worker")?;
+        receiver.await.context("Failed to receive response from worker")?
+    }
+
+    /// Initiates a key rotation and reports its progress.
+    ///
+    /// Unlike `rotate_key`, this method returns as soon as the rotation has started, so the caller
+    /// decides how long to wait for the result and may call `abort_dkg` to cancel it.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which, on success, contains a stream of `DkgProgress` notifications and a
+    /// receiver resolving to the new `AnyVerifyingKey`. The stream ends when the rotation
+    /// completes or is aborted.
+    pub async fn rotate_key_with_progress(
+        &self,
+    ) -> Result<(mpsc::UnboundedReceiver<DkgProgress>, oneshot::Receiver<Result<AnyVerifyingKey>>)>
+    {
+        let (progress, progress_receiver) = mpsc::unbounded();
+        let (sender, receiver) = oneshot::channel();
+        self.to_worker
+            .clone()
+            .send(Command::RotateKey { progress: Some(progress), sender })
+            .await
+            .context("Failed to send command to worker")?;
+        Ok((progress_receiver, receiver))
+    }
+
+    /// Aborts the key rotation in progress, if any.
+    ///
+    /// The pending rotation resolves with `Error::DkgAborted` and the previous key stays in use.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating the success or failure of the operation.
+    pub async fn abort_dkg(&self) -> Result<()> {
+        let (sender, receiver) = oneshot::channel();
+        self.to_worker
+            .clone()
+            .send(Command::AbortDkg { sender })
+            .await
+            .context("Failed to send command to worker")?;
+        receiver.await.context("Failed to receive response from worker")?
+    }
+
+    /// Rehearses a key rotation, checking that all participants can exchange part-1 messages.
+    ///
+    /// Every participant answers the rehearsal with a part-1 package generated for the occasion,
+    /// which is then discarded: no key is generated and the current key stays in use. Run it
+    /// before `rotate_key` to find unreachable participants while the rotation can still wait.
+    ///
+    /// The rehearsal ends once every participant answered, or once
+    /// `WorkerConfig::rehearsal_timeout` has elapsed. Answers to the rehearsals of other nodes
+    /// are not counted.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which, on success, contains the `DkgRehearsalReport` of the participants that
+    /// answered, or an error if the key generation has fewer than two participants.
+    pub async fn dkg_rehearsal(&self) -> Result<DkgRehearsalReport> {
         let (sender, receiver) = oneshot::channel();
         self.to_worker
             .clone()
-            .send(Command::RotateKey { sender })
+            .send(Command::DkgRehearsal { sender })
             .await
             .context("Failed to send command to worker")?;
         receiver.await.context("Failed to receive response from worker")?
@@ -79,8 +181,9 @@ impl Service {
     ///
     /// # Returns
     ///
-    /// A `Result` which, on success, contains the `DkgSignature`.
-    pub async fn start_signing(&self, message: &[u8]) -> Result<DkgSignature> {
+    /// A `Result` which, on success, contains the signature, of the ciphersuite selected with
+    /// `setup`.
+    pub async fn start_signing(&self, message: &[u8]) -> Result<AnySignature> {
         let (sender, receiver) = oneshot::channel();
         self.to_worker
             .clone()
@@ -90,20 +193,81 @@ impl Service {
         receiver.await.context("Failed to receive response from worker")?
     }
 
+    /// Returns the message of the signing round in progress.
+    ///
+    /// This is useful to check that a stuck round is signing the expected metadata rather than
+    /// a stale request.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which, on success, contains the bytes being signed, or `None` if no signing
+    /// round is in progress.
+    pub async fn active_sign_message(&self) -> Result<Option<Vec<u8>>> {
+        let (sender, receiver) = oneshot::channel();
+        self.to_worker
+            .clone()
+            .send(Command::ActiveSignMessage { sender })
+            .await
+            .context("Failed to send command to worker")?;
+        receiver.await.context("Failed to receive response from worker")
+    }
+
+    /// Returns the FROST identifier of the local validator.
+    ///
+    /// DKG and signing messages carry the identifier of their sender, so this maps protocol
+    /// messages, such as those of a missing participant, to nodes.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which, on success, contains the local `Identifier`.
+    pub async fn local_identifier(&self) -> Result<Identifier> {
+        let (sender, receiver) = oneshot::channel();
+        self.to_worker
+            .clone()
+            .send(Command::LocalIdentifier { sender })
+            .await
+            .context("Failed to send command to worker")?;
+        receiver.await.context("Failed to receive response from worker")
+    }
+
+    /// Reports whether enough validators are connected to reach the signing threshold.
+    ///
+    /// `start_signing` fails while the network is not ready, so callers can check this first
+    /// and wait for validators to come back online instead.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which, on success, contains the current `SigningReadiness`.
+    pub async fn signing_readiness(&self) -> Result<SigningReadiness> {
+        let (sender, receiver) = oneshot::channel();
+        self.to_worker
+            .clone()
+            .send(Command::SigningReadiness { sender })
+            .await
+            .context("Failed to send command to worker")?;
+        receiver.await.context("Failed to receive response from worker")?
+    }
+
     /// Sets up the validator network with specified threshold and total number of participants.
     ///
+    /// The key generation and signing run `ciphersuite`, which must be one of
+    /// `supported_ciphersuites` and the same on every participant. Only keys and signatures of
+    /// `Ciphersuite::Ed25519` are verified by the task pallet. Switching to another ciphersuite
+    /// discards the current key, and is refused while a round is in progress.
+    ///
     /// # Arguments
     ///
     /// * `nt` - A tuple (u16, u16) where the first element is the threshold and the second is the total number of participants.
+    /// * `ciphersuite` - The ciphersuite of the key generation and signing.
     ///
     /// # Returns
     ///
     /// A `Result` indicating the success or failure of the operation.
-    pub async fn setup(&self, nt: (u16, u16)) -> Result<()> {
+    pub async fn setup(&self, nt: (u16, u16), ciphersuite: Ciphersuite) -> Result<()> {
         let (sender, receiver) = oneshot::channel();
         self.to_worker
             .clone()
-            .send(Command::Setup { nt, sender })
+            .send(Command::Setup { nt, ciphersuite, sender })
             .await
             .context("Failed to send command to worker")?;
         receiver.await.context("Failed to receive response from worker")?
@@ -146,5 +310,85 @@ impl Service {
             .context("Failed to send command to worker")?;
         receiver.await.context("Failed to receive response from worker")?
     }
-}
 
+    /// Connects to validators ahead of time, without including them in the validator network.
+    ///
+    /// Use it before a scheduled key rotation, so that connections are already established when
+    /// the validators are added with `add_validators` and the DKG starts.
+    ///
+    /// # Arguments
+    ///
+    /// * `validators` - A vector of `ValidatorId` representing the validators to connect to.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating the success or failure of the operation.
+    pub async fn preconnect(&self, validators: Vec<ValidatorId>) -> Result<()> {
+        let (sender, receiver) = oneshot::channel();
+        self.to_worker
+            .clone()
+            .send(Command::Preconnect { validators, sender })
+            .await
+            .context("Failed to send command to worker")?;
+        receiver.await.context("Failed to receive response from worker")?
+    }
+
+    /// Shuts the worker down, letting the round in progress finish first.
+    ///
+    /// New key rotations and signing requests are rejected with `Error::ShuttingDown` from now
+    /// on. The worker stops once the key rotation or signing round in progress completes, or
+    /// once `grace` has elapsed, in which case the round is aborted.
+    ///
+    /// # Arguments
+    ///
+    /// * `grace` - How long the round in progress may take to complete.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` resolved when the worker stops, which is an error if a round had to be aborted
+    /// or a shutdown was already in progress.
+    pub async fn begin_shutdown(&self, grace: Duration) -> Result<()> {
+        let (sender, receiver) = oneshot::channel();
+        self.to_worker
+            .clone()
+            .send(Command::BeginShutdown { grace, sender })
+            .await
+            .context("Failed to send command to worker")?;
+        receiver.await.context("Failed to receive response from worker")?
+    }
+
+    /// Removes all validators from the network.
+    ///
+    /// This also resets any state that depends on the current membership: a key rotation or
+    /// signing round in progress is aborted, and its caller receives `Error::DkgAborted` or
+    /// `Error::SignAborted`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which, on success, contains the number of validators that were cleared.
+    pub async fn clear_validators(&self) -> Result<usize> {
+        let (sender, receiver) = oneshot::channel();
+        self.to_worker
+            .clone()
+            .send(Command::ClearValidators { sender })
+            .await
+            .context("Failed to send command to worker")?;
+        receiver.await.context("Failed to receive response from worker")?
+    }
+
+    /// Reads the last `n` entries of the audit log, oldest first.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which, on success, contains the entries. Fails with `Error::AuditLogDisabled`
+    /// if the worker was not configured with `WorkerConfig::audit_log`.
+    pub async fn audit_tail(&self, n: usize) -> Result<Vec<AuditEntry>> {
+        let (sender, receiver) = oneshot::channel();
+        self.to_worker
+            .clone()
+            .send(Command::AuditTail { n, sender })
+            .await
+            .context("Failed to send command to worker")?;
+        receiver.await.context("Failed to receive response from worker")?
+    }
+}
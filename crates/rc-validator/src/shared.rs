@@ -12,16 +12,115 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{DkgSignature, DkgVerifyingKey};
+use crate::{AnySignature, AnyVerifyingKey, AuditEntry, Ciphersuite, Identifier};
 use anyhow::Result;
 use cumulus_primitives_core::relay_chain::ValidatorId;
-use futures::channel::oneshot;
+use futures::channel::{mpsc, oneshot};
+use std::{collections::BTreeSet, time::Duration};
+
+/// Progress notifications emitted while a key rotation is running.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DkgProgress {
+	/// The local part-1 package has been generated and published.
+	Started,
+	/// A part-1 package has been received from a participant.
+	Part1Received { from: Identifier },
+	/// A part-2 package has been received from a participant.
+	Part2Received { from: Identifier },
+	/// The new verifying key has been generated.
+	Completed,
+}
+
+/// Notification broadcast to `Service` subscribers when a key rotation completes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyRotated {
+	/// The new group verifying key.
+	pub new_key: AnyVerifyingKey,
+}
+
+/// Kind of round run by the worker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundKind {
+	/// A key rotation, running the DKG.
+	Dkg,
+	/// A signing round.
+	Sign,
+}
+
+/// How a round ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundOutcome {
+	/// The round produced a key or a signature.
+	Completed,
+	/// The round failed with an error.
+	Failed,
+	/// The round was aborted before completing, for instance by a caller whose timeout elapsed,
+	/// or superseded by a new request.
+	Aborted,
+}
+
+/// Notification broadcast to `Service` subscribers when a round ends, with how long it took.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoundDuration {
+	/// Kind of the round.
+	pub kind: RoundKind,
+	/// How the round ended.
+	pub outcome: RoundOutcome,
+	/// Time from the start of the round to its end.
+	pub elapsed: Duration,
+}
+
+/// Whether enough validators are connected to run a signing round.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SigningReadiness {
+	/// Number of participants available, including the local node.
+	pub participants: usize,
+	/// Number of participants the signing threshold requires.
+	pub threshold: usize,
+}
+
+impl SigningReadiness {
+	/// Returns `true` if a signing round can reach the threshold.
+	pub fn is_ready(&self) -> bool {
+		self.participants >= self.threshold
+	}
+}
+
+/// Participants that answered a DKG rehearsal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DkgRehearsalReport {
+	/// Number of participants of the key generation, including the local node.
+	pub participants: usize,
+	/// Identifiers of the participants whose rehearsal package was received.
+	pub responded: BTreeSet<Identifier>,
+}
+
+impl DkgRehearsalReport {
+	/// Returns `true` if every other participant answered, so a key rotation can go through.
+	///
+	/// A key generation with fewer than two participants is never complete.
+	pub fn is_complete(&self) -> bool {
+		self.participants >= 2 && self.responded.len() + 1 >= self.participants
+	}
+}
 
 #[derive(Debug)]
 pub enum Command {
-	RotateKey { sender: oneshot::Sender<Result<DkgVerifyingKey>> },
-	Sign { message: Vec<u8>, sender: oneshot::Sender<Result<DkgSignature>> },
-	Setup { nt: (u16, u16), sender: oneshot::Sender<Result<()>> },
+	RotateKey {
+		progress: Option<mpsc::UnboundedSender<DkgProgress>>,
+		sender: oneshot::Sender<Result<AnyVerifyingKey>>,
+	},
+	AbortDkg { sender: oneshot::Sender<Result<()>> },
+	DkgRehearsal { sender: oneshot::Sender<Result<DkgRehearsalReport>> },
+	Sign { message: Vec<u8>, sender: oneshot::Sender<Result<AnySignature>> },
+	ActiveSignMessage { sender: oneshot::Sender<Option<Vec<u8>>> },
+	LocalIdentifier { sender: oneshot::Sender<Identifier> },
+	SigningReadiness { sender: oneshot::Sender<Result<SigningReadiness>> },
+	Setup { nt: (u16, u16), ciphersuite: Ciphersuite, sender: oneshot::Sender<Result<()>> },
 	RemoveValidators { validators: Vec<ValidatorId>, sender: oneshot::Sender<Result<()>> },
 	AddValidators { validators: Vec<ValidatorId>, sender: oneshot::Sender<Result<()>> },
+	Preconnect { validators: Vec<ValidatorId>, sender: oneshot::Sender<Result<()>> },
+	ClearValidators { sender: oneshot::Sender<Result<usize>> },
+	BeginShutdown { grace: Duration, sender: oneshot::Sender<Result<()>> },
+	AuditTail { n: usize, sender: oneshot::Sender<Result<Vec<AuditEntry>>> },
 }
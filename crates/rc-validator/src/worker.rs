@@ -12,22 +12,76 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{Command, DkgSignature, DkgVerifyingKey, Identifier};
+use crate::{
+	audit::{to_hex, AuditEntry, AuditLog, AuditRecord},
+	AnySignature, AnyVerifyingKey, Ciphersuite, Command, DkgProgress, DkgRehearsalReport, Error,
+	Identifier, KeyRotated, RoundDuration, RoundKind, RoundOutcome, SigningReadiness,
+};
 use anyhow::{Ok as AnyOk, Result};
 use cumulus_primitives_core::relay_chain::ValidatorId;
+use frost_ed25519::Ed25519Sha512;
+use frost_ristretto255::Ristretto255Sha512;
 use futures::{
 	channel::{mpsc, oneshot},
 	stream::StreamExt,
+	FutureExt,
+};
+use log::{debug, error, info, warn};
+use rc_validator_network::{
+	Arc, ConnectionStatus, NetworkHealth, Service as ValidatorNetworkService,
+};
+use redot_core_primitives::{
+	ciphersuite::{CiphersuiteDkg, SupportedCiphersuite},
+	clock::{Clock, SystemClock},
+	crypto::{DkgEquivocation, DkgMessage, FrostDkg, SignMessage},
 };
-use log::{debug, error};
-use rc_validator_network::{Arc, Service as ValidatorNetworkService};
-use redot_core_primitives::crypto::{DkgMessage, FrostDkg, SignMessage};
 use serde::Serialize;
+use std::{
+	collections::{BTreeSet, VecDeque},
+	path::PathBuf,
+	time::{Duration, Instant},
+};
+use tokio::sync::broadcast;
 
 // Represents different types of responses that can be sent back from the Worker.
 enum QueryResultSender {
-	RotateKey(oneshot::Sender<Result<DkgVerifyingKey>>),
-	Sign(oneshot::Sender<Result<DkgSignature>>),
+	RotateKey(oneshot::Sender<Result<AnyVerifyingKey>>),
+	Sign(oneshot::Sender<Result<AnySignature>>),
+}
+
+impl QueryResultSender {
+	// Resolves the request with `Error::Superseded`.
+	fn supersede(self) {
+		let sent = match self {
+			QueryResultSender::RotateKey(ch) => ch.send(Err(Error::Superseded.into())).is_ok(),
+			QueryResultSender::Sign(ch) => ch.send(Err(Error::Superseded.into())).is_ok(),
+		};
+		if !sent {
+			debug!("Failed to send result");
+		}
+	}
+}
+
+// A shutdown requested with `Command::BeginShutdown`, waiting for the round in progress.
+struct PendingShutdown {
+	deadline: Instant,
+	sender: oneshot::Sender<Result<()>>,
+}
+
+// A rehearsal started with `Command::DkgRehearsal`, collecting the participants that answer.
+struct PendingRehearsal {
+	nonce: u64,
+	deadline: Instant,
+	responded: BTreeSet<Identifier>,
+	sender: oneshot::Sender<Result<DkgRehearsalReport>>,
+}
+
+// Stores `sender` as the pending request, resolving the request it replaces, if any, so that its
+// caller does not wait forever.
+fn replace_sender(pending: &mut Option<QueryResultSender>, sender: QueryResultSender) {
+	if let Some(previous) = pending.replace(sender) {
+		previous.supersede();
+	}
 }
 
 // Macro to handle sending responses back to the requestor.
@@ -41,22 +95,83 @@ macro_rules! handle_send {
 	};
 }
 
+/// Configuration for the `Worker`.
+#[derive(Clone, Debug)]
+pub struct WorkerConfig {
+	/// Maximum time to wait for the network to accept a published message.
+	///
+	/// When it elapses the message is dropped and the worker moves on, so a slow network does not
+	/// block the handling of other commands and messages.
+	pub publish_timeout: Duration,
+	/// Number of times a failed publication is retried before it is dropped.
+	pub publish_retries: u32,
+	/// Delay before the first retry of a failed publication. It doubles after each attempt.
+	pub publish_retry_backoff: Duration,
+	/// How long a DKG rehearsal waits for the participants to answer.
+	pub rehearsal_timeout: Duration,
+	/// File recording each completed key rotation and signing round, with its participants.
+	/// `None` disables the audit log.
+	pub audit_log: Option<PathBuf>,
+}
+
+impl Default for WorkerConfig {
+	fn default() -> Self {
+		WorkerConfig {
+			publish_timeout: Duration::from_secs(10),
+			publish_retries: 3,
+			publish_retry_backoff: Duration::from_millis(500),
+			rehearsal_timeout: Duration::from_secs(30),
+			audit_log: None,
+		}
+	}
+}
+
+// A message whose publication failed and is waiting to be retried.
+struct PendingPublish {
+	topic: &'static str,
+	context: &'static str,
+	message: Vec<u8>,
+	attempts: u32,
+	retry_at: Instant,
+}
+
 /// The Worker struct represents a worker in the network that handles various tasks.
 ///
 /// It processes commands and messages related to DKG (Distributed Key Generation) and signing,
 /// interacting with the FrostDkg protocol for cryptographic operations.
 pub struct Worker {
 	network: Arc<ValidatorNetworkService>,
-	frost_dkg: FrostDkg,
+	validator_id: ValidatorId,
+	frost_dkg: CiphersuiteDkg,
 	command_receiver: mpsc::Receiver<Command>,
 	dkg_sender: Option<QueryResultSender>,
+	dkg_progress: Option<mpsc::UnboundedSender<DkgProgress>>,
 	sign_sender: Option<QueryResultSender>,
+	pending_publishes: VecDeque<PendingPublish>,
+	key_rotations: broadcast::Sender<KeyRotated>,
+	round_durations: broadcast::Sender<RoundDuration>,
+	dkg_started_at: Option<Instant>,
+	sign_started_at: Option<Instant>,
+	rehearsal: Option<PendingRehearsal>,
+	shutdown: Option<PendingShutdown>,
+	audit_log: Option<AuditLog>,
+	// Participants whose final package of the running round was received.
+	dkg_participants: BTreeSet<Identifier>,
+	sign_participants: BTreeSet<Identifier>,
+	config: WorkerConfig,
+	clock: Arc<dyn Clock>,
 }
 
 // Topics for DKG and signing messages.
 const DKG_TOPIC: &str = "dkg_topic";
 const SIGN_TOPIC: &str = "sign_topic";
 
+// Number of key rotation notifications buffered for slow subscribers.
+const KEY_ROTATIONS_CAPACITY: usize = 16;
+
+// Number of round duration notifications buffered for slow subscribers.
+const ROUND_DURATIONS_CAPACITY: usize = 64;
+
 impl Worker {
 	/// Creates a new Worker instance.
 	///
@@ -74,15 +189,80 @@ impl Worker {
 		validator_id: ValidatorId,
 		command_receiver: mpsc::Receiver<Command>,
 	) -> Result<Self> {
-		let id = Identifier::derive(validator_id.to_string().as_bytes())?;
-		let frost_dkg = FrostDkg::new(id);
-		AnyOk(Self { network, frost_dkg, command_receiver, dkg_sender: None, sign_sender: None })
+		Self::with_config(network, validator_id, command_receiver, WorkerConfig::default())
+	}
+
+	/// Creates a new Worker instance with the given configuration.
+	///
+	/// # Arguments
+	///
+	/// * `network` - Shared reference to the ValidatorNetworkService.
+	/// * `validator_id` - The unique identifier of the validator.
+	/// * `command_receiver` - Receiver for commands to be processed by the worker.
+	/// * `config` - The worker configuration.
+	///
+	/// # Returns
+	///
+	/// A result containing either the new Worker instance or an error.
+	pub fn with_config(
+		network: Arc<ValidatorNetworkService>,
+		validator_id: ValidatorId,
+		command_receiver: mpsc::Receiver<Command>,
+		config: WorkerConfig,
+	) -> Result<Self> {
+		let frost_dkg =
+			CiphersuiteDkg::new(Ciphersuite::default(), validator_id.to_string().as_bytes())?;
+		let audit_log = config.audit_log.as_ref().map(AuditLog::open).transpose()?;
+		AnyOk(Self {
+			network,
+			validator_id,
+			frost_dkg,
+			command_receiver,
+			dkg_sender: None,
+			dkg_progress: None,
+			sign_sender: None,
+			pending_publishes: VecDeque::new(),
+			key_rotations: broadcast::channel(KEY_ROTATIONS_CAPACITY).0,
+			round_durations: broadcast::channel(ROUND_DURATIONS_CAPACITY).0,
+			dkg_started_at: None,
+			sign_started_at: None,
+			rehearsal: None,
+			shutdown: None,
+			audit_log,
+			dkg_participants: BTreeSet::new(),
+			sign_participants: BTreeSet::new(),
+			config,
+			clock: Arc::new(SystemClock),
+		})
+	}
+
+	/// Replaces the clock used for deadlines, round durations and publish retries.
+	///
+	/// Workers use the system clock by default. Tests can pass a `ManualClock` to expire
+	/// deadlines without waiting.
+	pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+		self.clock = clock;
+	}
+
+	/// Returns the sender on which completed key rotations are broadcast.
+	pub(crate) fn key_rotations(&self) -> broadcast::Sender<KeyRotated> {
+		self.key_rotations.clone()
+	}
+
+	/// Returns the sender on which round durations are broadcast.
+	pub(crate) fn round_durations(&self) -> broadcast::Sender<RoundDuration> {
+		self.round_durations.clone()
 	}
 
 	/// Main loop of the worker, handling incoming DKG and signing messages, and commands.
+	///
+	/// Returns once a shutdown requested with `Service::begin_shutdown` completes.
 	pub async fn run(&mut self) -> Result<()> {
-		let mut dkg_receiver = self.network.subscribe(DKG_TOPIC).await?.receiver;
-		let mut sign_receiver = self.network.subscribe(SIGN_TOPIC).await?.receiver;
+		// Only messages from validators the network was told about take part in the rounds.
+		let mut dkg_receiver = self.network.subscribe_validators_only(DKG_TOPIC).await?.receiver;
+		let mut sign_receiver = self.network.subscribe_validators_only(SIGN_TOPIC).await?.receiver;
+		let mut retry_interval =
+			tokio::time::interval(self.config.publish_retry_backoff.max(Duration::from_millis(1)));
 
 		loop {
 			futures::select! {
@@ -95,18 +275,164 @@ impl Worker {
 				command = self.command_receiver.select_next_some() => {
 					self.handle_command(command).await;
 				},
+				_ = retry_interval.tick().fuse() => {
+					self.retry_publishes().await;
+				},
+			}
+
+			self.finish_rehearsal();
+			if self.finish_shutdown() {
+				return AnyOk(())
 			}
 		}
 	}
 
+	// Completes a pending shutdown once no round is in progress, or once its grace period has
+	// elapsed, in which case the round is aborted. The deadline is checked at least once per
+	// publish retry interval.
+	//
+	// Returns `true` if the worker should stop.
+	fn finish_shutdown(&mut self) -> bool {
+		let Some(shutdown) = &self.shutdown else {
+			return false
+		};
+		let in_progress = self.dkg_sender.is_some() || self.sign_sender.is_some();
+		if in_progress && self.clock.now() < shutdown.deadline {
+			return false
+		}
+
+		let result = if in_progress {
+			self.abort_rounds();
+			Err(anyhow::anyhow!("Shutdown grace period elapsed, the round in progress was aborted"))
+		} else {
+			AnyOk(())
+		};
+		if let Some(shutdown) = self.shutdown.take() {
+			if shutdown.sender.send(result).is_err() {
+				debug!("Failed to send result for BeginShutdown command");
+			}
+		}
+		true
+	}
+
+	// Reports the rehearsal in progress once every participant answered, or once its timeout has
+	// elapsed. The deadline is checked at least once per publish retry interval.
+	fn finish_rehearsal(&mut self) {
+		let participants = self.frost_dkg.participants() as usize;
+		let Some(rehearsal) = &self.rehearsal else {
+			return
+		};
+		let complete = participants >= 2 && rehearsal.responded.len() + 1 >= participants;
+		if !complete && self.clock.now() < rehearsal.deadline {
+			return
+		}
+
+		if let Some(rehearsal) = self.rehearsal.take() {
+			let report = DkgRehearsalReport { participants, responded: rehearsal.responded };
+			info!(
+				"DKG rehearsal ended with {} of {} participants",
+				report.responded.len() + 1,
+				participants
+			);
+			if rehearsal.sender.send(AnyOk(report)).is_err() {
+				debug!("Failed to send result for DkgRehearsal command");
+			}
+		}
+	}
+
+	// Aborts the key rotation and signing round in progress, if any.
+	fn abort_rounds(&mut self) {
+		if self.dkg_sender.is_some() {
+			self.frost_dkg.abort_dkg();
+			self.dkg_progress = None;
+			self.drop_pending_publishes(DKG_TOPIC);
+			self.record_round(RoundKind::Dkg, RoundOutcome::Aborted);
+			handle_send!(RotateKey, self.dkg_sender.take(), Err(Error::DkgAborted.into()));
+		}
+		if self.sign_sender.is_some() {
+			self.frost_dkg.abort_sign();
+			self.record_round(RoundKind::Sign, RoundOutcome::Aborted);
+			self.drop_pending_publishes(SIGN_TOPIC);
+			handle_send!(Sign, self.sign_sender.take(), Err(Error::SignAborted.into()));
+		}
+	}
+
 	// Handles commands received by the worker.
 	//
 	// Processes various commands like key rotation, signing, setup, and validator management.
 	async fn handle_command(&mut self, command: Command) {
 		match command {
-			Command::RotateKey { sender } => {
+			Command::RotateKey { sender, .. } if self.shutdown.is_some() => {
+				if sender.send(Err(Error::ShuttingDown.into())).is_err() {
+					debug!("Failed to send result");
+				}
+			},
+			Command::Sign { sender, .. } if self.shutdown.is_some() => {
+				if sender.send(Err(Error::ShuttingDown.into())).is_err() {
+					debug!("Failed to send result");
+				}
+			},
+			Command::DkgRehearsal { sender } if self.shutdown.is_some() => {
+				if sender.send(Err(Error::ShuttingDown.into())).is_err() {
+					debug!("Failed to send result");
+				}
+			},
+			Command::BeginShutdown { grace, sender } => {
+				if self.shutdown.is_some() {
+					if sender.send(Err(Error::ShuttingDown.into())).is_err() {
+						debug!("Failed to send result for BeginShutdown command");
+					}
+				} else {
+					let deadline = self.clock.now() + grace;
+					self.shutdown = Some(PendingShutdown { deadline, sender });
+				}
+			},
+			Command::RotateKey { progress, sender } => {
+				// The packages of the superseded round must not reach peers after the new ones.
+				if self.dkg_sender.is_some() {
+					self.drop_pending_publishes(DKG_TOPIC);
+					self.record_round(RoundKind::Dkg, RoundOutcome::Aborted);
+				}
+				self.dkg_progress = progress;
+				self.dkg_started_at = Some(self.clock.now());
+				self.dkg_participants.clear();
 				self.start_dkg().await;
-				self.dkg_sender = Some(QueryResultSender::RotateKey(sender));
+				replace_sender(&mut self.dkg_sender, QueryResultSender::RotateKey(sender));
+				self.report_dkg_progress(DkgProgress::Started);
+			},
+			Command::AbortDkg { sender } => {
+				if self.dkg_sender.is_some() {
+					self.frost_dkg.abort_dkg();
+					self.drop_pending_publishes(DKG_TOPIC);
+					self.record_round(RoundKind::Dkg, RoundOutcome::Aborted);
+					handle_send!(RotateKey, self.dkg_sender.take(), Err(Error::DkgAborted.into()));
+				}
+				self.dkg_progress = None;
+				if sender.send(AnyOk(())).is_err() {
+					debug!("Failed to send result for AbortDkg command");
+				}
+			},
+			Command::DkgRehearsal { sender } => {
+				let result = if self.rehearsal.is_some() {
+					Err(anyhow::anyhow!("Another DKG rehearsal is in progress"))
+				} else {
+					self.start_rehearsal().await
+				};
+				match result {
+					Ok(nonce) => {
+						self.rehearsal = Some(PendingRehearsal {
+							nonce,
+							deadline: self.clock.now() + self.config.rehearsal_timeout,
+							responded: BTreeSet::new(),
+							sender,
+						});
+					},
+					Err(e) => {
+						if sender.send(Err(e)).is_err() {
+							debug!("Failed to send result for DkgRehearsal command");
+						}
+					},
+				}
 			},
 			Command::Sign { message, sender } => {
 				if self.sign_sender.is_some() {
@@ -116,13 +442,39 @@ impl Worker {
 					{
 						debug!("Failed to send result");
 					}
+				} else if let Err(e) = self.ensure_sign_participants().await {
+					if sender.send(Err(e)).is_err() {
+						debug!("Failed to send result");
+					}
 				} else {
+					self.sign_started_at = Some(self.clock.now());
+					self.sign_participants.clear();
 					self.start_sign(message.as_slice()).await;
-					self.sign_sender = Some(QueryResultSender::Sign(sender));
+					replace_sender(&mut self.sign_sender, QueryResultSender::Sign(sender));
+				}
+			},
+			Command::ActiveSignMessage { sender } => {
+				let message = self
+					.sign_sender
+					.as_ref()
+					.map(|_| self.frost_dkg.sign_message().to_vec());
+				if sender.send(message).is_err() {
+					debug!("Failed to send result for ActiveSignMessage command");
+				}
+			},
+			Command::LocalIdentifier { sender } => {
+				if sender.send(self.frost_dkg.identifier()).is_err() {
+					debug!("Failed to send result for LocalIdentifier command");
+				}
+			},
+			Command::SigningReadiness { sender } => {
+				let result = self.signing_readiness().await;
+				if sender.send(result).is_err() {
+					debug!("Failed to send result for SigningReadiness command");
 				}
 			},
-			Command::Setup { nt, sender } => {
-				let result = self.frost_dkg.set_nt(nt.0, nt.1);
+			Command::Setup { nt, ciphersuite, sender } => {
+				let result = self.setup(nt, ciphersuite);
 				if sender.send(result).is_err() {
 					debug!("Failed to send Setup result");
 				}
@@ -139,42 +491,147 @@ impl Worker {
 					debug!("Failed to send result for AddValidators command");
 				}
 			},
+			Command::Preconnect { validators, sender } => {
+				let result = self.network.preconnect(validators).await;
+				if sender.send(result).is_err() {
+					debug!("Failed to send result for Preconnect command");
+				}
+			},
+			Command::AuditTail { n, sender } => {
+				let result = match &self.audit_log {
+					Some(audit_log) => audit_log.tail(n),
+					None => Err(Error::AuditLogDisabled.into()),
+				};
+				if sender.send(result).is_err() {
+					debug!("Failed to send result for AuditTail command");
+				}
+			},
+			Command::ClearValidators { sender } => {
+				self.abort_rounds();
+				let result = self.network.clear_validators().await;
+				if sender.send(result).is_err() {
+					debug!("Failed to send result for ClearValidators command");
+				}
+			},
 		}
 	}
 
+	// Sets the participants and threshold of the key generation, switching it to `ciphersuite`
+	// first if another one runs. Switching discards the current key, so it is refused while a
+	// round is in progress.
+	fn setup(&mut self, nt: (u16, u16), ciphersuite: Ciphersuite) -> Result<()> {
+		if ciphersuite == self.frost_dkg.ciphersuite() {
+			return self.frost_dkg.set_nt(nt.0, nt.1)
+		}
+		if self.dkg_sender.is_some() || self.sign_sender.is_some() || self.rehearsal.is_some() {
+			return Err(anyhow::anyhow!(
+				"Cannot switch to {} while a round is in progress",
+				ciphersuite
+			))
+		}
+		let mut frost_dkg =
+			CiphersuiteDkg::new(ciphersuite, self.validator_id.to_string().as_bytes())?;
+		frost_dkg.set_nt(nt.0, nt.1)?;
+		self.frost_dkg = frost_dkg;
+		info!("Switched the key generation to {}", ciphersuite);
+		AnyOk(())
+	}
+
+	// Returns the key generation, which runs `C` when called from the methods dispatched on the
+	// selected ciphersuite.
+	fn dkg<C: SupportedCiphersuite>(&mut self) -> &mut FrostDkg<C> {
+		C::frost_dkg(&mut self.frost_dkg).expect("calls are dispatched on the selected ciphersuite")
+	}
+
     // Processes DKG-related messages received by the worker.
     //
     // Handles different stages of the DKG process including part1 and part2 messages.
 	async fn handle_dkg_message(&mut self, message: Vec<u8>) {
-		match serde_json::from_slice::<DkgMessage>(&message) {
+		match self.frost_dkg.ciphersuite() {
+			Ciphersuite::Ed25519 => self.handle_dkg_message_as::<Ed25519Sha512>(message).await,
+			Ciphersuite::Ristretto255 => {
+				self.handle_dkg_message_as::<Ristretto255Sha512>(message).await
+			},
+		}
+	}
+
+	// Processes a DKG-related message of the ciphersuite `C`.
+	async fn handle_dkg_message_as<C: SupportedCiphersuite>(&mut self, message: Vec<u8>)
+	where
+		frost_core::Error<C>: Send + Sync,
+		frost_core::Identifier<C>: Send + Sync,
+	{
+		match serde_json::from_slice::<DkgMessage<C>>(&message) {
 			Ok(message) => match message {
 				DkgMessage::DkgPart1(dkg_part1_message) => {
-					match self.frost_dkg.dkg_part1(dkg_part1_message) {
-						Ok(msg) => {
-							if let Err(e) = self.serialize_and_publish(DKG_TOPIC, &msg).await {
-								error!("Failed to publish DKG Part1 message: {}", e);
+					self.report_dkg_progress(DkgProgress::Part1Received {
+						from: C::identifier(dkg_part1_message.id),
+					});
+					match self.dkg::<C>().dkg_part1(dkg_part1_message) {
+						// All part-1 packages are in, publish our part-2 packages.
+						Ok(Some(msg)) => {
+							if let Err(e) =
+								self.serialize_and_publish(DKG_TOPIC, "DKG Part2", &msg).await
+							{
+								error!("Failed to publish DKG Part2 message: {}", e);
 							}
 						},
+						Ok(None) => debug!("Waiting for more DKG Part1 packages"),
+						Err(e) if e.is::<DkgEquivocation>() => {
+							warn!("Dropped equivocating DKG message: {}", e)
+						},
 						Err(e) => error!("Error in DKG Part1 processing: {}", e),
 					}
 				},
 				DkgMessage::DkgPart2(dkg_part2_message) => {
-					match self.frost_dkg.dkg_part2(dkg_part2_message) {
-						Ok(msg) => {
-							if let Some(key) = msg {
-								handle_send!(RotateKey, self.dkg_sender.take(), Ok(key));
-							} else {
-								if let Err(e) = self.serialize_and_publish(DKG_TOPIC, &msg).await {
-									error!("Failed to publish DKG Part2 message: {}", e);
-								}
-							}
+					let from = C::identifier(dkg_part2_message.id);
+					self.report_dkg_progress(DkgProgress::Part2Received { from });
+					self.dkg_participants.insert(from);
+					match self.dkg::<C>().dkg_part2(dkg_part2_message) {
+						// The DKG is complete, there is nothing left to publish.
+						Ok(Some(key)) => {
+							let key = C::verifying_key(key);
+							self.report_dkg_progress(DkgProgress::Completed);
+							self.dkg_progress = None;
+							self.record_round(RoundKind::Dkg, RoundOutcome::Completed);
+							let verifying_key = to_hex(&key.serialize());
+							let participants = std::mem::take(&mut self.dkg_participants);
+							self.audit(participants, AuditRecord::KeyRotated { verifying_key });
+							// Sending only fails when nobody is subscribed.
+							let _ = self.key_rotations.send(KeyRotated { new_key: key });
+							handle_send!(RotateKey, self.dkg_sender.take(), Ok(key));
+						},
+						Ok(None) => debug!("Waiting for more DKG Part2 packages"),
+						// The round goes on with the package received first.
+						Err(e) if e.is::<DkgEquivocation>() => {
+							warn!("Dropped equivocating DKG message: {}", e)
 						},
 						Err(e) => {
+							self.dkg_progress = None;
+							self.record_round(RoundKind::Dkg, RoundOutcome::Failed);
 							handle_send!(RotateKey, self.dkg_sender.take(), Err(e.into()));
 							error!("Error in DKG Part2 processing.");
 						},
 					}
 				},
+				DkgMessage::RehearsalPart1(rehearsal_message) => {
+					match self.dkg::<C>().rehearsal_reply(rehearsal_message.nonce) {
+						Ok(reply) => {
+							let reply = DkgMessage::RehearsalReply(reply);
+							if let Err(e) = self
+								.serialize_and_publish(DKG_TOPIC, "DKG rehearsal reply", &reply)
+								.await
+							{
+								error!("Failed to publish DKG rehearsal reply: {}", e);
+							}
+						},
+						Err(e) => error!("Failed to answer DKG rehearsal: {}", e),
+					}
+				},
+				DkgMessage::RehearsalReply(rehearsal_message) => {
+					let from = C::identifier(rehearsal_message.id);
+					self.record_rehearsal_answer(rehearsal_message.nonce, from);
+				},
 			},
 			Err(e) => error!("Failed to deserialize DKG message: {}", e),
 		}
@@ -184,12 +641,28 @@ impl Worker {
     //
     // Handles different stages of the signing process including part1 and part2 messages.
     async fn handle_sign_message(&mut self, message: Vec<u8>) {
-		match serde_json::from_slice::<SignMessage>(&message) {
+		match self.frost_dkg.ciphersuite() {
+			Ciphersuite::Ed25519 => self.handle_sign_message_as::<Ed25519Sha512>(message).await,
+			Ciphersuite::Ristretto255 => {
+				self.handle_sign_message_as::<Ristretto255Sha512>(message).await
+			},
+		}
+	}
+
+	// Processes a signing-related message of the ciphersuite `C`.
+	async fn handle_sign_message_as<C: SupportedCiphersuite>(&mut self, message: Vec<u8>)
+	where
+		frost_core::Error<C>: Send + Sync,
+		frost_core::Identifier<C>: Send + Sync,
+	{
+		match serde_json::from_slice::<SignMessage<C>>(&message) {
 			Ok(message) => match message {
 				SignMessage::SignPart1(sign_part1_message) => {
-					match self.frost_dkg.sign_part1(sign_part1_message.clone()) {
+					match self.dkg::<C>().sign_part1(sign_part1_message.clone()) {
 						Ok(msg) => {
-							if let Err(e) = self.serialize_and_publish(SIGN_TOPIC, &msg).await {
+							if let Err(e) =
+								self.serialize_and_publish(SIGN_TOPIC, "Sign Part2", &msg).await
+							{
 								error!("Failed to publish Sign Part1 message: {}", e);
 							}
 						},
@@ -198,18 +671,30 @@ impl Worker {
 
 					let message = SignMessage::SignPart1(sign_part1_message);
 
-					if let Err(e) = self.serialize_and_publish(SIGN_TOPIC, &message).await {
+					if let Err(e) =
+						self.serialize_and_publish(SIGN_TOPIC, "Sign Part1", &message).await
+					{
 						error!("Failed to publish Sign Part1 message: {}", e);
 					}
 				},
 				SignMessage::SignPart2(sign_part2_message) => {
-					match self.frost_dkg.sign_part2(sign_part2_message.clone()) {
+					self.sign_participants.insert(C::identifier(sign_part2_message.id));
+					match self.dkg::<C>().sign_part2(sign_part2_message.clone()) {
 						Ok(signature) => {
 							if let Some(sign) = signature {
+								let sign = C::signature(sign);
+								self.record_round(RoundKind::Sign, RoundOutcome::Completed);
+								let record = AuditRecord::Signed {
+									message: to_hex(self.frost_dkg.sign_message()),
+									signature: to_hex(&sign.serialize()),
+								};
+								let participants = std::mem::take(&mut self.sign_participants);
+								self.audit(participants, record);
 								handle_send!(Sign, self.sign_sender.take(), Ok(sign));
 							}
 						},
 						Err(e) => {
+							self.record_round(RoundKind::Sign, RoundOutcome::Failed);
 							handle_send!(Sign, self.sign_sender.take(), Err(e.into()));
 						},
 					}
@@ -219,13 +704,62 @@ impl Worker {
 		}
 	}
 
+	// Broadcasts how long the running round of `kind` took, if one was started.
+	fn record_round(&mut self, kind: RoundKind, outcome: RoundOutcome) {
+		let started_at = match kind {
+			RoundKind::Dkg => self.dkg_started_at.take(),
+			RoundKind::Sign => self.sign_started_at.take(),
+		};
+		let Some(started_at) = started_at else {
+			return
+		};
+		let elapsed = self.clock.now().saturating_duration_since(started_at);
+		let duration = RoundDuration { kind, outcome, elapsed };
+		info!("{:?} round {:?} after {:?}", kind, outcome, duration.elapsed);
+		// Sending only fails when nobody is subscribed.
+		let _ = self.round_durations.send(duration);
+	}
+
+	// Appends a completed round to the audit log, if it is enabled. The local node is added to
+	// the `participants` of the round.
+	fn audit(&self, mut participants: BTreeSet<Identifier>, record: AuditRecord) {
+		let Some(audit_log) = &self.audit_log else {
+			return
+		};
+		participants.insert(self.frost_dkg.identifier());
+		let entry = AuditEntry::new(self.clock.unix(), &participants, record);
+		if let Err(e) = audit_log.append(&entry) {
+			warn!("Failed to append to the audit log: {}", e);
+		}
+	}
+
+	// Forwards a progress notification to the caller of the running key rotation, if it asked
+	// for one.
+	fn report_dkg_progress(&self, progress: DkgProgress) {
+		if let Some(sender) = &self.dkg_progress {
+			let _ = sender.unbounded_send(progress);
+		}
+	}
+
 	// Initiates the DKG process.
     //
     // Starts the DKG process by generating and publishing the first part of the DKG message.
     async fn start_dkg(&mut self) {
-		match self.frost_dkg.start_dkg() {
+		match self.frost_dkg.ciphersuite() {
+			Ciphersuite::Ed25519 => self.start_dkg_as::<Ed25519Sha512>().await,
+			Ciphersuite::Ristretto255 => self.start_dkg_as::<Ristretto255Sha512>().await,
+		}
+	}
+
+	// Initiates the DKG process of the ciphersuite `C`.
+	async fn start_dkg_as<C: SupportedCiphersuite>(&mut self)
+	where
+		frost_core::Error<C>: Send + Sync,
+		frost_core::Identifier<C>: Send + Sync,
+	{
+		match self.dkg::<C>().start_dkg() {
 			Ok(msg) => {
-				if let Err(e) = self.serialize_and_publish(DKG_TOPIC, &msg).await {
+				if let Err(e) = self.serialize_and_publish(DKG_TOPIC, "DKG Part1", &msg).await {
 					error!("Failed to publish DKG Part1 message: {}", e);
 				}
 			},
@@ -233,13 +767,84 @@ impl Worker {
 		}
 	}
 
+	// Publishes the part-1 package of a new rehearsal and returns the nonce tagging it.
+	//
+	// A failed publication is retried like any other, so only a key generation with fewer than
+	// two participants or failing to generate the package prevents the rehearsal from starting.
+	async fn start_rehearsal(&mut self) -> Result<u64> {
+		if self.frost_dkg.participants() < 2 {
+			return Err(anyhow::anyhow!("A DKG rehearsal needs at least two participants"))
+		}
+		match self.frost_dkg.ciphersuite() {
+			Ciphersuite::Ed25519 => self.start_rehearsal_as::<Ed25519Sha512>().await,
+			Ciphersuite::Ristretto255 => self.start_rehearsal_as::<Ristretto255Sha512>().await,
+		}
+	}
+
+	// Publishes the part-1 package of a new rehearsal of the ciphersuite `C`.
+	async fn start_rehearsal_as<C: SupportedCiphersuite>(&mut self) -> Result<u64>
+	where
+		frost_core::Error<C>: Send + Sync,
+		frost_core::Identifier<C>: Send + Sync,
+	{
+		let rehearsal_message = self.dkg::<C>().rehearsal_part1()?;
+		let nonce = rehearsal_message.nonce;
+		let message = DkgMessage::RehearsalPart1(rehearsal_message);
+		if let Err(e) = self.serialize_and_publish(DKG_TOPIC, "DKG rehearsal", &message).await {
+			error!("Failed to publish DKG rehearsal message: {}", e);
+		}
+		AnyOk(nonce)
+	}
+
+	// Counts a participant as answering the rehearsal in progress, if `nonce` tags it. Answers to
+	// the rehearsals of other nodes are ignored.
+	fn record_rehearsal_answer(&mut self, nonce: u64, from: Identifier) {
+		match &mut self.rehearsal {
+			Some(rehearsal) if rehearsal.nonce == nonce => {
+				rehearsal.responded.insert(from);
+			},
+			_ => debug!("Ignored an answer to another DKG rehearsal"),
+		}
+	}
+
+	// Counts the participants available for a signing round against the threshold.
+	async fn signing_readiness(&self) -> Result<SigningReadiness> {
+		let health = self.network.health().await?;
+		AnyOk(signing_readiness(&health, self.frost_dkg.threshold()))
+	}
+
+	// Checks that enough validators are connected to reach the signing threshold.
+	async fn ensure_sign_participants(&self) -> Result<()> {
+		let readiness = self.signing_readiness().await?;
+		if !readiness.is_ready() {
+			return Err(Error::InsufficientParticipants {
+				have: readiness.participants,
+				need: readiness.threshold,
+			}
+			.into());
+		}
+		AnyOk(())
+	}
+
 	// Initiates the signing process for a given message.
     //
     // Starts the signing process by generating and publishing the first part of the signing message.
     async fn start_sign(&mut self, message: &[u8]) {
-		match self.frost_dkg.start_sign(message) {
+		match self.frost_dkg.ciphersuite() {
+			Ciphersuite::Ed25519 => self.start_sign_as::<Ed25519Sha512>(message).await,
+			Ciphersuite::Ristretto255 => self.start_sign_as::<Ristretto255Sha512>(message).await,
+		}
+	}
+
+	// Initiates the signing process of the ciphersuite `C`.
+	async fn start_sign_as<C: SupportedCiphersuite>(&mut self, message: &[u8])
+	where
+		frost_core::Error<C>: Send + Sync,
+		frost_core::Identifier<C>: Send + Sync,
+	{
+		match self.dkg::<C>().start_sign(message) {
 			Ok(msg) => {
-				if let Err(e) = self.serialize_and_publish(SIGN_TOPIC, &msg).await {
+				if let Err(e) = self.serialize_and_publish(SIGN_TOPIC, "Sign Part1", &msg).await {
 					error!("Failed to publish Sign Part1 message: {}", e);
 				}
 			},
@@ -249,21 +854,373 @@ impl Worker {
 
 	// Serializes and publishes a given message to a specified topic.
     //
+    // If publishing fails, the message is queued and retried from the run loop.
+    //
     // # Arguments
     //
     // * `topic` - The topic to which the message will be published.
+    // * `context` - The round step the message belongs to, used in logs.
     // * `message` - The message to be serialized and published.
     //
     // # Returns
     //
-    // A result indicating success or failure of the operation.
-	async fn serialize_and_publish<T: Serialize>(&self, topic: &str, message: &T) -> Result<()> {
-		match serde_json::to_vec(message) {
-			Ok(encoded_msg) => self.network.publish(topic, encoded_msg).await.map_err(Into::into),
+    // A result indicating success or failure of the first attempt.
+	async fn serialize_and_publish<T: Serialize>(
+		&mut self,
+		topic: &'static str,
+		context: &'static str,
+		message: &T,
+	) -> Result<()> {
+		let encoded_msg = match serde_json::to_vec(message) {
+			Ok(encoded_msg) => encoded_msg,
 			Err(e) => {
 				error!("Failed to serialize message: {}", e);
-				Err(e.into())
+				return Err(e.into())
 			},
+		};
+
+		let result = self.publish(topic, encoded_msg.clone()).await;
+		if result.is_err() {
+			self.queue_publish(PendingPublish {
+				topic,
+				context,
+				message: encoded_msg,
+				attempts: 1,
+				retry_at: self.clock.now(),
+			});
+		}
+		result
+	}
+
+	// Publishes an encoded message, giving up after the configured timeout.
+	async fn publish(&self, topic: &str, message: Vec<u8>) -> Result<()> {
+		let publish = self.network.publish(topic, message);
+		match tokio::time::timeout(self.config.publish_timeout, publish).await {
+			Ok(result) => result,
+			Err(_) => {
+				error!("Publishing to {} timed out", topic);
+				Err(anyhow::anyhow!("Publish timed out after {:?}", self.config.publish_timeout))
+			},
+		}
+	}
+
+	// Schedules the retry of a failed publication, or dead-letters it once the retries are
+	// exhausted.
+	fn queue_publish(&mut self, mut pending: PendingPublish) {
+		if pending.attempts > self.config.publish_retries {
+			error!(
+				"Dead-lettered {} message on {} after {} attempts (DKG in progress: {}, signing in progress: {})",
+				pending.context,
+				pending.topic,
+				pending.attempts,
+				self.dkg_sender.is_some(),
+				self.sign_sender.is_some(),
+			);
+			return
 		}
+
+		let backoff = self.config.publish_retry_backoff * 2u32.saturating_pow(pending.attempts - 1);
+		pending.retry_at = self.clock.now() + backoff;
+		self.pending_publishes.push_back(pending);
+	}
+
+	// Retries the queued publications that are due.
+	async fn retry_publishes(&mut self) {
+		let now = self.clock.now();
+		for pending in std::mem::take(&mut self.pending_publishes) {
+			if pending.retry_at > now {
+				self.pending_publishes.push_back(pending);
+				continue
+			}
+
+			match self.publish(pending.topic, pending.message.clone()).await {
+				Ok(()) => debug!(
+					"Published {} message on {} after {} attempts",
+					pending.context,
+					pending.topic,
+					pending.attempts + 1
+				),
+				Err(e) => {
+					warn!("Retry of {} message on {} failed: {}", pending.context, pending.topic, e);
+					self.queue_publish(PendingPublish { attempts: pending.attempts + 1, ..pending });
+				},
+			}
+		}
+	}
+
+	// Drops the queued publications of a topic, when the round they belong to is abandoned.
+	fn drop_pending_publishes(&mut self, topic: &str) {
+		self.pending_publishes.retain(|pending| pending.topic != topic);
+	}
+}
+
+// Counts the validators connected in `health` against the signing threshold `t`.
+//
+// Only tracked validators can take part in a round, so other peers, such as bootnodes, are not
+// counted. The local node counts as one participant, so `t - 1` connected validators are required.
+fn signing_readiness(health: &NetworkHealth, t: u16) -> SigningReadiness {
+	let connected = health
+		.validators
+		.values()
+		.filter(|status| **status == ConnectionStatus::Connected)
+		.count();
+	SigningReadiness { participants: connected + 1, threshold: t as usize }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::Decode;
+	use rc_validator_network::ValidatorNetworkConfig;
+	use redot_core_primitives::clock::ManualClock;
+
+	// Starts a validator network without any peer, and a worker on top of it driven by a manual
+	// clock.
+	fn test_worker(config: WorkerConfig) -> (Worker, ManualClock) {
+		let network_config = ValidatorNetworkConfig {
+			listen_addr: "127.0.0.1".to_string(),
+			listen_port: 0,
+			..Default::default()
+		};
+		let (network, network_worker) =
+			rc_validator_network::default(Some(network_config), None).unwrap();
+		tokio::spawn(network_worker.run());
+
+		let validator_id = ValidatorId::decode(&mut &[1u8; 32][..]).unwrap();
+		let (_commands, command_receiver) = mpsc::channel(8);
+		let mut worker =
+			Worker::with_config(Arc::new(network), validator_id, command_receiver, config).unwrap();
+		let clock = ManualClock::new(0);
+		worker.set_clock(Arc::new(clock.clone()));
+		(worker, clock)
+	}
+
+	#[test]
+	fn only_connected_validators_count_towards_signing_readiness() {
+		let validator = |byte: u8| ValidatorId::decode(&mut &[byte; 32][..]).unwrap();
+		let health = NetworkHealth {
+			// Bootnodes and other peers are connected too.
+			connected_peers: 10,
+			validators: [
+				(validator(1), ConnectionStatus::Connected),
+				(validator(2), ConnectionStatus::Disconnected),
+				(validator(3), ConnectionStatus::Connected),
+			]
+			.into_iter()
+			.collect(),
+			..Default::default()
+		};
+
+		let readiness = signing_readiness(&health, 4);
+		assert_eq!(readiness, SigningReadiness { participants: 3, threshold: 4 });
+		assert!(!readiness.is_ready());
+		assert!(signing_readiness(&health, 3).is_ready());
+	}
+
+	#[tokio::test]
+	async fn publications_without_peers_are_retried_then_dead_lettered() {
+		let config = WorkerConfig {
+			publish_retries: 2,
+			publish_retry_backoff: Duration::from_secs(1),
+			..Default::default()
+		};
+		let (mut worker, clock) = test_worker(config);
+
+		// No peer is subscribed to the topic, so gossipsub refuses the message.
+		assert!(worker.serialize_and_publish(DKG_TOPIC, "test", &"message").await.is_err());
+		assert_eq!(worker.pending_publishes.len(), 1);
+
+		// The retry is not due before its backoff elapsed.
+		worker.retry_publishes().await;
+		assert_eq!(worker.pending_publishes[0].attempts, 1);
+
+		clock.advance(Duration::from_secs(1));
+		worker.retry_publishes().await;
+		assert_eq!(worker.pending_publishes.len(), 1);
+		assert_eq!(worker.pending_publishes[0].attempts, 2);
+
+		// The third failed attempt exhausts the retries.
+		clock.advance(Duration::from_secs(2));
+		worker.retry_publishes().await;
+		assert!(worker.pending_publishes.is_empty());
+	}
+
+	#[tokio::test]
+	async fn shutdown_aborts_the_round_in_progress_once_the_grace_period_elapsed() {
+		let (mut worker, clock) = test_worker(WorkerConfig::default());
+		let (sign, mut sign_receiver) = oneshot::channel();
+		worker.sign_sender = Some(QueryResultSender::Sign(sign));
+
+		let (sender, mut receiver) = oneshot::channel();
+		let grace = Duration::from_secs(10);
+		worker.handle_command(Command::BeginShutdown { grace, sender }).await;
+
+		// The round in progress keeps the worker running during the grace period.
+		clock.advance(grace - Duration::from_secs(1));
+		assert!(!worker.finish_shutdown());
+		assert!(receiver.try_recv().unwrap().is_none());
+
+		clock.advance(Duration::from_secs(1));
+		assert!(worker.finish_shutdown());
+		assert!(receiver.try_recv().unwrap().unwrap().is_err());
+		let error = sign_receiver.try_recv().unwrap().unwrap().unwrap_err();
+		assert_eq!(error.downcast_ref::<Error>(), Some(&Error::SignAborted));
+	}
+
+	#[tokio::test]
+	async fn completed_dkg_resolves_the_rotation_without_publishing() {
+		let (mut worker, _clock) = test_worker(WorkerConfig::default());
+		worker.frost_dkg.set_nt(2, 2).unwrap();
+		let mut peer = FrostDkg::new(Identifier::try_from(1).unwrap());
+		peer.set_nt(2, 2).unwrap();
+		let DkgMessage::DkgPart1(peer_part1) = peer.start_dkg().unwrap() else {
+			panic!("Expected a DKG Part1 message")
+		};
+
+		// No peer is subscribed to the topic, so every published message stays queued.
+		let published = |worker: &Worker, index: usize| {
+			serde_json::from_slice::<DkgMessage>(&worker.pending_publishes[index].message).unwrap()
+		};
+
+		let (sender, mut receiver) = oneshot::channel();
+		worker.handle_command(Command::RotateKey { progress: None, sender }).await;
+		let DkgMessage::DkgPart1(worker_part1) = published(&worker, 0) else {
+			panic!("Expected a DKG Part1 message")
+		};
+		let Some(DkgMessage::DkgPart2(peer_part2)) = peer.dkg_part1(worker_part1).unwrap() else {
+			panic!("Expected a DKG Part2 message")
+		};
+
+		let message = DkgMessage::DkgPart1(peer_part1);
+		worker.handle_dkg_message(serde_json::to_vec(&message).unwrap()).await;
+		let DkgMessage::DkgPart2(worker_part2) = published(&worker, 1) else {
+			panic!("Expected a DKG Part2 message")
+		};
+
+		let message = DkgMessage::DkgPart2(peer_part2);
+		worker.handle_dkg_message(serde_json::to_vec(&message).unwrap()).await;
+		let key = receiver.try_recv().unwrap().unwrap().unwrap();
+		assert_eq!(peer.dkg_part2(worker_part2).unwrap().map(AnyVerifyingKey::from), Some(key));
+
+		// Completing the DKG published nothing, and no message was published without a payload.
+		assert_eq!(worker.pending_publishes.len(), 2);
+		assert!(worker.pending_publishes.iter().all(|pending| pending.message != b"null"));
+	}
+
+	#[tokio::test]
+	async fn setup_selects_the_ciphersuite_of_the_key_generation() {
+		let (mut worker, _clock) = test_worker(WorkerConfig::default());
+		let (sender, mut receiver) = oneshot::channel();
+		let ciphersuite = Ciphersuite::Ristretto255;
+		worker.handle_command(Command::Setup { nt: (2, 2), ciphersuite, sender }).await;
+		assert!(receiver.try_recv().unwrap().unwrap().is_ok());
+		assert_eq!(worker.frost_dkg.ciphersuite(), Ciphersuite::Ristretto255);
+
+		let mut peer = FrostDkg::<Ristretto255Sha512>::new(1.try_into().unwrap());
+		peer.set_nt(2, 2).unwrap();
+		let DkgMessage::DkgPart1(peer_part1) = peer.start_dkg().unwrap() else {
+			panic!("Expected a DKG Part1 message")
+		};
+		let published = |worker: &Worker, index: usize| {
+			let message = &worker.pending_publishes[index].message;
+			serde_json::from_slice::<DkgMessage<Ristretto255Sha512>>(message).unwrap()
+		};
+
+		let (sender, mut receiver) = oneshot::channel();
+		worker.handle_command(Command::RotateKey { progress: None, sender }).await;
+
+		// The ciphersuite cannot be switched while the rotation runs.
+		let (setup, mut setup_receiver) = oneshot::channel();
+		let ciphersuite = Ciphersuite::Ed25519;
+		worker.handle_command(Command::Setup { nt: (2, 2), ciphersuite, sender: setup }).await;
+		assert!(setup_receiver.try_recv().unwrap().unwrap().is_err());
+
+		let DkgMessage::DkgPart1(worker_part1) = published(&worker, 0) else {
+			panic!("Expected a DKG Part1 message")
+		};
+		let Some(DkgMessage::DkgPart2(peer_part2)) = peer.dkg_part1(worker_part1).unwrap() else {
+			panic!("Expected a DKG Part2 message")
+		};
+		let message = DkgMessage::DkgPart1(peer_part1);
+		worker.handle_dkg_message(serde_json::to_vec(&message).unwrap()).await;
+		let DkgMessage::DkgPart2(worker_part2) = published(&worker, 1) else {
+			panic!("Expected a DKG Part2 message")
+		};
+		let message = DkgMessage::DkgPart2(peer_part2);
+		worker.handle_dkg_message(serde_json::to_vec(&message).unwrap()).await;
+
+		let key = receiver.try_recv().unwrap().unwrap().unwrap();
+		let peer_key = peer.dkg_part2(worker_part2).unwrap().map(AnyVerifyingKey::Ristretto255);
+		assert_eq!(peer_key, Some(key));
+		assert!(key.into_ed25519().is_err());
+	}
+
+	#[tokio::test]
+	async fn rehearsal_needs_at_least_two_participants() {
+		let (mut worker, _clock) = test_worker(WorkerConfig::default());
+
+		for n in [0, 1] {
+			worker.frost_dkg.set_nt(n, 0).unwrap();
+			let (sender, mut receiver) = oneshot::channel();
+			worker.handle_command(Command::DkgRehearsal { sender }).await;
+			assert!(receiver.try_recv().unwrap().unwrap().is_err());
+			assert!(worker.rehearsal.is_none());
+		}
+	}
+
+	#[tokio::test]
+	async fn rehearsal_only_counts_replies_carrying_its_nonce() {
+		let (mut worker, _clock) = test_worker(WorkerConfig::default());
+		worker.frost_dkg.set_nt(2, 2).unwrap();
+		let peer_id = Identifier::try_from(1).unwrap();
+		let mut peer = FrostDkg::new(peer_id);
+		peer.set_nt(2, 2).unwrap();
+
+		let (sender, mut receiver) = oneshot::channel();
+		worker.handle_command(Command::DkgRehearsal { sender }).await;
+		let message = serde_json::from_slice::<DkgMessage>(&worker.pending_publishes[0].message);
+		let DkgMessage::RehearsalPart1(rehearsal) = message.unwrap() else {
+			panic!("Expected a DKG rehearsal message")
+		};
+
+		// An answer to the rehearsal of another node does not complete this one.
+		let reply = DkgMessage::RehearsalReply(peer.rehearsal_reply(rehearsal.nonce ^ 1).unwrap());
+		worker.handle_dkg_message(serde_json::to_vec(&reply).unwrap()).await;
+		worker.finish_rehearsal();
+		assert!(receiver.try_recv().unwrap().is_none());
+
+		let reply = DkgMessage::RehearsalReply(peer.rehearsal_reply(rehearsal.nonce).unwrap());
+		worker.handle_dkg_message(serde_json::to_vec(&reply).unwrap()).await;
+		worker.finish_rehearsal();
+		let report = receiver.try_recv().unwrap().unwrap().unwrap();
+		assert!(report.is_complete());
+		assert_eq!(report.responded, BTreeSet::from([peer_id]));
+	}
+
+	#[test]
+	fn replaced_rotation_resolves_with_superseded() {
+		let (first, mut first_receiver) = oneshot::channel();
+		let (second, mut second_receiver) = oneshot::channel();
+		let mut pending = None;
+
+		replace_sender(&mut pending, QueryResultSender::RotateKey(first));
+		replace_sender(&mut pending, QueryResultSender::RotateKey(second));
+
+		let error = first_receiver.try_recv().unwrap().unwrap().unwrap_err();
+		assert_eq!(error.downcast_ref::<Error>(), Some(&Error::Superseded));
+		assert!(second_receiver.try_recv().unwrap().is_none());
+	}
+
+	#[test]
+	fn replaced_sign_request_resolves_with_superseded() {
+		let (first, mut first_receiver) = oneshot::channel();
+		let (second, _second_receiver) = oneshot::channel();
+		let mut pending = None;
+
+		replace_sender(&mut pending, QueryResultSender::Sign(first));
+		replace_sender(&mut pending, QueryResultSender::Sign(second));
+
+		let error = first_receiver.try_recv().unwrap().unwrap().unwrap_err();
+		assert_eq!(error.downcast_ref::<Error>(), Some(&Error::Superseded));
 	}
 }
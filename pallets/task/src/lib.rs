@@ -21,6 +21,12 @@
 
 pub use pallet::*;
 
+/// Tag a validator may prepend to the metadata message before signing it.
+///
+/// `new_metadata` accepts a signature over the bare message or over the message prefixed with
+/// this tag, which keeps the signature from being valid for another message with the same bytes.
+pub const METADATA_DOMAIN_TAG: &[u8] = b"REDOT_METADATA_V1";
+
 // #[cfg(test)]
 // mod mock;
 
@@ -34,6 +40,7 @@ pub use pallet::*;
 pub mod pallet {
 
 	use frame_support::{dispatch::DispatchResultWithPostInfo, pallet_prelude::*, WeakBoundedVec};
+	use frame_support::sp_std::prelude::*;
 	use frame_system::pallet_prelude::*;
 	// use redot_core_primitives::{DkgSignature, WrapVerifyingKey};
 	use ed25519_consensus::{Signature, VerificationKey};
@@ -180,6 +187,9 @@ pub mod pallet {
 
 		/// Add a new metadata entry.
 		///
+		/// `signature_bytes` signs the encoded `metadata`, `id` and `nonce`, optionally prefixed
+		/// with `METADATA_DOMAIN_TAG`.
+		///
 		/// The dispatch origin for this call must be `Signed` by the caller.
 		#[pallet::call_index(1)]
 		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().writes(1))]
@@ -204,7 +214,12 @@ pub mod pallet {
 			let key = VerificationKey::try_from(key_bytes)
 				.map_err(|_| Error::<T>::InvalidOldVerificationKey)?;
 
-			let is_valid = key.verify(&signature, &msg).is_ok();
+			let is_valid = key.verify(&signature, &msg).is_ok() || {
+				let mut tagged = Vec::with_capacity(super::METADATA_DOMAIN_TAG.len() + msg.len());
+				tagged.extend_from_slice(super::METADATA_DOMAIN_TAG);
+				tagged.extend_from_slice(&msg);
+				key.verify(&signature, &tagged).is_ok()
+			};
 			ensure!(is_valid, Error::<T>::InvalidSignature);
 
 			let current_id = LastTaskId::<T>::get().unwrap_or(0);
@@ -12,9 +12,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::{
+	das_rpc::{DasClientConfig, SelectionPolicy},
+	data_dir::DEFAULT_DATA_DIR,
+	looper::{ForkPolicy, HeadSource, LooperConfig, NonceMode},
+	node_config::{NodeConfig, SignerSource},
+};
 use clap::{ArgAction, Parser};
 use rc_validator_network::ValidatorNetworkConfig;
-use std::net::SocketAddr;
+use redoxt::Finality;
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
 
 pub const DEFAULT_RPC_LISTEN_ADDR: &str = "127.0.0.1:4177";
 
@@ -23,10 +30,24 @@ const TEST_RPC_URL: &str = "wss://dev.melodot.io:9944";
 
 const DEFAULT_RPC_URL: &str = "ws://127.0.0.1:36565";
 
+const DEFAULT_DAS_RPC_URL: &str = "http://127.0.0.1:4177";
+
 /// Command line interface configuration
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 pub struct Cli {
+	/// TOML or JSON file configuring the RPC and DAS URLs, signer, network and threshold
+	#[clap(short = 'c', long, env = "REDLIGHT_CONFIG")]
+	config: Option<PathBuf>,
+
+	/// Directory holding the node key and the other persisted state
+	#[clap(long, env = "REDLIGHT_DATA_DIR")]
+	data_dir: Option<PathBuf>,
+
+	/// Record completed key rotations and signatures in an audit log in the data directory
+	#[clap(long, action = ArgAction::SetTrue)]
+	audit_log: bool,
+
 	/// Listening address for the RPC service
 	#[clap(short = 'a', long, env)]
 	rpc_listen_addr: Option<SocketAddr>,
@@ -39,6 +60,82 @@ pub struct Cli {
 	#[clap(short = 'd', long, env = "DAS_NET_LISTEN_PORT")]
 	das_net_listen_port: Option<u16>,
 
+	/// DAS RPC URLs, separated by commas
+	#[clap(long = "das-rpc-url", env = "DAS_RPC_URLS", value_delimiter = ',')]
+	das_rpc_urls: Vec<String>,
+
+	/// Spread DAS requests over all URLs instead of failing over in order
+	#[clap(long, action = ArgAction::SetTrue)]
+	das_round_robin: bool,
+
+	/// Keep a larger pool of idle connections, for remote or load-balanced DAS endpoints
+	#[clap(long, action = ArgAction::SetTrue)]
+	das_remote_pool: bool,
+
+	/// Seconds after which a DAS request is abandoned
+	#[clap(long, env = "DAS_TIMEOUT")]
+	das_timeout: Option<u64>,
+
+	/// Bearer token sent with every DAS request, for DAS nodes behind an authenticating proxy
+	#[clap(long, env = "DAS_BEARER_TOKEN", hide_env_values = true)]
+	das_bearer_token: Option<String>,
+
+	/// Seconds to wait at startup for the DAS node to report itself healthy
+	#[clap(long, env = "DAS_STARTUP_TIMEOUT")]
+	das_startup_timeout: Option<u64>,
+
+	/// Number of blocks DAS may lag behind the chain tip before warning
+	#[clap(long, env = "MAX_DAS_LAG")]
+	max_das_lag: Option<u32>,
+
+	/// Seconds during which a signature is reused for an identical message
+	#[clap(long, env = "SIGN_CACHE_WINDOW")]
+	sign_cache_window: Option<u64>,
+
+	/// Seconds to wait for DAS to determine the availability of a block before skipping it
+	#[clap(long, env = "DAS_CONFIRMATION_TIMEOUT")]
+	das_confirmation_timeout: Option<u64>,
+
+	/// Hex-encoded genesis hash of the chain the DAS nodes must serve, checked at startup
+	#[clap(long)]
+	das_genesis: Option<String>,
+
+	/// Use the DAS block number as the nonce of each submission
+	#[clap(long, action = ArgAction::SetTrue)]
+	block_number_nonce: bool,
+
+	/// Only process the latest of the best blocks received while busy, skipping fork churn
+	#[clap(long, action = ArgAction::SetTrue)]
+	tip_only: bool,
+
+	/// Process finalized blocks instead of best blocks
+	#[clap(long, action = ArgAction::SetTrue)]
+	follow_finalized: bool,
+
+	/// Check best block submissions once finalized and submit again those dropped by a reorg
+	#[clap(long, action = ArgAction::SetTrue)]
+	reconcile_on_finalized: bool,
+
+	/// Wait until each submission is finalized instead of only included in a block
+	#[clap(long, action = ArgAction::SetTrue)]
+	wait_finalized: bool,
+
+	/// Number of submissions that may wait for their inclusion at the same time
+	#[clap(long)]
+	submission_concurrency: Option<usize>,
+
+	/// Sign metadata prefixed with a domain tag, which the task pallet also accepts
+	#[clap(long, action = ArgAction::SetTrue)]
+	domain_separated_metadata: bool,
+
+	/// Listening address for the Prometheus metrics endpoint
+	#[clap(long, env = "PROMETHEUS_ADDR")]
+	prometheus_addr: Option<SocketAddr>,
+
+	/// SS58 address the signer account must match
+	#[clap(long, env = "EXPECTED_ACCOUNT")]
+	expected_account: Option<String>,
+
 	/// Activate development configuration
 	#[clap(long, action = ArgAction::SetTrue)]
 	dev_mode: bool,
@@ -53,10 +150,27 @@ pub struct Config {
 	pub rpc_listen_addr: SocketAddr,
 	pub rpc_url: String,
 	pub network_config: ValidatorNetworkConfig,
+	pub das_rpc_urls: Vec<String>,
+	pub das_selection_policy: SelectionPolicy,
+	pub das_client_config: DasClientConfig,
+	/// Bearer token sent with every DAS request, if any.
+	pub das_bearer_token: Option<String>,
+	/// How long to wait at startup for the DAS node to be healthy, if the startup waits for it.
+	pub das_startup_timeout: Option<Duration>,
+	pub looper_config: LooperConfig,
+	pub prometheus_addr: Option<SocketAddr>,
+	pub expected_account: Option<String>,
+	pub signer: SignerSource,
+	/// The `(n, t)` setup of the validator network, if configured.
+	pub threshold: Option<(u16, u16)>,
+	/// Directory holding all the persisted state of the node.
+	pub data_dir: PathBuf,
+	/// Whether completed rounds are recorded in the audit log of the data directory.
+	pub audit_log: bool,
 }
 
 impl Config {
-	pub fn from_cli_args(cli: Cli) -> Self {
+	pub fn from_cli_args(cli: Cli) -> anyhow::Result<Self> {
 		let rpc_listen_addr = cli.rpc_listen_addr.unwrap_or_else(|| {
 			DEFAULT_RPC_LISTEN_ADDR.parse().expect("Invalid DEFAULT SocketAddr")
 		});
@@ -71,17 +185,168 @@ impl Config {
 			rpc_url = TEST_RPC_URL.to_string();
 		}
 
+		// The config file overrides the defaults, and explicit command line flags override both.
+		let node_config = cli.config.map(NodeConfig::from_file).transpose()?;
+		if let Some(node_config) = &node_config {
+			rpc_url = node_config.rpc_url.clone();
+			node_config.network.apply(&mut das_network_config);
+		}
+
 		if let Some(rpc_remote_url) = cli.rpc_remote_url {
 			rpc_url = rpc_remote_url.to_string();
 		}
 
         print!("poot: {}", das_network_config.listen_port);
 
-		Config { rpc_listen_addr, rpc_url, network_config: das_network_config }
+		let das_rpc_urls = if !cli.das_rpc_urls.is_empty() {
+			cli.das_rpc_urls
+		} else if let Some(node_config) = &node_config {
+			node_config.das_rpc_urls.clone()
+		} else {
+			vec![DEFAULT_DAS_RPC_URL.to_string()]
+		};
+
+		let das_selection_policy = if cli.das_round_robin {
+			SelectionPolicy::RoundRobin
+		} else {
+			SelectionPolicy::Failover
+		};
+
+		let mut das_client_config = if cli.das_remote_pool {
+			DasClientConfig::remote()
+		} else {
+			DasClientConfig::default()
+		};
+		if let Some(das_timeout) = cli.das_timeout {
+			das_client_config.timeout = Duration::from_secs(das_timeout);
+		}
+
+		let mut looper_config = LooperConfig::default();
+		if let Some(max_das_lag) = cli.max_das_lag {
+			looper_config.max_das_lag = max_das_lag;
+		}
+		if let Some(sign_cache_window) = cli.sign_cache_window {
+			looper_config.sign_cache_window = Duration::from_secs(sign_cache_window);
+		}
+		looper_config.das_confirmation_timeout =
+			cli.das_confirmation_timeout.map(Duration::from_secs);
+		if let Some(das_genesis) = cli.das_genesis {
+			let das_genesis = hex::decode(das_genesis.trim_start_matches("0x"))
+				.map_err(|e| anyhow::anyhow!("Invalid DAS genesis hash: {}", e))?;
+			looper_config.das_genesis = Some(das_genesis);
+		}
+		if cli.block_number_nonce {
+			looper_config.nonce_mode = NonceMode::BlockNumber;
+		}
+		looper_config.domain_separated = cli.domain_separated_metadata;
+		if cli.tip_only {
+			looper_config.fork_policy = ForkPolicy::TipOnly;
+		}
+		if cli.follow_finalized {
+			looper_config.head_source = HeadSource::Finalized;
+		}
+		looper_config.reconcile_on_finalized = cli.reconcile_on_finalized;
+		if cli.wait_finalized {
+			looper_config.finality = Finality::Finalized;
+		}
+		if let Some(submission_concurrency) = cli.submission_concurrency {
+			anyhow::ensure!(submission_concurrency > 0, "Submission concurrency must be positive");
+			looper_config.submission_concurrency = submission_concurrency;
+		}
+
+		let mut signer = SignerSource::default();
+		let mut threshold = None;
+		let mut data_dir = None;
+		if let Some(node_config) = node_config {
+			signer = node_config.signer;
+			threshold = node_config.threshold.map(|threshold| (threshold.n, threshold.t));
+			data_dir = node_config.data_dir;
+		}
+		let data_dir = cli.data_dir.or(data_dir).unwrap_or_else(|| PathBuf::from(DEFAULT_DATA_DIR));
+
+		Ok(Config {
+			rpc_listen_addr,
+			rpc_url,
+			network_config: das_network_config,
+			das_rpc_urls,
+			das_selection_policy,
+			das_client_config,
+			das_bearer_token: cli.das_bearer_token,
+			das_startup_timeout: cli.das_startup_timeout.map(Duration::from_secs),
+			looper_config,
+			prometheus_addr: cli.prometheus_addr,
+			expected_account: cli.expected_account,
+			signer,
+			threshold,
+			data_dir,
+			audit_log: cli.audit_log,
+		})
 	}
 }
 
-pub fn parse_args() -> Config {
+pub fn parse_args() -> anyhow::Result<Config> {
 	let cli = Cli::parse();
 	Config::from_cli_args(cli)
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn write_config(name: &str, content: &str) -> PathBuf {
+		let path =
+			std::env::temp_dir().join(format!("redlight-{}-{}.toml", name, std::process::id()));
+		std::fs::write(&path, content).unwrap();
+		path
+	}
+
+	fn parse(args: &[&str]) -> Config {
+		let cli = Cli::try_parse_from(std::iter::once("redlight").chain(args.iter().copied()));
+		Config::from_cli_args(cli.unwrap()).unwrap()
+	}
+
+	#[test]
+	fn explicit_flags_override_the_config_file() {
+		let path = write_config(
+			"flags",
+			r#"
+			rpc_url = "ws://10.0.0.1:9944"
+			das_rpc_urls = ["http://10.0.0.1:4177"]
+			data_dir = "/var/lib/redlight"
+			"#,
+		);
+		let config = parse(&[
+			"--config",
+			path.to_str().unwrap(),
+			"--rpc-remote-url",
+			"ws://127.0.0.1:9944",
+			"--das-rpc-url",
+			"http://127.0.0.1:4177",
+			"--data-dir",
+			"/tmp/redlight",
+		]);
+		std::fs::remove_file(&path).unwrap();
+
+		assert_eq!(config.rpc_url, "ws://127.0.0.1:9944");
+		assert_eq!(config.das_rpc_urls, vec!["http://127.0.0.1:4177".to_string()]);
+		assert_eq!(config.data_dir, PathBuf::from("/tmp/redlight"));
+	}
+
+	#[test]
+	fn config_file_overrides_the_defaults() {
+		let path = write_config(
+			"defaults",
+			r#"
+			rpc_url = "ws://10.0.0.1:9944"
+			das_rpc_urls = ["http://10.0.0.1:4177"]
+			"#,
+		);
+		let config = parse(&["--config", path.to_str().unwrap()]);
+		std::fs::remove_file(&path).unwrap();
+
+		assert_eq!(config.rpc_url, "ws://10.0.0.1:9944");
+		assert_eq!(config.das_rpc_urls, vec!["http://10.0.0.1:4177".to_string()]);
+		// A file without a data directory does not clobber the default one.
+		assert_eq!(config.data_dir, PathBuf::from(DEFAULT_DATA_DIR));
+	}
+}
@@ -16,15 +16,195 @@
 //! 
 //! This is a simple RPC client used for querying the latest block and data availability from DAS (Decentralized Autonomous System).
 
+use crate::rpc_transport::{RetryPolicy, RpcTransport};
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use jsonrpsee::{
+    core::client::{Subscription, SubscriptionClientT},
+    rpc_params,
+    ws_client::{WsClient, WsClientBuilder},
+};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
 use serde_json::{json, Value};
 use anyhow::{Result, anyhow};
+use std::time::Duration;
+
+pub use crate::rpc_transport::{RequestTimeout, SelectionPolicy};
+
+// JSON-RPC error code returned for unknown methods.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+// Interval at which `das_last` is polled when no endpoint supports block subscriptions.
+const BLOCK_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// Interval between the health checks of `DasClient::wait_until_healthy`.
+const HEALTH_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A block processed by DAS.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DasBlock {
+    /// The block number.
+    pub number: u32,
+    /// The block hash.
+    pub hash: Vec<u8>,
+}
+
+impl DasBlock {
+    /// The block hash as lowercase hex without a `0x` prefix, as expected by `das_isAvailable`.
+    pub fn hash_hex(&self) -> String {
+        hex::encode(&self.hash)
+    }
+}
+
+/// A JSON-RPC error object returned by a DAS node in place of a result.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JsonRpcError {
+    /// The error code.
+    pub code: i64,
+    /// The error message.
+    pub message: String,
+}
+
+impl std::fmt::Display for JsonRpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "JSON-RPC error {}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for JsonRpcError {}
+
+/// Connection settings of a `DasClient`.
+///
+/// Idle connections are kept open and reused by later requests, which avoids a new TCP (and TLS)
+/// handshake on every poll. The default suits a single local DAS node, which needs only a couple
+/// of idle connections. For a remote endpoint behind a load balancer, or several DAS URLs polled
+/// at a high frequency, use `DasClientConfig::remote`, which keeps more connections per host.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DasClientConfig {
+    /// Maximum number of idle connections kept per host.
+    pub max_idle_connections_per_host: usize,
+    /// Timeout of a whole request to one endpoint, from connecting to reading the response.
+    ///
+    /// A request that times out on every endpoint, including retries, fails with a
+    /// `RequestTimeout` error.
+    pub timeout: Duration,
+    /// How requests are retried when every endpoint fails.
+    pub retry: RetryPolicy,
+}
+
+impl DasClientConfig {
+    /// Settings for remote or pooled DAS endpoints.
+    pub fn remote() -> Self {
+        DasClientConfig { max_idle_connections_per_host: 8, ..Default::default() }
+    }
+}
+
+impl Default for DasClientConfig {
+    fn default() -> Self {
+        DasClientConfig {
+            max_idle_connections_per_host: 2,
+            timeout: Duration::from_secs(10),
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+/// Builder of a `DasClient`.
+#[derive(Clone, Debug)]
+pub struct DasClientBuilder {
+    /// The URLs of the DAS RPC servers. Must not be empty.
+    pub urls: Vec<String>,
+    /// The endpoint selection policy.
+    pub policy: SelectionPolicy,
+    /// The connection settings.
+    pub config: DasClientConfig,
+    /// Token sent as `Authorization: Bearer <token>` with every request, for DAS nodes behind
+    /// an authenticating proxy.
+    pub bearer_token: Option<String>,
+}
+
+impl DasClientBuilder {
+    /// Creates a builder for a client of the DAS RPC server at `rpc_url`.
+    pub fn new(rpc_url: String) -> Self {
+        DasClientBuilder {
+            urls: vec![rpc_url],
+            policy: SelectionPolicy::Failover,
+            config: DasClientConfig::default(),
+            bearer_token: None,
+        }
+    }
+
+    /// Sends requests to several DAS RPC servers, picking one according to `policy`.
+    ///
+    /// See `DasClient::new_multi`.
+    pub fn with_urls(mut self, rpc_urls: Vec<String>, policy: SelectionPolicy) -> Self {
+        self.urls = rpc_urls;
+        self.policy = policy;
+        self
+    }
+
+    /// Sets the connection settings.
+    pub fn with_config(mut self, config: DasClientConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Sets the timeout of a request to one endpoint.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = timeout;
+        self
+    }
+
+    /// Sets how requests are retried when every endpoint fails.
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.config.retry = retry;
+        self
+    }
+
+    /// Sends `token` as a bearer token with every request.
+    pub fn with_bearer_token(mut self, token: String) -> Self {
+        self.bearer_token = Some(token);
+        self
+    }
+
+    /// Builds the client.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no URL is set, if the bearer token is not a valid header value, or if
+    /// the HTTP client cannot be initialized.
+    pub fn build(self) -> Result<DasClient> {
+        if self.urls.is_empty() {
+            return Err(anyhow!("DasClient requires at least one RPC URL"));
+        }
+        let mut headers = HeaderMap::new();
+        if let Some(token) = &self.bearer_token {
+            let mut value = HeaderValue::from_str(&format!("Bearer {}", token))
+                .map_err(|_| anyhow!("Invalid DAS bearer token"))?;
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
+        }
+        let client = reqwest::Client::builder()
+            .pool_max_idle_per_host(self.config.max_idle_connections_per_host)
+            .timeout(self.config.timeout)
+            .default_headers(headers.clone())
+            .build()?;
+        let transport = RpcTransport::new(client, self.urls, self.policy, self.config.retry)?;
+        Ok(DasClient { transport, timeout: self.config.timeout, headers })
+    }
+}
 
 /// A client for interacting with a DAS RPC server.
 ///
 /// This client provides functions to interact with DAS, allowing you to query information
 /// such as the latest processed block and check data availability.
 pub struct DasClient {
-    rpc_url: String,
+    transport: RpcTransport,
+    timeout: Duration,
+    // Headers sent with every request.
+    headers: HeaderMap,
 }
 
 impl DasClient {
@@ -34,7 +214,123 @@ impl DasClient {
     ///
     /// * `rpc_url` - A string slice that holds the URL of the DAS RPC server.
     pub fn new(rpc_url: String) -> Self {
-        DasClient { rpc_url }
+        DasClientBuilder::new(rpc_url).build().expect("Failed to build the DAS client")
+    }
+
+    /// Creates a new `DasClient` backed by several DAS RPC servers.
+    ///
+    /// Each request picks an endpoint according to `policy`. If the endpoint cannot be reached,
+    /// the request is retried on the next one, and an error is only returned once every
+    /// endpoint has failed on every retry.
+    ///
+    /// # Arguments
+    ///
+    /// * `rpc_urls` - The URLs of the DAS RPC servers. Must not be empty.
+    /// * `policy` - The endpoint selection policy.
+    pub fn new_multi(rpc_urls: Vec<String>, policy: SelectionPolicy) -> Self {
+        Self::with_config(rpc_urls, policy, DasClientConfig::default())
+    }
+
+    /// Creates a new `DasClient` like `new_multi`, with the given connection settings.
+    ///
+    /// See `DasClientBuilder` for the other settings, such as a bearer token.
+    ///
+    /// # Arguments
+    ///
+    /// * `rpc_urls` - The URLs of the DAS RPC servers. Must not be empty.
+    /// * `policy` - The endpoint selection policy.
+    /// * `config` - The connection settings.
+    pub fn with_config(
+        rpc_urls: Vec<String>,
+        policy: SelectionPolicy,
+        config: DasClientConfig,
+    ) -> Self {
+        DasClientBuilder { urls: rpc_urls, policy, config, bearer_token: None }
+            .build()
+            .expect("Failed to build the DAS client")
+    }
+
+    async fn call(&self, method: &str, params: Value) -> Result<Value> {
+        self.transport.call(method, params).await
+    }
+
+    /// Checks whether the DAS node is synced and healthy.
+    ///
+    /// Calls `das_health`, falling back to the standard `system_health` if the node does not
+    /// implement it. With `system_health`, the node is considered healthy when it is not syncing.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request to the RPC server fails, or if the response data
+    /// is in an unexpected format.
+    pub async fn health(&self) -> Result<bool> {
+        let value = self.call("das_health", json!([])).await?;
+        if let Some(Value::Bool(is_healthy)) = value.get("result") {
+            return Ok(*is_healthy);
+        }
+
+        // The node does not implement `das_health`.
+        if value["error"]["code"].as_i64() == Some(METHOD_NOT_FOUND) {
+            let value = self.call("system_health", json!([])).await?;
+            return value["result"]["isSyncing"]
+                .as_bool()
+                .map(|is_syncing| !is_syncing)
+                .ok_or_else(|| anyhow!("Unexpected response format"));
+        }
+
+        Err(anyhow!("Unexpected response format"))
+    }
+
+    /// Waits until the DAS node reports itself healthy, as checked by `health`.
+    ///
+    /// A failed check, such as one against a node that is not listening yet, counts as unhealthy
+    /// rather than as an error, so this can gate the startup on a node that is still starting.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the node is still not healthy once `timeout` has elapsed.
+    pub async fn wait_until_healthy(&self, timeout: Duration) -> Result<()> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let reason = match self.health().await {
+                Ok(true) => return Ok(()),
+                Ok(false) => "the node reports itself unhealthy".to_string(),
+                Err(e) => e.to_string(),
+            };
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                return Err(anyhow!("DAS node not healthy after {:?}: {}", timeout, reason));
+            }
+            log::debug!("DAS node not healthy yet: {}", reason);
+            tokio::time::sleep(HEALTH_POLL_INTERVAL.min(deadline - now)).await;
+        }
+    }
+
+    /// Checks that every DAS endpoint serves the chain with the genesis hash `expected_genesis`.
+    ///
+    /// Calls `das_chainInfo` on each endpoint. A DAS node of another network answers
+    /// availability queries just as well, so this catches a misconfigured URL before its
+    /// answers are signed and submitted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an endpoint cannot be queried, answers without a genesis hash, or
+    /// serves another chain.
+    pub async fn verify_network(&self, expected_genesis: &[u8]) -> Result<()> {
+        for (url, response) in self.transport.call_each("das_chainInfo", json!([])).await {
+            let genesis = response.and_then(|value| genesis_hash(&value)).map_err(|e| {
+                anyhow!("Failed to read the chain of DAS endpoint {}: {}", url, e)
+            })?;
+            if genesis != expected_genesis {
+                return Err(anyhow!(
+                    "DAS endpoint {} serves the chain with genesis 0x{}, expected 0x{}",
+                    url,
+                    hex::encode(&genesis),
+                    hex::encode(expected_genesis)
+                ));
+            }
+        }
+        Ok(())
     }
 
     /// Fetches the latest processed block from the DAS system.
@@ -44,39 +340,87 @@ impl DasClient {
     /// # Returns
     ///
     /// Returns a `Result` which is either:
-    /// - An `Option` containing a tuple of the block number (`u32`) and its hash (`Vec<u8>`), or
+    /// - An `Option` containing the block, or
     /// - None if the block information is not found or available.
     ///
     /// # Errors
     ///
     /// Returns an error if the request to the RPC server fails, or if the response data
-    /// is in an unexpected format.
-    pub fn get_latest_block(&self) -> Result<Option<(u32, Vec<u8>)>> {
-        let resp = ureq::post(&self.rpc_url)
-            .send_json(json!({
-                "method": "das_last",
-                "params": [],
-                "id": 1,
-                "jsonrpc": "2.0"
-            }))?;
-
-        let value: Value = resp.into_json()?;
-        if let Some(result) = value["result"].as_array() {
-            let number = result.get(0)
-                .and_then(|v| v.as_u64())
-                .map(|n| n as u32)
-                .ok_or_else(|| anyhow!("Invalid number format"))?;
-
-            let hash_str = result.get(1)
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| anyhow!("Invalid hash format"))?;
-
-            let hash = hex::decode(&hash_str.trim_start_matches("0x"))?;
-
-            Ok(Some((number, hash)))
-        } else {
-            Ok(None)
+    /// is in an unexpected format. A JSON-RPC error object in the response is returned as a
+    /// `JsonRpcError`.
+    pub async fn get_latest_block(&self) -> Result<Option<DasBlock>> {
+        let value = self.call("das_last", json!([])).await?;
+        if let Some(error) = json_rpc_error(&value) {
+            return Err(error.into());
+        }
+        das_block(&value["result"])
+    }
+
+    /// Subscribes to the blocks processed by DAS.
+    ///
+    /// Opens a `das_subscribeBlocks` subscription over WebSocket on the first endpoint that
+    /// supports it, at the endpoint URL with its `http` scheme replaced by `ws`. If no endpoint
+    /// does, falls back to polling `das_last`, so the stream works with any DAS node.
+    ///
+    /// # Returns
+    ///
+    /// A stream of the new blocks. A subscription yields the blocks as
+    /// DAS pushes them, while polling only yields the latest block whenever it changes.
+    pub async fn subscribe_blocks(&self) -> BoxStream<'_, Result<DasBlock>> {
+        for url in self.transport.urls() {
+            match self.subscribe_ws(url).await {
+                Ok((client, subscription)) => {
+                    return stream::unfold((client, subscription), |(client, mut subscription)| {
+                        async move {
+                            let block = subscription.next().await?.map_err(anyhow::Error::from);
+                            let block = block.and_then(|value| {
+                                das_block(&value)?
+                                    .ok_or_else(|| anyhow!("Unexpected notification format"))
+                            });
+                            Some((block, (client, subscription)))
+                        }
+                    })
+                    .boxed()
+                },
+                Err(e) => log::debug!("Failed to subscribe to the blocks of {}: {:?}", url, e),
+            }
         }
+
+        log::info!("DAS endpoints do not support block subscriptions, polling das_last instead");
+        stream::unfold((None, true), move |(mut last, mut first)| async move {
+            loop {
+                if !first {
+                    tokio::time::sleep(BLOCK_POLL_INTERVAL).await;
+                }
+                first = false;
+                match self.get_latest_block().await {
+                    Ok(Some(block)) if last.as_ref() != Some(&block) => {
+                        last = Some(block.clone());
+                        return Some((Ok(block), (last, first)));
+                    },
+                    Ok(_) => {},
+                    Err(e) => return Some((Err(e), (last, first))),
+                }
+            }
+        })
+        .boxed()
+    }
+
+    async fn subscribe_ws(&self, url: &str) -> Result<(WsClient, Subscription<Value>)> {
+        let url = match url.strip_prefix("http") {
+            Some(rest) => format!("ws{}", rest),
+            None => url.to_string(),
+        };
+        let client = WsClientBuilder::default()
+            .connection_timeout(self.timeout)
+            .request_timeout(self.timeout)
+            .set_headers(self.headers.clone())
+            .build(&url)
+            .await?;
+        let subscription = client
+            .subscribe("das_subscribeBlocks", rpc_params![], "das_unsubscribeBlocks")
+            .await?;
+        Ok((client, subscription))
     }
 
     /// Checks the data availability for a given block hash in the DAS system.
@@ -96,21 +440,332 @@ impl DasClient {
     /// # Errors
     ///
     /// Returns an error if the request to the RPC server fails, or if the response data
-    /// is in an unexpected format.
-    pub fn check_data_availability(&self, block_hash: &str) -> Result<Option<bool>> {
-        let resp = ureq::post(&self.rpc_url)
-            .send_json(json!({
-                "method": "das_isAvailable",
-                "params": [block_hash],
-                "id": 1,
-                "jsonrpc": "2.0"
-            }))?;
-    
-        let value: Value = resp.into_json()?;
+    /// is in an unexpected format. A JSON-RPC error object in the response is returned as a
+    /// `JsonRpcError`.
+    pub async fn check_data_availability(&self, block_hash: &str) -> Result<Option<bool>> {
+        let value = self.call("das_isAvailable", json!([block_hash])).await?;
+        if let Some(error) = json_rpc_error(&value) {
+            return Err(error.into());
+        }
         match value.get("result") {
             Some(Value::Bool(is_available)) => Ok(Some(*is_available)),
             None => Ok(None),
             _ => Err(anyhow!("Unexpected response format")),
         }
     }
+
+    /// Checks the data availability of several blocks with a single JSON-RPC batch request.
+    ///
+    /// # Arguments
+    ///
+    /// * `block_hashes` - The hashes of the blocks to check, as for `check_data_availability`.
+    ///
+    /// # Returns
+    ///
+    /// The availability of each block, in the order of `block_hashes`. An entry is `None` if
+    /// the availability is not known yet, or if the response for that block is missing or
+    /// failed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request to the RPC server fails, or a `JsonRpcError` if the
+    /// server rejected the whole batch.
+    pub async fn check_data_availability_batch(
+        &self,
+        block_hashes: &[&str],
+    ) -> Result<Vec<Option<bool>>> {
+        if block_hashes.is_empty() {
+            return Ok(Vec::new());
+        }
+        let calls = block_hashes
+            .iter()
+            .map(|hash| ("das_isAvailable", json!([hash])))
+            .collect::<Vec<_>>();
+        let value = self.transport.call_batch(&calls).await?;
+
+        let mut availability = vec![None; block_hashes.len()];
+        let Some(responses) = value.as_array() else {
+            return match json_rpc_error(&value) {
+                Some(error) => Err(error.into()),
+                None => Err(anyhow!("Unexpected response format")),
+            };
+        };
+        for response in responses {
+            let Some(index) = response["id"].as_u64().map(|id| id as usize) else {
+                continue;
+            };
+            if let Some(error) = json_rpc_error(response) {
+                log::debug!("das_isAvailable failed in batch: {}", error);
+            } else if let Some(entry) = availability.get_mut(index) {
+                *entry = response["result"].as_bool();
+            }
+        }
+        Ok(availability)
+    }
+}
+
+// Reads a `[number, hash]` block from the result of `das_last` or a block notification.
+fn das_block(result: &Value) -> Result<Option<DasBlock>> {
+    let Some(result) = result.as_array() else {
+        return Ok(None);
+    };
+    let number = result.get(0)
+        .and_then(|v| v.as_u64())
+        .map(|n| n as u32)
+        .ok_or_else(|| anyhow!("Invalid number format"))?;
+
+    let hash_str = result.get(1)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("Invalid hash format"))?;
+
+    let hash = hex::decode(&hash_str.trim_start_matches("0x"))?;
+
+    Ok(Some(DasBlock { number, hash }))
+}
+
+// Reads the error object of a JSON-RPC response, if it has one.
+fn json_rpc_error(value: &Value) -> Option<JsonRpcError> {
+    let error = value.get("error")?;
+    Some(JsonRpcError {
+        code: error["code"].as_i64().unwrap_or_default(),
+        message: error["message"].as_str().unwrap_or_default().to_string(),
+    })
+}
+
+// Reads the genesis hash from a `das_chainInfo` response.
+fn genesis_hash(value: &Value) -> Result<Vec<u8>> {
+    let hash = value["result"]["genesisHash"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Unexpected response format"))?;
+    Ok(hex::decode(hash.trim_start_matches("0x"))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc_transport::tests::{
+        keep_alive_server, recording_server, silent_server, stub_server,
+    };
+    use futures::TryStreamExt;
+    use jsonrpsee::{server::ServerBuilder, RpcModule};
+    use std::sync::atomic::Ordering;
+
+    fn block(number: u32, hash: &[u8]) -> DasBlock {
+        DasBlock { number, hash: hash.to_vec() }
+    }
+
+    #[test]
+    fn das_last_results_are_read_as_blocks() {
+        let block = das_block(&json!([7, "0x0A0b"])).unwrap().unwrap();
+        assert_eq!(block, DasBlock { number: 7, hash: vec![10, 11] });
+        assert_eq!(block.hash_hex(), "0a0b");
+
+        assert_eq!(das_block(&Value::Null).unwrap(), None);
+        assert!(das_block(&json!([7, "0xzz"])).is_err());
+    }
+
+    #[test]
+    fn genesis_hash_is_read_from_chain_info() {
+        let value = json!({ "jsonrpc": "2.0", "id": 1, "result": { "genesisHash": "0x0102" } });
+        assert_eq!(genesis_hash(&value).unwrap(), vec![1, 2]);
+
+        let error = json!({ "jsonrpc": "2.0", "id": 1, "error": { "code": -32601 } });
+        assert!(genesis_hash(&error).is_err());
+    }
+
+    #[tokio::test]
+    async fn latest_block_is_read_from_das_last() {
+        let url = stub_server(vec![(200, r#"{"jsonrpc":"2.0","id":1,"result":[7,"0x0a0b"]}"#)]);
+        let latest = DasClient::new(url).get_latest_block().await.unwrap();
+        assert_eq!(latest, Some(block(7, &[10, 11])));
+
+        let url = stub_server(vec![(200, r#"{"jsonrpc":"2.0","id":1,"result":null}"#)]);
+        assert_eq!(DasClient::new(url).get_latest_block().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn availability_is_read_from_das_is_available() {
+        let url = stub_server(vec![
+            (200, r#"{"jsonrpc":"2.0","id":1,"result":true}"#),
+            (200, r#"{"jsonrpc":"2.0","id":1,"result":"yes"}"#),
+        ]);
+        let das = DasClient::new(url);
+        assert_eq!(das.check_data_availability("0a0b").await.unwrap(), Some(true));
+        assert!(das.check_data_availability("0a0b").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn stalled_requests_fail_with_a_timeout() {
+        let config = DasClientConfig {
+            timeout: Duration::from_millis(100),
+            retry: RetryPolicy { retries: 1, backoff: Duration::from_millis(1) },
+            ..Default::default()
+        };
+        let das = DasClient::with_config(vec![silent_server()], SelectionPolicy::Failover, config);
+
+        let error = das.get_latest_block().await.unwrap_err();
+        assert!(error.is::<RequestTimeout>(), "{}", error);
+        let error = das.check_data_availability("0a0b").await.unwrap_err();
+        assert!(error.is::<RequestTimeout>(), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn sequential_requests_reuse_a_pooled_connection() {
+        let (url, connections) =
+            keep_alive_server(r#"{"jsonrpc":"2.0","id":1,"result":[7,"0x0a0b"]}"#);
+        let das = DasClient::new(url);
+        for _ in 0..3 {
+            assert_eq!(das.get_latest_block().await.unwrap(), Some(block(7, &[10, 11])));
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn unreachable_endpoints_are_retried() {
+        let url = stub_server(vec![
+            (503, ""),
+            (502, ""),
+            (200, r#"{"jsonrpc":"2.0","id":1,"result":[7,"0x0a0b"]}"#),
+        ]);
+        let config = DasClientConfig {
+            retry: RetryPolicy { retries: 2, backoff: Duration::from_millis(1) },
+            ..Default::default()
+        };
+        let das = DasClient::with_config(vec![url], SelectionPolicy::Failover, config);
+        assert_eq!(das.get_latest_block().await.unwrap(), Some(block(7, &[10, 11])));
+    }
+
+    #[tokio::test]
+    async fn json_rpc_errors_are_not_retried() {
+        let url = stub_server(vec![
+            (200, r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"busy"}}"#),
+            (200, r#"{"jsonrpc":"2.0","id":1,"result":true}"#),
+        ]);
+        let das = DasClient::new(url);
+        let first = das.check_data_availability("0a0b").await;
+        assert_ne!(first.ok().flatten(), Some(true));
+        // The second response is left for the next request.
+        assert_eq!(das.check_data_availability("0a0b").await.unwrap(), Some(true));
+    }
+
+    #[tokio::test]
+    async fn json_rpc_error_objects_are_surfaced() {
+        const ERROR: &str =
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32601,"message":"method not found"}}"#;
+        let expected = JsonRpcError { code: -32601, message: "method not found".into() };
+        let das = DasClient::new(stub_server(vec![(200, ERROR), (200, ERROR)]));
+
+        let error = das.get_latest_block().await.unwrap_err();
+        assert_eq!(error.downcast_ref::<JsonRpcError>(), Some(&expected));
+        let error = das.check_data_availability("0a0b").await.unwrap_err();
+        assert_eq!(error.downcast_ref::<JsonRpcError>(), Some(&expected));
+    }
+
+    #[tokio::test]
+    async fn batch_responses_are_mapped_back_by_id() {
+        // Out of order, with an error for the second hash and no response for the fourth.
+        const BATCH: &str = r#"[
+            {"jsonrpc":"2.0","id":2,"result":false},
+            {"jsonrpc":"2.0","id":0,"result":true},
+            {"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"unknown block"}}
+        ]"#;
+        let das = DasClient::new(stub_server(vec![(200, BATCH)]));
+        let availability =
+            das.check_data_availability_batch(&["0a", "0b", "0c", "0d"]).await.unwrap();
+        assert_eq!(availability, vec![Some(true), None, Some(false), None]);
+    }
+
+    #[tokio::test]
+    async fn subscribed_blocks_are_yielded() {
+        let mut module = RpcModule::new(());
+        module
+            .register_subscription(
+                "das_subscribeBlocks",
+                "das_block",
+                "das_unsubscribeBlocks",
+                |_, mut sink, _| {
+                    sink.accept()?;
+                    let _ = sink.send(&json!([7, "0x0a"]));
+                    let _ = sink.send(&json!([8, "0x0b"]));
+                    Ok(())
+                },
+            )
+            .unwrap();
+        let server = ServerBuilder::default().build("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", server.local_addr().unwrap());
+        let _handle = server.start(module).unwrap();
+
+        let das = DasClient::new(url);
+        let blocks = das.subscribe_blocks().await.take(2).try_collect::<Vec<_>>().await.unwrap();
+        assert_eq!(blocks, vec![block(7, &[10]), block(8, &[11])]);
+    }
+
+    #[tokio::test]
+    async fn blocks_are_polled_without_subscriptions() {
+        // The first connection is the refused WebSocket handshake.
+        let url = stub_server(vec![
+            (200, ""),
+            (200, r#"{"jsonrpc":"2.0","id":1,"result":[7,"0x0a"]}"#),
+            (200, r#"{"jsonrpc":"2.0","id":1,"result":[7,"0x0a"]}"#),
+            (200, r#"{"jsonrpc":"2.0","id":1,"result":[8,"0x0b"]}"#),
+        ]);
+        let das = DasClient::new(url);
+        let blocks = das.subscribe_blocks().await.take(2).try_collect::<Vec<_>>().await.unwrap();
+        assert_eq!(blocks, vec![block(7, &[10]), block(8, &[11])]);
+    }
+
+    #[tokio::test]
+    async fn health_reports_healthy_and_unhealthy_nodes() {
+        let url = stub_server(vec![
+            (200, r#"{"jsonrpc":"2.0","id":1,"result":true}"#),
+            (200, r#"{"jsonrpc":"2.0","id":1,"result":false}"#),
+            (200, r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32601,"message":"not found"}}"#),
+            (200, r#"{"jsonrpc":"2.0","id":1,"result":{"isSyncing":true,"peers":3}}"#),
+        ]);
+        let das = DasClient::new(url);
+        assert!(das.health().await.unwrap());
+        assert!(!das.health().await.unwrap());
+        // Falls back to `system_health`.
+        assert!(!das.health().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn wait_until_healthy_waits_for_a_healthy_node() {
+        let url = stub_server(vec![
+            (200, r#"{"jsonrpc":"2.0","id":1,"result":false}"#),
+            (200, r#"{"jsonrpc":"2.0","id":1,"result":true}"#),
+        ]);
+        DasClient::new(url).wait_until_healthy(Duration::from_secs(5)).await.unwrap();
+
+        let url = stub_server(vec![(200, r#"{"jsonrpc":"2.0","id":1,"result":false}"#)]);
+        let das = DasClient::new(url);
+        assert!(das.wait_until_healthy(Duration::from_millis(100)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn bearer_token_is_sent_with_requests() {
+        let (url, requests) =
+            recording_server(vec![(200, r#"{"jsonrpc":"2.0","id":1,"result":true}"#)]);
+        let das = DasClientBuilder::new(url)
+            .with_timeout(Duration::from_secs(5))
+            .with_bearer_token("secret".into())
+            .build()
+            .unwrap();
+        assert_eq!(das.check_data_availability("0a0b").await.unwrap(), Some(true));
+
+        let request = requests.recv().unwrap().to_ascii_lowercase();
+        assert!(request.contains("authorization: bearer secret"), "{}", request);
+    }
+
+    #[test]
+    fn invalid_bearer_tokens_are_rejected() {
+        let builder = DasClientBuilder::new("http://127.0.0.1:1".into());
+        assert!(builder.with_bearer_token("bad\ntoken".into()).build().is_err());
+    }
+
+    #[test]
+    fn empty_url_lists_are_rejected() {
+        let builder = DasClientBuilder::new("http://127.0.0.1:1".into());
+        assert!(builder.with_urls(vec![], SelectionPolicy::Failover).build().is_err());
+    }
 }
@@ -0,0 +1,125 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Layout of the node data directory.
+//!
+//! Everything a node persists lives under one directory, so that backing it up and restoring it
+//! restores the whole node identity:
+//!
+//! ```text
+//! <data_dir>/
+//!     network/node_key    libp2p identity of the node
+//!     frost/audit.log     audit log of the completed rounds, when enabled
+//! ```
+//!
+//! FROST key shares, cached validator addresses and the looper state are kept in memory only,
+//! and are rebuilt when the node restarts.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Default data directory, relative to the working directory.
+pub const DEFAULT_DATA_DIR: &str = "redlight-data";
+
+const NETWORK_DIR: &str = "network";
+const FROST_DIR: &str = "frost";
+
+/// Paths of the persisted state inside a data directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DataDir {
+    root: PathBuf,
+}
+
+impl DataDir {
+    /// Describes the layout of the data directory at `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        DataDir { root: root.into() }
+    }
+
+    /// The data directory itself.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// File holding the libp2p identity of the node.
+    pub fn node_key(&self) -> PathBuf {
+        self.root.join(NETWORK_DIR).join("node_key")
+    }
+
+    /// Directory holding the FROST audit log.
+    pub fn frost(&self) -> PathBuf {
+        self.root.join(FROST_DIR)
+    }
+
+    /// File holding the audit log of the completed key rotations and signatures.
+    pub fn audit_log(&self) -> PathBuf {
+        self.frost().join("audit.log")
+    }
+}
+
+/// Creates the data directory layout at `path`, keeping what already exists.
+///
+/// The directories are only accessible by the current user, as they hold secret keys.
+pub fn init_data_dir(path: impl AsRef<Path>) -> Result<()> {
+    let data_dir = DataDir::new(path.as_ref());
+    let dirs = [
+        data_dir.root().to_path_buf(),
+        data_dir.root().join(NETWORK_DIR),
+        data_dir.frost(),
+    ];
+    for dir in dirs.iter() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create data directory {}", dir.display()))?;
+        restrict_to_owner(dir)?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn restrict_to_owner(dir: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+        .with_context(|| format!("Failed to set the permissions of {}", dir.display()))
+}
+
+#[cfg(not(unix))]
+fn restrict_to_owner(_dir: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn init_creates_layout() {
+        let root = std::env::temp_dir().join(format!("redlight-data-{}", std::process::id()));
+        init_data_dir(&root).unwrap();
+        // Initializing an existing directory keeps it.
+        init_data_dir(&root).unwrap();
+
+        let data_dir = DataDir::new(&root);
+        assert!(data_dir.node_key().parent().unwrap().is_dir());
+        assert!(data_dir.frost().is_dir());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&root).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
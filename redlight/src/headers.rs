@@ -0,0 +1,236 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Chain headers joined with the DAS availability of the latest DAS block, and the queries of
+//! that availability.
+
+use crate::das_rpc::{DasBlock, DasClient};
+use anyhow::{Context, Result};
+use futures::{
+    stream::{self, BoxStream},
+    Stream, StreamExt, TryStreamExt,
+};
+use rc_validator::Clock;
+use redoxt::{Client, H256};
+use std::time::Duration;
+
+// Delay between two availability checks while waiting for DAS to confirm a block.
+const DAS_CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A chain header and the availability of the latest DAS block when it was received.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeaderWithAvailability {
+    /// Number of the chain header.
+    pub header_number: u32,
+    /// Hash of the chain header.
+    pub header_hash: H256,
+    /// Number of the latest DAS block.
+    pub block_number: u32,
+    /// Hash of the latest DAS block.
+    pub block_hash: Vec<u8>,
+    /// Whether the data of the DAS block is available.
+    pub is_available: bool,
+}
+
+/// Subscription to chain headers correlated with DAS availability.
+pub trait SubscribeHeadersWithDas {
+    /// Subscribes to new best block headers, joining each with the availability of the latest
+    /// DAS block.
+    ///
+    /// Headers received while DAS has no block, or no availability result for it, are skipped.
+    /// Errors from DAS are yielded for the header they occurred on and the stream goes on, so the
+    /// caller decides whether to stop.
+    fn subscribe_headers_with_das<'a>(
+        &'a self,
+        das: &'a DasClient,
+    ) -> BoxStream<'a, Result<HeaderWithAvailability>>;
+}
+
+impl SubscribeHeadersWithDas for Client {
+    fn subscribe_headers_with_das<'a>(
+        &'a self,
+        das: &'a DasClient,
+    ) -> BoxStream<'a, Result<HeaderWithAvailability>> {
+        let headers = stream::once(self.api().blocks().subscribe_best())
+            .map_ok(|blocks| blocks.map_err(anyhow::Error::from))
+            .map_err(anyhow::Error::from)
+            .try_flatten()
+            .map_ok(|block| (block.header().number, block.hash()));
+        with_das_availability(headers, das)
+    }
+}
+
+/// Joins each `(number, hash)` chain header of `headers` with the availability of the latest DAS
+/// block, as `SubscribeHeadersWithDas::subscribe_headers_with_das` does.
+pub fn with_das_availability<'a, S>(
+    headers: S,
+    das: &'a DasClient,
+) -> BoxStream<'a, Result<HeaderWithAvailability>>
+where
+    S: Stream<Item = Result<(u32, H256)>> + Send + 'a,
+{
+    headers
+        .try_filter_map(move |(header_number, header_hash)| async move {
+            let availability = das_availability(das).await?;
+            Ok(availability.map(|(das_block, is_available)| HeaderWithAvailability {
+                header_number,
+                header_hash,
+                block_number: das_block.number,
+                block_hash: das_block.hash,
+                is_available,
+            }))
+        })
+        .boxed()
+}
+
+/// Queries the latest DAS block and whether its data is available.
+///
+/// # Returns
+///
+/// The block and its availability, or `None` if DAS has no block or no availability result yet.
+pub async fn das_availability(das: &DasClient) -> Result<Option<(DasBlock, bool)>> {
+    let Some(block) = das.get_latest_block().await.context("Failed to get the latest DAS block")?
+    else {
+        return Ok(None);
+    };
+    let is_available = das
+        .check_data_availability(&block.hash_hex())
+        .await
+        .context("Failed to check block availability")?;
+    Ok(is_available.map(|is_available| (block, is_available)))
+}
+
+/// DAS did not determine the availability of a block in time.
+#[derive(Debug)]
+pub struct DasConfirmationTimeout {
+    /// Number of the DAS block.
+    pub block_number: u32,
+    /// How long the availability was waited for.
+    pub timeout: Duration,
+}
+
+impl std::fmt::Display for DasConfirmationTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Availability of DAS block #{} still unknown after {:?}",
+            self.block_number, self.timeout
+        )
+    }
+}
+
+impl std::error::Error for DasConfirmationTimeout {}
+
+/// Queries the latest DAS block, then waits up to `timeout`, as measured by `clock`, for DAS to
+/// determine whether its data is available.
+///
+/// # Returns
+///
+/// The block and its availability, `None` if DAS has no block, or a `DasConfirmationTimeout`
+/// error if the availability is still unknown when the timeout elapses.
+pub async fn await_das_confirmation(
+    das_client: &DasClient,
+    timeout: Duration,
+    clock: &dyn Clock,
+) -> Result<Option<(DasBlock, bool)>> {
+    let Some(block) =
+        das_client.get_latest_block().await.context("Failed to get the latest DAS block")?
+    else {
+        return Ok(None);
+    };
+    let block_hash_hex = block.hash_hex();
+    let deadline = clock.now() + timeout;
+    loop {
+        let is_available = das_client
+            .check_data_availability(&block_hash_hex)
+            .await
+            .context("Failed to check block availability")?;
+        if let Some(is_available) = is_available {
+            return Ok(Some((block, is_available)));
+        }
+        let now = clock.now();
+        if now >= deadline {
+            return Err(DasConfirmationTimeout { block_number: block.number, timeout }.into());
+        }
+        let remaining = deadline.saturating_duration_since(now);
+        tokio::time::sleep(DAS_CONFIRMATION_POLL_INTERVAL.min(remaining)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc_transport::tests::{recording_server, stub_server};
+
+    #[tokio::test]
+    async fn headers_are_joined_with_das_availability() {
+        let url = stub_server(vec![
+            // DAS has no block yet when the first header arrives.
+            (200, r#"{"jsonrpc":"2.0","id":1,"result":null}"#),
+            (200, r#"{"jsonrpc":"2.0","id":1,"result":[7,"0x0a0b"]}"#),
+            (200, r#"{"jsonrpc":"2.0","id":1,"result":true}"#),
+            (200, r#"{"jsonrpc":"2.0","id":1,"result":[7,"0xzz"]}"#),
+            (200, r#"{"jsonrpc":"2.0","id":1,"result":[8,"0x0c"]}"#),
+            (200, r#"{"jsonrpc":"2.0","id":1,"result":false}"#),
+        ]);
+        let das = DasClient::new(url);
+        let headers = stream::iter((1..=4u32).map(|n| Ok((n, H256::repeat_byte(n as u8)))));
+
+        let items: Vec<_> = with_das_availability(headers, &das).collect().await;
+        assert_eq!(items.len(), 3);
+        assert_eq!(
+            items[0].as_ref().unwrap(),
+            &HeaderWithAvailability {
+                header_number: 2,
+                header_hash: H256::repeat_byte(2),
+                block_number: 7,
+                block_hash: vec![10, 11],
+                is_available: true,
+            }
+        );
+        // A DAS error is yielded for its header, and the following headers are still joined.
+        assert!(items[1].is_err());
+        let last = items[2].as_ref().unwrap();
+        assert_eq!((last.header_number, last.block_number, last.is_available), (4, 8, false));
+    }
+
+    #[tokio::test]
+    async fn das_confirmation_times_out_on_the_clock() {
+        const UNKNOWN: &str = r#"{"jsonrpc":"2.0","id":1}"#;
+        let (url, requests) = recording_server(vec![
+            (200, r#"{"jsonrpc":"2.0","id":1,"result":[7,"0x0a0b"]}"#),
+            (200, UNKNOWN),
+            (200, UNKNOWN),
+            (200, UNKNOWN),
+        ]);
+        let das_client = DasClient::new(url);
+        let clock = rc_validator::ManualClock::new(0);
+        let timeout = Duration::from_secs(3_600);
+
+        // The timeout elapses once the first availability check was sent, without waiting for it.
+        let advance = async {
+            let mut received = 0;
+            while received < 2 {
+                received += requests.try_iter().count();
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            clock.advance(timeout);
+        };
+        let confirmation = await_das_confirmation(&das_client, timeout, &clock);
+        let (result, ()) = tokio::join!(confirmation, advance);
+
+        let error = result.unwrap_err();
+        assert_eq!(error.downcast_ref::<DasConfirmationTimeout>().unwrap().block_number, 7);
+    }
+}
@@ -0,0 +1,24 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Clients of the DAS node used by the Melodot light client.
+//!
+//! They are published as a library so that other tools can query DAS availability and join it
+//! with chain headers the way the light client does.
+
+pub mod das_rpc;
+pub mod headers;
+pub mod rpc_transport;
+
+pub use das_rpc::DasClient;
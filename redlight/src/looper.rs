@@ -12,16 +12,658 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::DasClient;
+use crate::{
+    das_rpc::{DasBlock, JsonRpcError, RequestTimeout},
+    headers::{await_das_confirmation, das_availability, DasConfirmationTimeout},
+    metrics::LooperMetrics, rotation::rotate_and_register, DasClient,
+};
 use anyhow::{anyhow, Context};
 use codec::Encode;
-use log::{error, info};
-use rc_validator::Service as ValidatorService;
-use redoxt::{Client, ClientSync};
-use std::time::Instant;
-use tokio::sync::mpsc::Sender;
+use futures::{FutureExt, Stream};
+use log::{error, info, warn};
+use rc_validator::{Clock, DkgSignature, Service as ValidatorService, SystemClock};
+use redoxt::{
+    domain_separated_message, metadata_signing_message, AlreadySubmitted, Client, ClientSync,
+    Disconnected, ExtrinsicFailed, Finality, RedoxtError, SubmissionFailed, SubmitReceipt,
+};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use subxt::{tx::TxProgress, OnlineClient};
+use tokio::{
+    sync::{
+        mpsc::{self, Sender, UnboundedReceiver, UnboundedSender},
+        OwnedSemaphorePermit, Semaphore,
+    },
+    task::JoinHandle,
+};
 use tokio_stream::StreamExt;
 
+/// Builds the metadata submitted to the `task` pallet for a DAS availability result.
+///
+/// The layout is the SCALE encoding of `(block_number, block_hash, is_available)` followed by
+/// the SCALE-encoded task `id` and `nonce`. The signed bytes are built from it by
+/// `signing_message`.
+pub(crate) fn build_metadata_message(
+    block_number: u32,
+    block_hash: Vec<u8>,
+    is_available: bool,
+    id: u32,
+    nonce: u32,
+) -> Vec<u8> {
+    let metadata = (block_number, block_hash, is_available);
+    let mut msg = metadata.encode();
+    msg.extend_from_slice(&id.encode());
+    msg.extend_from_slice(&nonce.encode());
+    msg
+}
+
+/// Returns the bytes the validator network signs for the metadata message `msg` submitted with
+/// the task `id` and `nonce`, which are the bytes the `task` pallet verifies.
+pub(crate) fn signing_message(
+    msg: &[u8],
+    id: u32,
+    nonce: u32,
+    domain_separated: bool,
+) -> Vec<u8> {
+    let verified = metadata_signing_message(msg, id, nonce);
+    if domain_separated {
+        domain_separated_message(&verified)
+    } else {
+        verified
+    }
+}
+
+/// How the looper picks the nonce of each metadata submission.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NonceMode {
+    /// A counter incremented after each successful submission.
+    Counter,
+    /// The number of the DAS block, so the same block always maps to the same nonce.
+    BlockNumber,
+}
+
+/// Which best block notifications the looper processes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForkPolicy {
+    /// Every notification, including the blocks of short-lived forks.
+    EveryNotification,
+    /// Only the most recent notification of those queued while the previous block was being
+    /// processed. Bursts of best block changes during fork churn then cost one DAS poll and at
+    /// most one submission.
+    TipOnly,
+}
+
+/// Which chain head the looper follows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeadSource {
+    /// Best blocks, for the lowest latency. A submission included in a fork that is abandoned
+    /// later is lost, unless `LooperConfig::reconcile_on_finalized` is set.
+    Best,
+    /// Finalized blocks, which are never reverted.
+    Finalized,
+}
+
+/// Configuration of the looper.
+#[derive(Clone, Debug)]
+pub struct LooperConfig {
+    /// Number of blocks the DAS node may lag behind the chain tip before a warning is logged.
+    pub max_das_lag: u32,
+    /// How long a signature is reused when the same message is signed again, for example when a
+    /// submission is retried. A zero window disables the reuse.
+    pub sign_cache_window: Duration,
+    /// How the nonce of each submission is picked.
+    pub nonce_mode: NonceMode,
+    /// Sign `METADATA_DOMAIN_TAG` followed by the metadata message instead of the bare message.
+    /// The submitted metadata is unchanged, and the `task` pallet verifies the message with or
+    /// without the tag.
+    pub domain_separated: bool,
+    /// How long to wait for DAS to determine the availability of the latest block before
+    /// signing. A block whose availability is still unknown when it elapses is skipped. `None`
+    /// skips such blocks right away.
+    pub das_confirmation_timeout: Option<Duration>,
+    /// Which best block notifications are processed.
+    pub fork_policy: ForkPolicy,
+    /// Which chain head triggers the processing of a block.
+    pub head_source: HeadSource,
+    /// With `HeadSource::Best`, check each submission once the block including it is finalized,
+    /// and submit again the ones a reorg dropped from the chain.
+    pub reconcile_on_finalized: bool,
+    /// Maximum number of submissions waiting for their inclusion at the same time. With `1`,
+    /// each submission is included before the next header is processed. Above it, headers keep
+    /// being processed while earlier submissions are pending. Submissions are still signed and
+    /// sent to the transaction pool one at a time, in nonce order.
+    pub submission_concurrency: usize,
+    /// Genesis hash of the chain the DAS node must serve. When set, the looper checks it at
+    /// startup and does not run against a DAS node of another network.
+    pub das_genesis: Option<Vec<u8>>,
+    /// How far each submission must progress before it counts as submitted. With
+    /// `Finality::Finalized`, a submission a reorg drops from the chain fails instead of going
+    /// unnoticed, at the cost of waiting for finalization.
+    pub finality: Finality,
+}
+
+impl Default for LooperConfig {
+    fn default() -> Self {
+        LooperConfig {
+            max_das_lag: 10,
+            sign_cache_window: Duration::from_secs(60),
+            nonce_mode: NonceMode::Counter,
+            domain_separated: false,
+            das_confirmation_timeout: None,
+            fork_policy: ForkPolicy::EveryNotification,
+            head_source: HeadSource::Best,
+            reconcile_on_finalized: false,
+            submission_concurrency: 1,
+            das_genesis: None,
+            finality: Finality::InBlock,
+        }
+    }
+}
+
+// Number of recent blocks remembered to detect nonce collisions.
+const BLOCK_NONCE_HISTORY: usize = 1024;
+
+/// Takes the items already queued in `stream` behind `item` without waiting for new ones.
+///
+/// # Returns
+///
+/// The most recent item, and the number of items it superseded.
+pub(crate) fn latest_ready<S: Stream + Unpin>(
+    stream: &mut S,
+    mut item: S::Item,
+) -> (S::Item, usize) {
+    let mut skipped = 0;
+    while let Some(Some(next)) = stream.next().now_or_never() {
+        item = next;
+        skipped += 1;
+    }
+    (item, skipped)
+}
+
+/// Outcome of claiming the nonce of a DAS block.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum BlockNonce {
+    /// The block was not submitted yet, its number is the nonce to use.
+    Fresh(u32),
+    /// The same block was already submitted.
+    Duplicate,
+    /// Another block with the same number was already submitted, for example before a reorg.
+    Collision,
+}
+
+/// Remembers the blocks submitted with `NonceMode::BlockNumber`.
+///
+/// Only the most recent `capacity` block numbers are kept.
+pub(crate) struct BlockNonces {
+    capacity: usize,
+    submitted: BTreeMap<u32, Vec<u8>>,
+}
+
+impl BlockNonces {
+    pub(crate) fn new(capacity: usize) -> Self {
+        BlockNonces { capacity, submitted: BTreeMap::new() }
+    }
+
+    /// Claims the nonce of the block `block_number` with hash `block_hash`.
+    pub(crate) fn claim(&mut self, block_number: u32, block_hash: &[u8]) -> BlockNonce {
+        match self.submitted.get(&block_number) {
+            Some(hash) if hash == block_hash => BlockNonce::Duplicate,
+            Some(_) => BlockNonce::Collision,
+            None => {
+                self.submitted.insert(block_number, block_hash.to_vec());
+                while self.submitted.len() > self.capacity {
+                    self.submitted.pop_first();
+                }
+                BlockNonce::Fresh(block_number)
+            },
+        }
+    }
+
+    /// Gives up the claim on the block `block_number` after its submission failed, so the block
+    /// is submitted when it is seen again.
+    pub(crate) fn release(&mut self, block_number: u32) {
+        self.submitted.remove(&block_number);
+    }
+}
+
+/// A submission made on the best chain, waiting for the block including it to be finalized.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PendingSubmission {
+    /// Number of the DAS block the metadata is about.
+    pub(crate) block_number: u32,
+    /// Hash of the DAS block the metadata is about.
+    pub(crate) block_hash: Vec<u8>,
+    /// Whether the data of the DAS block is available.
+    pub(crate) is_available: bool,
+    /// Task id of the submission.
+    pub(crate) id: u32,
+    /// Nonce of the submission.
+    pub(crate) nonce: u32,
+    /// Number of a best block at or after the one including the submission.
+    pub(crate) included_by: u32,
+}
+
+impl PendingSubmission {
+    /// The metadata the `task` pallet stores for the submission.
+    pub(crate) fn stored_metadata(&self) -> Vec<u8> {
+        build_metadata_message(
+            self.block_number,
+            self.block_hash.clone(),
+            self.is_available,
+            self.id,
+            self.nonce,
+        )
+        .encode()
+    }
+}
+
+// Checks again the availability of the DAS blocks of `submissions`, which a reorg dropped, with
+// a single batch request. DAS may have settled a block differently since it was first submitted.
+// The recorded availability is kept for the blocks DAS gives no answer for, or if the batch fails.
+async fn refresh_availability(
+    das_client: &DasClient,
+    mut submissions: Vec<PendingSubmission>,
+) -> Vec<PendingSubmission> {
+    if submissions.is_empty() {
+        return submissions;
+    }
+    let hashes: Vec<String> =
+        submissions.iter().map(|submission| hex::encode(&submission.block_hash)).collect();
+    let hashes: Vec<&str> = hashes.iter().map(String::as_str).collect();
+    match das_client.check_data_availability_batch(&hashes).await {
+        Ok(availability) => {
+            for (submission, is_available) in submissions.iter_mut().zip(availability) {
+                if let Some(is_available) = is_available {
+                    submission.is_available = is_available;
+                }
+            }
+        },
+        Err(e) => warn!("⚠️ Fail to refresh the availability of reorged blocks: {:?}", e),
+    }
+    submissions
+}
+
+/// Submissions waiting to be checked against the finalized chain.
+#[derive(Default)]
+pub(crate) struct PendingSubmissions {
+    pending: Vec<PendingSubmission>,
+}
+
+impl PendingSubmissions {
+    pub(crate) fn push(&mut self, submission: PendingSubmission) {
+        self.pending.push(submission);
+    }
+
+    /// Removes and returns the submissions included at or before the finalized block
+    /// `finalized`.
+    pub(crate) fn take_settled(&mut self, finalized: u32) -> Vec<PendingSubmission> {
+        let (settled, pending) =
+            self.pending.drain(..).partition(|submission| submission.included_by <= finalized);
+        self.pending = pending;
+        settled
+    }
+}
+
+// Tracks the submissions made on the best chain and receives those a reorg dropped.
+struct Reconciler {
+    pending: Arc<Mutex<PendingSubmissions>>,
+    reorged: UnboundedReceiver<PendingSubmission>,
+}
+
+impl Reconciler {
+    // Starts checking the submissions against each finalized block.
+    fn spawn(rpc_client: Arc<Client>) -> Self {
+        let pending = Arc::new(Mutex::new(PendingSubmissions::default()));
+        let (reorged_tx, reorged) = mpsc::unbounded_channel();
+        tokio::spawn(reconcile_finalized(rpc_client, pending.clone(), reorged_tx));
+        Reconciler { pending, reorged }
+    }
+
+    // Records a submission that was just included in a best block.
+    async fn track(
+        &self,
+        rpc_client: &Client,
+        block_number: u32,
+        block_hash: Vec<u8>,
+        is_available: bool,
+        id: u32,
+        nonce: u32,
+    ) {
+        let pending = &self.pending;
+        track_submission(pending, rpc_client, block_number, block_hash, is_available, id, nonce)
+            .await
+    }
+
+    // Returns the submissions found missing from the finalized chain so far.
+    fn take_reorged(&mut self) -> Vec<PendingSubmission> {
+        let mut reorged = Vec::new();
+        while let Ok(submission) = self.reorged.try_recv() {
+            reorged.push(submission);
+        }
+        reorged
+    }
+}
+
+// Records in `pending` a submission that was just included in a best block.
+async fn track_submission(
+    pending: &Mutex<PendingSubmissions>,
+    rpc_client: &Client,
+    block_number: u32,
+    block_hash: Vec<u8>,
+    is_available: bool,
+    id: u32,
+    nonce: u32,
+) {
+    // The submission is in a block at or before the current best block.
+    let included_by = match rpc_client.api().blocks().at_latest().await {
+        Ok(block) => block.header().number,
+        Err(e) => {
+            warn!("⚠️ Fail to read the best block, not reconciling #{}: {:?}", nonce, e);
+            return;
+        },
+    };
+    let submission =
+        PendingSubmission { block_number, block_hash, is_available, id, nonce, included_by };
+    pending.lock().unwrap_or_else(|e| e.into_inner()).push(submission);
+}
+
+// Checks the pending submissions against each finalized block, and sends the ones missing from
+// the finalized chain to `reorged`. Stops once the receiving side of `reorged` is dropped.
+async fn reconcile_finalized(
+    rpc_client: Arc<Client>,
+    pending: Arc<Mutex<PendingSubmissions>>,
+    reorged: UnboundedSender<PendingSubmission>,
+) {
+    let mut finalized_sub = match rpc_client.api().blocks().subscribe_finalized().await {
+        Ok(subscription) => subscription,
+        Err(e) => {
+            error!("⚠️ Failed to subscribe to finalized blocks, not reconciling: {:?}", e);
+            return;
+        },
+    };
+
+    while let Some(block) = finalized_sub.next().await {
+        let block = match block {
+            Ok(block) => block,
+            Err(e) => {
+                warn!("⚠️ Error receiving finalized block: {:?}", e);
+                continue;
+            },
+        };
+        if reorged.is_closed() {
+            return;
+        }
+
+        let settled = pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take_settled(block.header().number);
+        for submission in settled {
+            let stored = rpc_client
+                .stored_metadata_at(submission.id, submission.nonce, block.hash())
+                .await;
+            match stored {
+                Ok(Some(stored)) if stored == submission.stored_metadata() => {
+                    info!("✅ Submission #{} finalized", submission.nonce);
+                },
+                Ok(_) => {
+                    warn!(
+                        "⚠️ Submission #{} of DAS block #{} was dropped by a reorg",
+                        submission.nonce, submission.block_number
+                    );
+                    if reorged.send(submission).is_err() {
+                        return;
+                    }
+                },
+                Err(e) => {
+                    warn!("⚠️ Fail to check submission #{}: {:?}", submission.nonce, e);
+                },
+            }
+        }
+    }
+}
+
+/// Information about a header processed by the looper, passed to the `on_header` hook.
+#[derive(Clone, Debug)]
+pub struct HeaderInfo {
+    /// Number of the chain header that triggered the processing.
+    pub header_number: u32,
+    /// Number of the latest DAS block that was checked.
+    pub block_number: u32,
+    /// Hash of the latest DAS block that was checked.
+    pub block_hash: Vec<u8>,
+    /// Whether the data of the DAS block is available.
+    pub is_available: bool,
+}
+
+/// Callback invoked for each processed header.
+pub type HeaderHook = Arc<dyn Fn(HeaderInfo) + Send + Sync>;
+
+/// Details of a metadata submission, passed to a `SubmissionObserver`.
+#[derive(Clone, Debug)]
+pub struct SubmissionInfo {
+    /// Number of the DAS block the metadata is about.
+    pub block_number: u32,
+    /// Hash of the DAS block the metadata is about.
+    pub block_hash: Vec<u8>,
+    /// Whether the data of the DAS block is available.
+    pub is_available: bool,
+    /// Task id of the submission.
+    pub id: u32,
+    /// Nonce of the submission.
+    pub nonce: u32,
+    /// When the header that triggered the submission was received.
+    pub received_at: Instant,
+}
+
+/// Observer of the metadata submissions made by the looper.
+///
+/// This is the extension point for integrators that want to record submissions, for example
+/// for metrics or persistence.
+pub trait SubmissionObserver: Send + Sync {
+    /// Called after metadata has been submitted.
+    fn on_submitted(&self, info: SubmissionInfo);
+
+    /// Called when submitting metadata failed.
+    fn on_failed(&self, err: &anyhow::Error);
+}
+
+/// State of a running looper, for operators to inspect.
+///
+/// Timestamps are wall-clock times, to the second, so that a stalled looper can be told apart
+/// from an idle one.
+#[derive(Clone, Debug, Serialize)]
+pub struct LooperStatus {
+    /// When the looper started.
+    pub started_at: SystemTime,
+    /// When the status last changed.
+    pub updated_at: SystemTime,
+    /// Number of the last chain header received.
+    pub last_header: Option<u32>,
+    /// When the last header was received.
+    pub last_header_at: Option<SystemTime>,
+    /// Number, hash and availability of the last DAS block checked.
+    pub last_das_block: Option<(u32, Vec<u8>, bool)>,
+    /// When the last DAS block was checked.
+    pub last_das_block_at: Option<SystemTime>,
+    /// Nonce of the last metadata submitted.
+    pub last_submitted_nonce: Option<u32>,
+    /// When the last metadata was submitted.
+    pub last_submitted_at: Option<SystemTime>,
+    /// Extrinsic and block of the last metadata submitted, when known. Submissions recovered
+    /// from a transient error do not report one.
+    pub last_receipt: Option<SubmitReceipt>,
+    /// Nonce the next submission will use in `NonceMode::Counter`.
+    pub nonce: u32,
+    /// The last error encountered, which may have been recovered from since.
+    pub last_error: Option<String>,
+    /// When the last error was encountered.
+    pub last_error_at: Option<SystemTime>,
+}
+
+impl LooperStatus {
+    fn new(now: SystemTime) -> Self {
+        LooperStatus {
+            started_at: now,
+            updated_at: now,
+            last_header: None,
+            last_header_at: None,
+            last_das_block: None,
+            last_das_block_at: None,
+            last_submitted_nonce: None,
+            last_submitted_at: None,
+            last_receipt: None,
+            nonce: 0,
+            last_error: None,
+            last_error_at: None,
+        }
+    }
+}
+
+/// Shared handle on the status of a running looper.
+#[derive(Clone)]
+pub struct LooperStatusHandle {
+    status: Arc<Mutex<LooperStatus>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl LooperStatusHandle {
+    pub(crate) fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// Creates a handle timestamping updates with `clock`.
+    pub(crate) fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        let status = LooperStatus::new(unix_time(clock.as_ref()));
+        LooperStatusHandle { status: Arc::new(Mutex::new(status)), clock }
+    }
+
+    /// Returns the clock the looper reads the time from.
+    pub(crate) fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    /// Returns a copy of the current status.
+    pub fn get(&self) -> LooperStatus {
+        self.status.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut LooperStatus, SystemTime)) {
+        let mut status = self.status.lock().unwrap_or_else(|e| e.into_inner());
+        let now = unix_time(self.clock.as_ref());
+        f(&mut status, now);
+        status.updated_at = now;
+    }
+
+    fn record_header(&self, number: u32) {
+        self.update(|status, now| {
+            status.last_header = Some(number);
+            status.last_header_at = Some(now);
+        });
+    }
+
+    fn record_das_block(&self, number: u32, hash: &[u8], is_available: bool) {
+        self.update(|status, now| {
+            status.last_das_block = Some((number, hash.to_vec(), is_available));
+            status.last_das_block_at = Some(now);
+        });
+    }
+
+    fn record_submission(&self, submitted_nonce: u32, next_nonce: u32) {
+        self.update(|status, now| {
+            status.last_submitted_nonce = Some(submitted_nonce);
+            status.last_submitted_at = Some(now);
+            status.nonce = next_nonce;
+        });
+    }
+
+    fn record_receipt(&self, receipt: SubmitReceipt) {
+        info!(
+            "📦 Extrinsic {:?} included in block #{} ({:?})",
+            receipt.tx_hash, receipt.block_number, receipt.block_hash
+        );
+        self.update(|status, _| status.last_receipt = Some(receipt));
+    }
+
+    fn record_error(&self, error: &impl Debug) {
+        self.update(|status, now| {
+            status.last_error = Some(format!("{:?}", error));
+            status.last_error_at = Some(now);
+        });
+    }
+}
+
+/// What a looper reports to besides its logs.
+///
+/// All of them are optional except the status, which defaults to a new handle.
+#[derive(Clone)]
+pub struct LooperHooks {
+    /// Metrics to report to.
+    pub metrics: Option<LooperMetrics>,
+    /// Hook invoked with the availability result of each processed header.
+    pub on_header: Option<HeaderHook>,
+    /// Observer notified of each submission.
+    pub observer: Option<Arc<dyn SubmissionObserver>>,
+    /// Status updated as headers are processed.
+    pub status: LooperStatusHandle,
+}
+
+impl Default for LooperHooks {
+    fn default() -> Self {
+        Self { metrics: None, on_header: None, observer: None, status: LooperStatusHandle::new() }
+    }
+}
+
+// Wall-clock time of `clock`, to the second.
+fn unix_time(clock: &dyn Clock) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(clock.unix())
+}
+
+/// Recently produced signatures, keyed by the signed message.
+///
+/// Entries expire once they are older than the configured window.
+pub(crate) struct SignatureCache<S> {
+    window: Duration,
+    entries: VecDeque<(Instant, Vec<u8>, S)>,
+}
+
+impl<S: Clone> SignatureCache<S> {
+    pub(crate) fn new(window: Duration) -> Self {
+        SignatureCache { window, entries: VecDeque::new() }
+    }
+
+    /// Returns the signature of `message` if it was signed within the window.
+    pub(crate) fn get(&mut self, message: &[u8], now: Instant) -> Option<S> {
+        self.evict(now);
+        self.entries.iter().find(|(_, msg, _)| msg == message).map(|(_, _, sign)| sign.clone())
+    }
+
+    /// Records the signature of `message`.
+    pub(crate) fn insert(&mut self, message: Vec<u8>, signature: S, now: Instant) {
+        self.evict(now);
+        if !self.window.is_zero() {
+            self.entries.push_back((now, message, signature));
+        }
+    }
+
+    fn evict(&mut self, now: Instant) {
+        while let Some((signed_at, _, _)) = self.entries.front() {
+            if now.saturating_duration_since(*signed_at) < self.window {
+                break;
+            }
+            self.entries.pop_front();
+        }
+    }
+}
+
 // A simplified function for handling finalized block headers.
 //
 // This asynchronous function subscribes to the latest finalized block headers from a blockchain node
@@ -34,103 +676,851 @@ use tokio_stream::StreamExt;
 // * `das_client` - Client instance to interact with the DAS system.
 // * `service` - Validator service for cryptographic operations like key rotation and signing.
 // * `error_sender` - Sender channel for forwarding encountered errors.
+// * `config` - Looper configuration.
+// * `hooks` - Metrics, hooks and status the looper reports to.
 pub async fn finalized_headers(
     rpc_client: Client,
     message_tx: Sender<Instant>,
     das_client: DasClient,
     service: ValidatorService,
     error_sender: Sender<anyhow::Error>,
+    config: LooperConfig,
+    hooks: LooperHooks,
     // database: Arc<Mutex<SqliteDasDb>>,
 ) {
     // Subscribe to new blockchain headers. If it fails, log the error and return.
-    let mut new_heads_sub = match rpc_client.api.blocks().subscribe_best().await {
+    let finality = match config.head_source {
+        HeadSource::Best => Finality::InBlock,
+        HeadSource::Finalized => Finality::Finalized,
+    };
+    let subscription = rpc_client.subscribe_blocks(finality).await;
+    let mut new_heads_sub = match subscription {
         Ok(subscription) => {
-            info!("🌐 Subscribed to finalized block headers");
+            info!("🌐 Subscribed to {:?} block headers", config.head_source);
             subscription
         },
         Err(e) => {
             error!("⚠️ Failed to subscribe to finalized blocks: {:?}", e);
+            hooks.status.record_error(&e);
             return;
         },
     };
 
     // A simple counter to keep track of processed headers.
     let mut nonce = 0;
+    let mut sign_cache = SignatureCache::new(config.sign_cache_window);
+    let mut block_nonces = BlockNonces::new(BLOCK_NONCE_HISTORY);
+    let mut quorum_lost = false;
+
+    // Submissions made on the best chain are checked once finalized.
+    let rpc_client = Arc::new(rpc_client);
+    let reconcile = config.head_source == HeadSource::Best && config.reconcile_on_finalized;
+    let mut reconciler = reconcile.then(|| Reconciler::spawn(rpc_client.clone()));
+
+    // Bounds the submissions waiting for their inclusion, when they run concurrently. A failed
+    // one sets `resync_nonce`, as the nonces sent after it may leave a gap.
+    let in_flight = (config.submission_concurrency > 1)
+        .then(|| Arc::new(Semaphore::new(config.submission_concurrency)));
+    let resync_nonce = Arc::new(AtomicBool::new(false));
+
+    // Refuse to submit availability read from a DAS node of another network.
+    if let Some(das_genesis) = &config.das_genesis {
+        if let Err(e) = das_client.verify_network(das_genesis).await {
+            error!("❌ DAS network mismatch: {:?}", e);
+            hooks.status.record_error(&e);
+            return;
+        }
+    }
 
     // Rotate the validator's key and register the new key with the blockchain.
-    let init_key = service.rotate_key().await.unwrap();
-    rpc_client.new_key(&init_key).await.unwrap();
+    if let Err(e) = rotate_and_register(&service, &rpc_client).await {
+        error!("❌ Failed to set up the validator key: {:?}", e);
+        hooks.status.record_error(&e);
+        return;
+    }
 
     // Process each new header message as it arrives.
-    while let Some(message) = new_heads_sub.next().await {
-        let received_at = Instant::now();
+    let mut disconnected = None;
+    while let Some(mut message) = new_heads_sub.next().await {
+        if config.fork_policy == ForkPolicy::TipOnly {
+            let (latest, skipped) = latest_ready(&mut new_heads_sub, message);
+            message = latest;
+            if skipped > 0 {
+                info!("Skipped {} superseded best block notifications", skipped);
+            }
+        }
+        let received_at = hooks.status.clock().now();
         if let Ok(block) = message {
             let header = block.header().clone();
             let block_number = header.number;
             info!("✅ Received finalized block header #{}", block_number.clone());
+            hooks.status.record_header(block_number);
 
             // Send the timestamp of the received header to the message channel.
             if let Err(error) = message_tx.send(received_at).await.context("Send failed") {
                 error!("❌ Fail to process finalized block header: {error}");
             }
 
-            // Retrieve the latest block information from the DAS system.
+            // Skip this cycle rather than submit stale availability if the DAS node is unhealthy.
+            match das_client.health().await {
+                Ok(true) => {},
+                Ok(false) => {
+                    warn!("⚠️ DAS node is unhealthy, skipping block #{}", header.number);
+                    continue;
+                },
+                Err(e) => {
+                    warn!("⚠️ Fail to check DAS health, skipping block #{}: {:?}", header.number, e);
+                    hooks.status.record_error(&e);
+                    continue;
+                },
+            }
+
+            // Retrieve the latest DAS block and check the availability of its data.
             // If it's not available or if there's an error, log it and continue or return.
-            let (block_number, block_hash) = match das_client.get_latest_block() {
-                Ok(Some((block_number, block_hash))) => (block_number, block_hash),
+            // Signing only starts once the availability is determined, so no signing round is
+            // spent on a block DAS has not settled yet.
+            let availability = match config.das_confirmation_timeout {
+                Some(timeout) => {
+                    await_das_confirmation(&das_client, timeout, hooks.status.clock()).await
+                },
+                None => das_availability(&das_client).await,
+            };
+            let (block, is_available) = match availability {
+                Ok(Some(availability)) => availability,
                 Ok(None) => {
                     info!("No new block available yet, continuing...");
                     continue;
                 },
+                Err(e) if e.is::<DasConfirmationTimeout>() => {
+                    warn!("⚠️ {}, skipping it", e);
+                    continue;
+                },
+                Err(e) if e.is::<RequestTimeout>() => {
+                    warn!("⚠️ DAS timed out, skipping block #{}: {}", header.number, e);
+                    hooks.status.record_error(&e);
+                    continue;
+                },
+                Err(e) if e.is::<JsonRpcError>() => {
+                    warn!("⚠️ DAS returned an error, skipping block #{}: {:?}", header.number, e);
+                    hooks.status.record_error(&e);
+                    continue;
+                },
                 Err(e) => {
-                    error!("❌ Fail to get latest block: {:?}", e);
+                    error!("❌ Fail to check DAS availability: {:?}", e);
+                    hooks.status.record_error(&e);
                     return;
                 },
             };
+            let block_hash_hex = block.hash_hex();
+            let DasBlock { number: block_number, hash: block_hash } = block;
+            hooks.status.record_das_block(block_number, &block_hash, is_available);
 
-            // Check the data availability of the latest block from DAS.
-            // If it's not available or if there's an error, log it and continue or return.
-            let block_hash_hex = hex::encode(&block_hash);
-            let is_available = match das_client.check_data_availability(&block_hash_hex) {
-                Ok(Some(is_available)) => is_available,
-                Ok(None) => {
-                    info!("No new block available yet, continuing...");
+            // Track how far DAS availability lags behind the chain tip.
+            let das_lag = header.number.saturating_sub(block_number);
+            if let Some(metrics) = &hooks.metrics {
+                metrics.das_lag.set(das_lag as u64);
+            }
+            if das_lag > config.max_das_lag {
+                warn!(
+                    "⚠️ DAS is {} blocks behind the chain tip (#{} vs #{}), max {}",
+                    das_lag, header.number, block_number, config.max_das_lag
+                );
+            }
+
+            if let Some(on_header) = &hooks.on_header {
+                on_header(HeaderInfo {
+                    header_number: header.number,
+                    block_number,
+                    block_hash: block_hash.clone(),
+                    is_available,
+                });
+            }
+
+            // Skip the block while too few validators are online to reach the signing threshold.
+            match service.signing_readiness().await {
+                Ok(readiness) if readiness.is_ready() => {
+                    if quorum_lost {
+                        info!(
+                            "✅ {} of {} required signers online, resuming submissions",
+                            readiness.participants, readiness.threshold
+                        );
+                        quorum_lost = false;
+                    }
+                },
+                Ok(readiness) => {
+                    if !quorum_lost {
+                        warn!(
+                            "⚠️ Only {} of {} required signers online, skipping submissions",
+                            readiness.participants, readiness.threshold
+                        );
+                        quorum_lost = true;
+                    }
+                    info!("Skipping block #{} until enough signers are online", block_number);
                     continue;
                 },
                 Err(e) => {
-                    error!("❌ Fail to check block availability: {:?}", e);
-                    return;
+                    warn!("⚠️ Fail to check signer readiness, skipping block #{}: {:?}", block_number, e);
+                    hooks.status.record_error(&e);
+                    continue;
+                },
+            }
+
+            // Submit again what a reorg dropped, before the current block.
+            if let Some(reconciler) = &mut reconciler {
+                let reorged = refresh_availability(&das_client, reconciler.take_reorged()).await;
+                for dropped in reorged {
+                    let resubmit_nonce = match config.nonce_mode {
+                        NonceMode::Counter => nonce,
+                        NonceMode::BlockNumber => dropped.nonce,
+                    };
+                    let msg = build_metadata_message(
+                        dropped.block_number,
+                        dropped.block_hash.clone(),
+                        dropped.is_available,
+                        dropped.id,
+                        resubmit_nonce,
+                    );
+                    let signed_msg =
+                        signing_message(&msg, dropped.id, resubmit_nonce, config.domain_separated);
+                    let signature =
+                        sign_cached(&service, &mut sign_cache, &hooks, &signed_msg).await;
+                    let res = match signature {
+                        Ok(signature) => {
+                            rpc_client
+                                .submit_metadata(
+                                    &msg,
+                                    dropped.id,
+                                    resubmit_nonce,
+                                    &signature,
+                                    config.finality,
+                                )
+                                .await
+                        },
+                        Err(e) => Err(e),
+                    };
+                    match res {
+                        Ok(receipt) => {
+                            info!("✅ Submitted DAS block #{} again", dropped.block_number);
+                            hooks.status.record_receipt(receipt);
+                            if config.nonce_mode == NonceMode::Counter {
+                                nonce = resubmit_nonce + 1;
+                                hooks.status.record_submission(resubmit_nonce, nonce);
+                            }
+                            reconciler
+                                .track(
+                                    &rpc_client,
+                                    dropped.block_number,
+                                    dropped.block_hash,
+                                    dropped.is_available,
+                                    dropped.id,
+                                    resubmit_nonce,
+                                )
+                                .await;
+                        },
+                        Err(e) => {
+                            warn!(
+                                "⚠️ Fail to submit DAS block #{} again: {:?}",
+                                dropped.block_number, e
+                            );
+                            hooks.status.record_error(&e);
+                        },
+                    }
+                }
+            }
+
+            let id = 1;
+
+            // Read the nonce from the chain again after a failed concurrent submission.
+            let counter_mode = config.nonce_mode == NonceMode::Counter;
+            if counter_mode && resync_nonce.swap(false, Ordering::Relaxed) {
+                match rpc_client.next_metadata_nonce(id).await {
+                    Ok(chain_nonce) => nonce = chain_nonce,
+                    Err(e) => {
+                        warn!("⚠️ Fail to read the on-chain nonce: {:?}", e);
+                        resync_nonce.store(true, Ordering::Relaxed);
+                    },
+                }
+            }
+
+            // Pick the nonce of this submission.
+            let submit_nonce = match config.nonce_mode {
+                NonceMode::Counter => nonce,
+                NonceMode::BlockNumber => match block_nonces.claim(block_number, &block_hash) {
+                    BlockNonce::Fresh(block_nonce) => block_nonce,
+                    BlockNonce::Duplicate => {
+                        info!("Block #{} already submitted, continuing...", block_number);
+                        continue;
+                    },
+                    BlockNonce::Collision => {
+                        warn!(
+                            "⚠️ Block #{} was already submitted with another hash, skipping 0x{}",
+                            block_number, block_hash_hex
+                        );
+                        continue;
+                    },
                 },
             };
 
             // Prepare and encode the metadata to be submitted to the blockchain.
-            let metadata = (block_number, block_hash, is_available);
-            let id = 1;
-            let mut msg = metadata.encode();
-            msg.extend_from_slice(&id.encode());
-            msg.extend_from_slice(&nonce.encode());
+            let msg = build_metadata_message(
+                block_number,
+                block_hash.clone(),
+                is_available,
+                id,
+                submit_nonce,
+            );
 
             // Sign the message and submit the metadata to the blockchain.
             // Log the success or failure of the submission.
-            let signature = service.start_signing(&msg.clone()).await.unwrap();
-            let res = rpc_client.submit_metadata(&msg, 1u32, nonce.clone(), &signature).await;
+            // Signers may still drop out after the readiness check, so a failed signing round
+            // skips the block rather than stopping the looper.
+            let signed_msg = signing_message(&msg, id, submit_nonce, config.domain_separated);
+            let signature =
+                match sign_cached(&service, &mut sign_cache, &hooks, &signed_msg).await {
+                    Ok(signature) => signature,
+                    Err(e) => {
+                        warn!("⚠️ Fail to sign metadata, skipping block #{}: {:?}", block_number, e);
+                        hooks.status.record_error(&e);
+                        block_nonces.release(block_number);
+                        continue;
+                    },
+                };
+            let submission = SubmissionInfo {
+                block_number,
+                block_hash: block_hash.clone(),
+                is_available,
+                id,
+                nonce: submit_nonce,
+                received_at,
+            };
+
+            // Hand the wait for the inclusion over to a task, once the pool accepted the
+            // extrinsic.
+            if let Some(in_flight) = &in_flight {
+                let permit =
+                    in_flight.clone().acquire_owned().await.expect("Semaphore is never closed");
+                let sent =
+                    rpc_client.submit_metadata_in_order(&msg, id, submit_nonce, &signature).await;
+                match sent {
+                    Ok(progress) => {
+                        nonce = submit_nonce + 1;
+                        let pending =
+                            reconciler.as_ref().map(|reconciler| reconciler.pending.clone());
+                        let context = SubmissionContext {
+                            rpc_client: rpc_client.clone(),
+                            hooks: hooks.clone(),
+                            pending,
+                            resync_nonce: resync_nonce.clone(),
+                            finality: config.finality,
+                        };
+                        tokio::spawn(complete_submission(context, progress, submission, permit));
+                    },
+                    Err(e) => {
+                        error!("❌ Submit metadata failed: {:?}", e);
+                        hooks.status.record_error(&e);
+                        block_nonces.release(block_number);
+                        if let Some(observer) = &hooks.observer {
+                            observer.on_failed(e.inner());
+                        }
+                    },
+                }
+                continue;
+            }
+
+            let res = rpc_client
+                .submit_metadata(&msg, id, submit_nonce, &signature, config.finality)
+                .await;
             match res {
-                Ok(_) => {
+                Ok(receipt) => {
                     info!("✅ Submit metadata success");
-                    nonce += 1;
+                    nonce = submit_nonce + 1;
+                    hooks.status.record_submission(submit_nonce, nonce);
+                    hooks.status.record_receipt(receipt);
+                    if let Some(reconciler) = &reconciler {
+                        reconciler
+                            .track(
+                                &rpc_client,
+                                block_number,
+                                block_hash,
+                                is_available,
+                                id,
+                                submit_nonce,
+                            )
+                            .await;
+                    }
+                    if let Some(observer) = &hooks.observer {
+                        observer.on_submitted(submission);
+                    }
+                },
+                // A previous attempt with this nonce is already in a block, so the nonce is used.
+                Err(e) if e.is::<AlreadySubmitted>() => {
+                    info!("✅ Metadata already submitted: {}", e);
+                    nonce = submit_nonce + 1;
+                    hooks.status.record_submission(submit_nonce, nonce);
+                },
+                // The call was included but rejected, so the nonce was not used. The
+                // next block is submitted with the same nonce.
+                Err(e) if e.is::<ExtrinsicFailed>() => {
+                    error!("❌ Submit metadata failed on chain: {}", e);
+                    hooks.status.record_error(&e);
+                    block_nonces.release(block_number);
+                    if let Some(observer) = &hooks.observer {
+                        observer.on_failed(e.inner());
+                    }
+                },
+                // The node could not be reached or did not answer in time, so the same
+                // submission is tried again, over a new connection if the previous one was
+                // lost. Other failures stop the looper.
+                Err(e @ (RedoxtError::Connection(_) | RedoxtError::Timeout(_))) => {
+                    warn!("⚠️ Submit metadata failed, retrying: {:?}", e);
+                    let retry: anyhow::Result<u32> = async {
+                        if matches!(e, RedoxtError::Connection(_)) {
+                            rpc_client.reconnect().await.context("Failed to reconnect")?;
+                        }
+                        match config.nonce_mode {
+                            NonceMode::Counter => {
+                                resubmit_metadata(
+                                    &rpc_client,
+                                    &service,
+                                    &mut sign_cache,
+                                    &hooks,
+                                    &submission,
+                                    &config,
+                                )
+                                .await
+                            },
+                            // The nonce is tied to the block, so the same submission is repeated.
+                            NonceMode::BlockNumber => rpc_client
+                                .submit_metadata(
+                                    &msg,
+                                    id,
+                                    submit_nonce,
+                                    &signature,
+                                    config.finality,
+                                )
+                                .await
+                                .map(|_| submit_nonce + 1)
+                                .map_err(anyhow::Error::from),
+                        }
+                    }
+                    .await;
+                    match retry {
+                        Ok(next_nonce) => {
+                            info!("✅ Submit metadata success");
+                            nonce = next_nonce;
+                            hooks.status.record_submission(next_nonce - 1, nonce);
+                            if let Some(reconciler) = &reconciler {
+                                reconciler
+                                    .track(
+                                        &rpc_client,
+                                        submission.block_number,
+                                        submission.block_hash.clone(),
+                                        is_available,
+                                        id,
+                                        next_nonce - 1,
+                                    )
+                                    .await;
+                            }
+                            if let Some(observer) = &hooks.observer {
+                                // The retry may have used the on-chain nonce.
+                                observer.on_submitted(SubmissionInfo {
+                                    nonce: next_nonce - 1,
+                                    ..submission
+                                });
+                            }
+                        },
+                        Err(e) => {
+                            error!("❌ Submit metadata failed: {:?}", e);
+                            hooks.status.record_error(&e);
+                            if let Some(observer) = &hooks.observer {
+                                observer.on_failed(&e);
+                            }
+                            return;
+                        },
+                    }
                 },
                 Err(e) => {
                     error!("❌ Submit metadata failed: {:?}", e);
+                    hooks.status.record_error(&e);
+                    if let Some(observer) = &hooks.observer {
+                        observer.on_failed(e.inner());
+                    }
                     return;
                 },
             }
         } else if let Err(e) = message {
             error!("❗ Error receiving finalized header message: {:?}", e);
+            hooks.status.record_error(&e);
+            // The subscription ends after reporting the lost connection.
+            if e.is::<Disconnected>() {
+                disconnected = Some(anyhow::Error::from(e));
+            }
         }
     }
 
     // If the subscription to finalized blocks is disconnected, send an error through the error channel.
-    if let Err(error) = error_sender.send(anyhow!("Finalized blocks subscription disconnected")).await {
+    let error =
+        disconnected.unwrap_or_else(|| anyhow!("Finalized blocks subscription disconnected"));
+    if let Err(error) = error_sender.send(error).await {
         error!("🚫 Cannot send error to error channel: {error}");
     }
 }
+
+/// Spawns `finalized_headers` and returns its task together with a handle on its status.
+///
+/// The handle can be polled, for example by an operator endpoint, while the looper runs and
+/// after it stops.
+pub fn spawn_finalized_headers(
+    rpc_client: Client,
+    message_tx: Sender<Instant>,
+    das_client: DasClient,
+    service: ValidatorService,
+    error_sender: Sender<anyhow::Error>,
+    config: LooperConfig,
+    hooks: LooperHooks,
+) -> (JoinHandle<()>, LooperStatusHandle) {
+    let status = hooks.status.clone();
+    let handle = tokio::spawn(finalized_headers(
+        rpc_client,
+        message_tx,
+        das_client,
+        service,
+        error_sender,
+        config,
+        hooks,
+    ));
+    (handle, status)
+}
+
+// What a submission handed over to a task needs to complete.
+struct SubmissionContext {
+    rpc_client: Arc<Client>,
+    hooks: LooperHooks,
+    // The submissions the reconciler checks once finalized, if any.
+    pending: Option<Arc<Mutex<PendingSubmissions>>>,
+    resync_nonce: Arc<AtomicBool>,
+    finality: Finality,
+}
+
+// Waits until a submission accepted by the transaction pool reaches `finality`, and reports it.
+//
+// The permit is held until the submission completes. A failure sets `resync_nonce`.
+async fn complete_submission(
+    context: SubmissionContext,
+    progress: TxProgress<redoxt::RedotConfig, OnlineClient<redoxt::RedotConfig>>,
+    submission: SubmissionInfo,
+    _permit: OwnedSemaphorePermit,
+) {
+    let SubmissionContext { rpc_client, hooks, pending, resync_nonce, finality } = context;
+    let LooperHooks { status, observer, .. } = hooks;
+    let receipt = match rpc_client.wait_for_finality(progress, finality).await {
+        Ok(outcome) => rpc_client.receipt(outcome).await,
+        Err(e) => Err(e),
+    };
+    match receipt {
+        Ok(receipt) => {
+            info!("✅ Submit metadata #{} success", submission.nonce);
+            status.record_submission(submission.nonce, submission.nonce + 1);
+            status.record_receipt(receipt);
+            if let Some(pending) = &pending {
+                track_submission(
+                    pending,
+                    &rpc_client,
+                    submission.block_number,
+                    submission.block_hash.clone(),
+                    submission.is_available,
+                    submission.id,
+                    submission.nonce,
+                )
+                .await;
+            }
+            if let Some(observer) = &observer {
+                observer.on_submitted(submission);
+            }
+        },
+        Err(e) => {
+            error!("❌ Submit metadata #{} failed: {:?}", submission.nonce, e);
+            status.record_error(&e);
+            resync_nonce.store(true, Ordering::Relaxed);
+            // The extrinsic left the pool, so the transaction nonce it reserved may be unused.
+            if e.is::<SubmissionFailed>() {
+                if let Err(e) = rpc_client.refresh_nonce().await {
+                    warn!("⚠️ Failed to refresh the transaction nonce: {:?}", e);
+                }
+            }
+            if let Some(observer) = &observer {
+                observer.on_failed(e.inner());
+            }
+        },
+    }
+}
+
+// Signs `msg` with the validator network, reusing the signature of an identical message signed
+// within the cache window instead of running another signing round.
+async fn sign_cached(
+    service: &ValidatorService,
+    sign_cache: &mut SignatureCache<DkgSignature>,
+    hooks: &LooperHooks,
+    msg: &[u8],
+) -> anyhow::Result<DkgSignature> {
+    if let Some(signature) = sign_cache.get(msg, hooks.status.clock().now()) {
+        if let Some(metrics) = &hooks.metrics {
+            metrics.sign_cache_hits.inc();
+        }
+        return Ok(signature);
+    }
+
+    let signature = service.start_signing(msg).await?.into_ed25519()?;
+    sign_cache.insert(msg.to_vec(), signature, hooks.status.clock().now());
+    Ok(signature)
+}
+
+// Recovers from a transient failure to submit metadata, once the connection was replaced if it
+// was lost.
+//
+// Re-reads the task nonce from the chain. If the metadata was included despite the error, the
+// submission is not repeated. Otherwise the metadata is signed again for the on-chain nonce and
+// submitted once more.
+//
+// Returns the nonce to use for the next submission.
+async fn resubmit_metadata(
+    rpc_client: &Client,
+    service: &ValidatorService,
+    sign_cache: &mut SignatureCache<DkgSignature>,
+    hooks: &LooperHooks,
+    submission: &SubmissionInfo,
+    config: &LooperConfig,
+) -> anyhow::Result<u32> {
+    let id = submission.id;
+    let chain_nonce = rpc_client
+        .next_metadata_nonce(id)
+        .await
+        .context("Failed to read the on-chain nonce")?;
+    if chain_nonce > submission.nonce {
+        return Ok(chain_nonce);
+    }
+
+    let msg = build_metadata_message(
+        submission.block_number,
+        submission.block_hash.clone(),
+        submission.is_available,
+        id,
+        chain_nonce,
+    );
+    let signed_msg = signing_message(&msg, id, chain_nonce, config.domain_separated);
+    let signature = sign_cached(service, sign_cache, hooks, &signed_msg).await?;
+    rpc_client.submit_metadata(&msg, id, chain_nonce, &signature, config.finality).await?;
+    Ok(chain_nonce + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latest_ready_skips_queued_items_only() {
+        let mut stream = futures::stream::iter(vec![2, 3]).chain(futures::stream::pending());
+        assert_eq!(latest_ready(&mut stream, 1), (3, 2));
+        assert_eq!(latest_ready(&mut stream, 4), (4, 0));
+    }
+
+    #[tokio::test]
+    async fn reorged_submissions_get_the_current_availability() {
+        let mut module = jsonrpsee::RpcModule::new(());
+        module
+            .register_method("das_isAvailable", |params, _| {
+                let (hash,): (String,) = params.parse()?;
+                // DAS settled the first block since it was submitted, and knows nothing of the
+                // second one.
+                Ok((hash == "0a").then_some(false))
+            })
+            .unwrap();
+        let server =
+            jsonrpsee::server::ServerBuilder::default().build("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", server.local_addr().unwrap());
+        let _handle = server.start(module).unwrap();
+
+        let submission = |block_hash: &[u8]| PendingSubmission {
+            block_number: 1,
+            block_hash: block_hash.to_vec(),
+            is_available: true,
+            id: 1,
+            nonce: 0,
+            included_by: 10,
+        };
+        let submissions = vec![submission(&[0x0a]), submission(&[0x0b])];
+        let refreshed = refresh_availability(&DasClient::new(url), submissions).await;
+        let availability: Vec<_> = refreshed.iter().map(|s| s.is_available).collect();
+        assert_eq!(availability, vec![false, true]);
+    }
+
+    #[test]
+    fn metadata_message_layout() {
+        let msg = build_metadata_message(0x0102_0304, vec![0xaa, 0xbb], true, 1, 7);
+
+        let expected = vec![
+            // block_number, little endian
+            0x04, 0x03, 0x02, 0x01,
+            // block_hash, compact length prefix followed by the bytes
+            0x08, 0xaa, 0xbb,
+            // is_available
+            0x01,
+            // id
+            0x01, 0x00, 0x00, 0x00,
+            // nonce
+            0x07, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(msg, expected);
+    }
+
+    #[test]
+    fn signed_message_layout() {
+        let msg = build_metadata_message(0x0102_0304, vec![0xaa, 0xbb], true, 1, 7);
+
+        // The pallet verifies the encoding of the stored `WeakBoundedVec(msg.encode())` followed
+        // by the task id and nonce.
+        let mut expected = vec![
+            // compact length of `msg.encode()`, 17 bytes
+            0x44,
+            // compact length of `msg`, 16 bytes
+            0x40,
+        ];
+        expected.extend_from_slice(&msg);
+        expected.extend_from_slice(&[
+            // id
+            0x01, 0x00, 0x00, 0x00,
+            // nonce
+            0x07, 0x00, 0x00, 0x00,
+        ]);
+        assert_eq!(signing_message(&msg, 1, 7, false), expected);
+    }
+
+    #[test]
+    fn signature_cache_reuses_within_window() {
+        let now = Instant::now();
+        let mut cache = SignatureCache::new(Duration::from_secs(10));
+        cache.insert(b"msg".to_vec(), 1u8, now);
+
+        assert_eq!(cache.get(b"msg", now + Duration::from_secs(5)), Some(1));
+        assert_eq!(cache.get(b"other", now + Duration::from_secs(5)), None);
+        assert_eq!(cache.get(b"msg", now + Duration::from_secs(10)), None);
+    }
+
+    #[test]
+    fn signature_cache_disabled_with_zero_window() {
+        let now = Instant::now();
+        let mut cache = SignatureCache::new(Duration::ZERO);
+        cache.insert(b"msg".to_vec(), 1u8, now);
+
+        assert_eq!(cache.get(b"msg", now), None);
+    }
+
+    #[test]
+    fn status_records_submissions_and_errors() {
+        let status = LooperStatusHandle::new();
+        status.record_submission(4, 5);
+        status.record_error(&anyhow!("boom"));
+
+        let snapshot = status.get();
+        assert_eq!(snapshot.last_submitted_nonce, Some(4));
+        assert_eq!(snapshot.nonce, 5);
+        assert_eq!(snapshot.last_error.as_deref(), Some("boom"));
+        assert!(snapshot.last_error_at.is_some());
+        assert!(snapshot.last_header.is_none());
+        assert!(snapshot.updated_at >= snapshot.started_at);
+    }
+
+    #[test]
+    fn status_records_the_last_receipt() {
+        let status = LooperStatusHandle::new();
+        assert!(status.get().last_receipt.is_none());
+
+        let receipt = SubmitReceipt {
+            tx_hash: redoxt::H256::repeat_byte(1),
+            block_hash: redoxt::H256::repeat_byte(2),
+            block_number: 12,
+        };
+        status.record_receipt(receipt);
+        assert_eq!(status.get().last_receipt, Some(receipt));
+    }
+
+    #[test]
+    fn status_timestamps_follow_the_clock() {
+        let clock = rc_validator::ManualClock::new(1_000);
+        let status = LooperStatusHandle::with_clock(Arc::new(clock.clone()));
+        clock.advance(Duration::from_secs(30));
+        status.record_header(7);
+
+        let snapshot = status.get();
+        assert_eq!(snapshot.started_at, UNIX_EPOCH + Duration::from_secs(1_000));
+        assert_eq!(snapshot.last_header_at, Some(UNIX_EPOCH + Duration::from_secs(1_030)));
+        assert_eq!(snapshot.updated_at, UNIX_EPOCH + Duration::from_secs(1_030));
+    }
+
+    #[test]
+    fn pending_submissions_settle_once_finalized() {
+        let submission = |nonce, included_by| PendingSubmission {
+            block_number: 1,
+            block_hash: vec![0xaa],
+            is_available: true,
+            id: 1,
+            nonce,
+            included_by,
+        };
+        let mut pending = PendingSubmissions::default();
+        pending.push(submission(0, 10));
+        pending.push(submission(1, 12));
+
+        assert_eq!(pending.take_settled(9), vec![]);
+        assert_eq!(pending.take_settled(11), vec![submission(0, 10)]);
+        assert_eq!(pending.take_settled(12), vec![submission(1, 12)]);
+        assert_eq!(
+            submission(0, 10).stored_metadata(),
+            build_metadata_message(1, vec![0xaa], true, 1, 0).encode()
+        );
+    }
+
+    #[test]
+    fn block_nonces_detect_duplicates_and_collisions() {
+        let mut nonces = BlockNonces::new(2);
+
+        assert_eq!(nonces.claim(5, b"a"), BlockNonce::Fresh(5));
+        assert_eq!(nonces.claim(5, b"a"), BlockNonce::Duplicate);
+        assert_eq!(nonces.claim(5, b"b"), BlockNonce::Collision);
+
+        // The oldest block is forgotten once the capacity is exceeded.
+        assert_eq!(nonces.claim(6, b"c"), BlockNonce::Fresh(6));
+        assert_eq!(nonces.claim(7, b"d"), BlockNonce::Fresh(7));
+        assert_eq!(nonces.claim(5, b"b"), BlockNonce::Fresh(5));
+    }
+
+    #[test]
+    fn released_blocks_are_claimed_again() {
+        let mut nonces = BlockNonces::new(2);
+
+        assert_eq!(nonces.claim(5, b"a"), BlockNonce::Fresh(5));
+        nonces.release(5);
+        assert_eq!(nonces.claim(5, b"a"), BlockNonce::Fresh(5));
+        assert_eq!(nonces.claim(5, b"a"), BlockNonce::Duplicate);
+    }
+
+    #[test]
+    fn metadata_message_depends_on_id_and_nonce_order() {
+        let a = build_metadata_message(1, vec![0u8; 32], false, 1, 2);
+        let b = build_metadata_message(1, vec![0u8; 32], false, 2, 1);
+
+        assert_ne!(a, b);
+        assert_eq!(a.len(), 4 + 1 + 32 + 1 + 4 + 4);
+        assert_eq!(&a[a.len() - 8..], &[1, 0, 0, 0, 2, 0, 0, 0]);
+    }
+
+    #[test]
+    fn signing_message_prepends_domain_tag() {
+        let msg = build_metadata_message(1, vec![0u8; 32], true, 1, 2);
+        let verified = signing_message(&msg, 1, 2, false);
+
+        let signed = signing_message(&msg, 1, 2, true);
+        assert_eq!(&signed[..redoxt::METADATA_DOMAIN_TAG.len()], redoxt::METADATA_DOMAIN_TAG);
+        assert_eq!(&signed[redoxt::METADATA_DOMAIN_TAG.len()..], &verified[..]);
+    }
+}
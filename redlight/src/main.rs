@@ -16,74 +16,163 @@ use cli::parse_args;
 use codec::Decode;
 use cumulus_primitives_core::relay_chain::ValidatorId;
 use log::{error, info};
-use redoxt::ClientBuilder;
+use redoxt::{AccountId, ClientBuilder};
 use std::sync::Arc;
-use subxt_signer::sr25519::{
-	dev::{self},
-	Keypair,
-};
-use tokio::sync::mpsc;
+use tokio::sync::{broadcast, mpsc};
 
 mod cli;
-mod das_rpc;
+mod data_dir;
 mod logger;
 mod looper;
+mod metrics;
+mod node_config;
+mod rotation;
 
-pub(crate) use das_rpc::DasClient;
+use redlight::{das_rpc, headers, DasClient};
 
-pub async fn run(config: &cli::Config) -> anyhow::Result<()> {
+pub async fn run_node(config: &cli::Config) -> anyhow::Result<()> {
 	logger::init_logger().unwrap();
 
 	info!("🚀 Redot Light Client starting up");
 
+	data_dir::init_data_dir(&config.data_dir)?;
+	let data_dir = data_dir::DataDir::new(&config.data_dir);
+	let node_key = rc_validator_network::load_or_generate_keypair(&data_dir.node_key())?;
+
 	let (network_service, network_worker) =
-		rc_validator_network::default(Some(config.network_config.clone()), None)?;
+		rc_validator_network::default(Some(config.network_config.clone()), Some(node_key))?;
 
-	let keypair = Keypair::from(dev::alice());
+	let keypair = config.signer.keypair()?;
 	let public_key = keypair.public_key();
 	let validator_id = ValidatorId::decode(&mut public_key.as_ref()).unwrap();
 
-	let (service, mut client) =
-		rc_validator::new_validator_network_service(validator_id, Arc::new(network_service))?;
+	let worker_config = rc_validator::WorkerConfig {
+		audit_log: config.audit_log.then(|| data_dir.audit_log()),
+		..Default::default()
+	};
+	let (service, mut client) = rc_validator::new_validator_network_service_with_config(
+		validator_id,
+		Arc::new(network_service),
+		worker_config,
+	)?;
 
 	let rpc_url = config.rpc_url.clone();
 
 	// let database = Arc::new(Mutex::new(SqliteDasDb::default()));
 
-	let rpc_client = match ClientBuilder::default().set_url(&rpc_url).build().await {
+	let builder = ClientBuilder::new(&rpc_url, keypair).with_idempotent_metadata(true);
+	let rpc_client = match builder.build().await {
 		Ok(client) => client,
 		Err(e) => {
 			error!("❌ Failed to build RPC client: {:?}", e);
-			return Err(e);
+			return Err(e.into());
 		},
 	};
 
-	let das_client = DasClient::new("http://127.0.0.1:4177".to_string());
+	if let Some(expected_account) = &config.expected_account {
+		let expected = expected_account
+			.parse::<AccountId>()
+			.map_err(|e| anyhow::anyhow!("Invalid expected account: {:?}", e))?;
+		if let Err(e) = rpc_client.expect_account(&expected) {
+			error!("❌ {:?}", e);
+			return Err(e.into());
+		}
+	}
+
+	let das_client = das_rpc::DasClientBuilder {
+		urls: config.das_rpc_urls.clone(),
+		policy: config.das_selection_policy,
+		config: config.das_client_config.clone(),
+		bearer_token: config.das_bearer_token.clone(),
+	}
+	.build()?;
+	if let Some(timeout) = config.das_startup_timeout {
+		info!("Waiting for the DAS node to be healthy");
+		if let Err(e) = das_client.wait_until_healthy(timeout).await {
+			error!("❌ {:?}", e);
+			return Err(e);
+		}
+	}
 
 	tokio::spawn(network_worker.run());
 	tokio::spawn(async move {
 		let _ = client.run().await;
 	});
+
+	// The task pallet only verifies keys and signatures of the Ed25519 ciphersuite.
+	if let Some(nt) = config.threshold {
+		if let Err(e) = service.setup(nt, rc_validator::Ciphersuite::Ed25519).await {
+			error!("❌ Failed to set up the validator network: {:?}", e);
+			return Err(e);
+		}
+	}
+
 	let (message_tx, _message_rx) = mpsc::channel(100);
 	let (error_tx, mut error_rx) = mpsc::channel(10);
 
-	tokio::spawn(looper::finalized_headers(rpc_client, message_tx, das_client, service, error_tx));
+	let registry = prometheus_endpoint::Registry::default();
+	let metrics = match metrics::LooperMetrics::register(&registry) {
+		Ok(metrics) => Some(metrics),
+		Err(e) => {
+			error!("❌ Failed to register metrics: {:?}", e);
+			None
+		},
+	};
+	match metrics::RoundMetrics::register(&registry) {
+		Ok(round_metrics) => {
+			let mut round_durations = service.subscribe_round_durations();
+			tokio::spawn(async move {
+				loop {
+					match round_durations.recv().await {
+						Ok(duration) => round_metrics.observe(&duration),
+						Err(broadcast::error::RecvError::Lagged(_)) => continue,
+						Err(broadcast::error::RecvError::Closed) => break,
+					}
+				}
+			});
+		},
+		Err(e) => error!("❌ Failed to register round metrics: {:?}", e),
+	}
+	if let Some(prometheus_addr) = config.prometheus_addr {
+		tokio::spawn(async move {
+			if let Err(e) = prometheus_endpoint::init_prometheus(prometheus_addr, registry).await {
+				error!("❌ Prometheus endpoint failed: {:?}", e);
+			}
+		});
+	}
+
+	let (_looper, looper_status) = looper::spawn_finalized_headers(
+		rpc_client,
+		message_tx,
+		das_client,
+		service,
+		error_tx,
+		config.looper_config.clone(),
+		looper::LooperHooks { metrics, ..Default::default() },
+	);
 
 	while let Some(error) = error_rx.recv().await {
 		error!("⚠️ Error in finalized headers stream: {:?}", error);
+		error!("Last looper status: {:?}", looper_status.get());
 	}
 
 	Ok(())
 }
 
 pub fn main() {
-	let config = parse_args();
+	let config = match parse_args() {
+		Ok(config) => config,
+		Err(e) => {
+			eprintln!("Invalid configuration: {:?}", e);
+			std::process::exit(1);
+		},
+	};
 
 	tokio::runtime::Builder::new_multi_thread()
 		.worker_threads(4)
 		.enable_all()
 		.build()
 		.expect("Failed to build runtime")
-		.block_on(run(&config))
+		.block_on(run_node(&config))
 		.unwrap_or_else(|e| error!("Fatal error: {}", e));
 }
@@ -0,0 +1,84 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use prometheus_endpoint::{
+	register, Counter, Gauge, HistogramOpts, HistogramVec, PrometheusError, Registry, U64,
+};
+use rc_validator::RoundDuration;
+
+/// Metrics reported by the looper.
+#[derive(Clone)]
+pub struct LooperMetrics {
+	/// Number of blocks the DAS node lags behind the chain tip.
+	pub das_lag: Gauge<U64>,
+	/// Number of signing requests served from the signature cache.
+	pub sign_cache_hits: Counter<U64>,
+}
+
+impl LooperMetrics {
+	/// Registers the looper metrics in `registry`.
+	pub fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			das_lag: register(
+				Gauge::new(
+					"redlight_das_lag_blocks",
+					"Number of blocks the latest DAS block lags behind the chain tip",
+				)?,
+				registry,
+			)?,
+			sign_cache_hits: register(
+				Counter::new(
+					"redlight_sign_cache_hits_total",
+					"Number of signing requests answered with a recently produced signature",
+				)?,
+				registry,
+			)?,
+		})
+	}
+}
+
+/// Metrics reported for the DKG and signing rounds of the validator network.
+#[derive(Clone)]
+pub struct RoundMetrics {
+	/// Duration of the rounds, labelled with their kind and outcome.
+	pub round_duration: HistogramVec,
+}
+
+impl RoundMetrics {
+	/// Registers the round metrics in `registry`.
+	pub fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			round_duration: register(
+				HistogramVec::new(
+					HistogramOpts::new(
+						"redlight_round_duration_seconds",
+						"Duration of DKG and signing rounds, whatever their outcome",
+					)
+					.buckets(vec![0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0]),
+					&["round", "outcome"],
+				)?,
+				registry,
+			)?,
+		})
+	}
+
+	/// Records the duration of a round.
+	pub fn observe(&self, duration: &RoundDuration) {
+		let round = format!("{:?}", duration.kind).to_lowercase();
+		let outcome = format!("{:?}", duration.outcome).to_lowercase();
+		self.round_duration
+			.with_label_values(&[&round, &outcome])
+			.observe(duration.elapsed.as_secs_f64());
+	}
+}
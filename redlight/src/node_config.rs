@@ -0,0 +1,207 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{anyhow, ensure, Context, Result};
+use rc_validator_network::ValidatorNetworkConfig;
+use serde::Deserialize;
+use std::{
+	net::IpAddr,
+	path::{Path, PathBuf},
+};
+use subxt_signer::sr25519::Keypair;
+
+/// Configuration of a whole node, loaded from a TOML or JSON file.
+///
+/// Settings that are not in the file, such as the looper tuning, still come from the command
+/// line, and explicit command line flags take precedence over the file.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct NodeConfig {
+	/// WebSocket URL of the chain RPC node.
+	pub rpc_url: String,
+	/// DAS RPC URLs, in failover order.
+	pub das_rpc_urls: Vec<String>,
+	/// Source of the key used to sign transactions and identify the validator.
+	#[serde(default)]
+	pub signer: SignerSource,
+	/// Validator network settings.
+	#[serde(default)]
+	pub network: NetworkSettings,
+	/// Signing threshold to set up at startup, if any.
+	pub threshold: Option<ThresholdConfig>,
+	/// Directory holding all the persisted state of the node, if not the default one.
+	pub data_dir: Option<PathBuf>,
+}
+
+/// Source of the signer key.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SignerSource {
+	/// A secret URI, such as `//Alice` or a mnemonic phrase followed by derivation junctions.
+	SecretUri(String),
+	/// The name of an environment variable holding a secret URI, to keep secrets out of the file.
+	Env(String),
+}
+
+impl SignerSource {
+	/// Builds the signer keypair.
+	pub fn keypair(&self) -> Result<Keypair> {
+		let uri = match self {
+			SignerSource::SecretUri(uri) => uri.clone(),
+			SignerSource::Env(var) => std::env::var(var)
+				.with_context(|| format!("Signer environment variable {} is not set", var))?,
+		};
+		redoxt::keypair_from_secret_uri(&uri)
+	}
+}
+
+impl Default for SignerSource {
+	fn default() -> Self {
+		SignerSource::SecretUri("//Alice".to_string())
+	}
+}
+
+/// Listen and bootstrap settings of the validator network.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkSettings {
+	/// The IP address to listen on.
+	pub listen_addr: String,
+	/// The port to listen on.
+	pub listen_port: u16,
+	/// Multiaddrs of the bootstrap nodes.
+	pub bootstrap_nodes: Vec<String>,
+}
+
+impl NetworkSettings {
+	/// Applies the settings to a validator network configuration.
+	pub fn apply(&self, config: &mut ValidatorNetworkConfig) {
+		config.listen_addr = self.listen_addr.clone();
+		config.listen_port = self.listen_port;
+		config.bootstrap_nodes = self.bootstrap_nodes.clone();
+	}
+}
+
+impl Default for NetworkSettings {
+	fn default() -> Self {
+		let config = ValidatorNetworkConfig::default();
+		NetworkSettings {
+			listen_addr: config.listen_addr,
+			listen_port: config.listen_port,
+			bootstrap_nodes: config.bootstrap_nodes,
+		}
+	}
+}
+
+/// Number of participants and signing threshold of the validator network.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ThresholdConfig {
+	/// Total number of participants.
+	pub n: u16,
+	/// Number of participants required to sign.
+	pub t: u16,
+}
+
+impl NodeConfig {
+	/// Loads and validates a configuration file.
+	///
+	/// The format is picked from the extension, which must be `.toml` or `.json`.
+	pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+		let path = path.as_ref();
+		let content = std::fs::read_to_string(path)
+			.with_context(|| format!("Failed to read config file {}", path.display()))?;
+		let config: NodeConfig = match path.extension().and_then(|ext| ext.to_str()) {
+			Some("toml") => toml::from_str(&content)
+				.with_context(|| format!("Invalid config file {}", path.display()))?,
+			Some("json") => serde_json::from_str(&content)
+				.with_context(|| format!("Invalid config file {}", path.display()))?,
+			_ => return Err(anyhow!("Config file {} must be .toml or .json", path.display())),
+		};
+		config.validate()?;
+		Ok(config)
+	}
+
+	/// Checks that the configuration can be used to run a node.
+	pub fn validate(&self) -> Result<()> {
+		ensure!(
+			self.rpc_url.starts_with("ws://") || self.rpc_url.starts_with("wss://"),
+			"rpc_url must be a ws:// or wss:// URL, got {}",
+			self.rpc_url
+		);
+		ensure!(!self.das_rpc_urls.is_empty(), "das_rpc_urls must not be empty");
+		for url in self.das_rpc_urls.iter() {
+			ensure!(
+				url.starts_with("http://") || url.starts_with("https://"),
+				"DAS RPC URL must be an http:// or https:// URL, got {}",
+				url
+			);
+		}
+		self.network
+			.listen_addr
+			.parse::<IpAddr>()
+			.with_context(|| format!("Invalid listen_addr {}", self.network.listen_addr))?;
+		if let Some(threshold) = self.threshold {
+			ensure!(
+				threshold.t >= 1 && threshold.t <= threshold.n,
+				"threshold t must be between 1 and n, got t = {}, n = {}",
+				threshold.t,
+				threshold.n
+			);
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_toml_config() {
+		let config: NodeConfig = toml::from_str(
+			r#"
+			rpc_url = "ws://127.0.0.1:9944"
+			das_rpc_urls = ["http://127.0.0.1:4177"]
+			signer = { env = "REDLIGHT_SIGNER" }
+			threshold = { n = 3, t = 2 }
+
+			[network]
+			listen_port = 4418
+			"#,
+		)
+		.unwrap();
+
+		assert_eq!(config.signer, SignerSource::Env("REDLIGHT_SIGNER".to_string()));
+		assert_eq!(config.threshold, Some(ThresholdConfig { n: 3, t: 2 }));
+		assert_eq!(config.network.listen_port, 4418);
+		assert_eq!(config.network.listen_addr, NetworkSettings::default().listen_addr);
+		assert_eq!(config.data_dir, None);
+		assert!(config.validate().is_ok());
+	}
+
+	#[test]
+	fn rejects_threshold_above_participants() {
+		let config = NodeConfig {
+			rpc_url: "ws://127.0.0.1:9944".to_string(),
+			das_rpc_urls: vec!["http://127.0.0.1:4177".to_string()],
+			signer: SignerSource::default(),
+			network: NetworkSettings::default(),
+			threshold: Some(ThresholdConfig { n: 2, t: 3 }),
+			data_dir: None,
+		};
+
+		assert!(config.validate().is_err());
+	}
+}
@@ -0,0 +1,71 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn};
+use rc_validator::{DkgVerifyingKey, Service as ValidatorService};
+use redoxt::{verifying_key_to_chain_bytes, Client, ClientSync};
+
+// Number of attempts made to register a new key on chain.
+const REGISTER_ATTEMPTS: u32 = 3;
+
+/// Runs a key rotation and registers the new key on chain.
+///
+/// The rotation is only considered complete once the registration extrinsic has been included
+/// successfully. A failed registration is retried, and before each retry the chain is checked in
+/// case the previous attempt was included despite the error.
+///
+/// # Failure atomicity
+///
+/// On success, the validator network and the chain hold the same key. On error, the validator
+/// network may hold a key the chain does not know: nothing must be signed with it, and the
+/// caller should call this function again, which runs a new DKG and replaces that key. The key
+/// registered on chain is never left half updated.
+pub async fn rotate_and_register(
+    service: &ValidatorService,
+    client: &Client,
+) -> Result<DkgVerifyingKey> {
+    let key = service.rotate_key().await.context("Failed to rotate the key")?;
+    let key = key.into_ed25519().context("The new key cannot be registered on chain")?;
+    let key_bytes = verifying_key_to_chain_bytes(&key);
+
+    let mut last_error = None;
+    for attempt in 1..=REGISTER_ATTEMPTS {
+        if attempt > 1 {
+            match client.verifying_key().await {
+                Ok(Some(registered)) if registered == key_bytes => {
+                    info!("🔑 New key found on chain after a failed registration");
+                    return Ok(key);
+                },
+                Ok(_) => {},
+                Err(e) => warn!("⚠️ Failed to read the registered key: {:?}", e),
+            }
+        }
+
+        match client.register_key(&key).await {
+            Ok(()) => {
+                info!("🔑 New key registered on chain");
+                return Ok(key);
+            },
+            Err(e) => {
+                warn!("⚠️ Key registration attempt {} failed: {:?}", attempt, e);
+                last_error = Some(anyhow::Error::from(e));
+            },
+        }
+    }
+
+    Err(last_error
+        .unwrap_or_else(|| anyhow!("Key registration failed"))
+        .context("The new key is not registered on chain and must not be used"))
+}
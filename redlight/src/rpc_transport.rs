@@ -0,0 +1,453 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! JSON-RPC over HTTP, with endpoint selection and retries.
+//!
+//! `RpcTransport` sends requests to one of several endpoints and retries with an exponential
+//! backoff when every endpoint is unreachable. It is shared by the JSON-RPC clients of the crate,
+//! such as `DasClient`. Requests are asynchronous, so they do not block the runtime threads.
+
+use anyhow::{anyhow, Result};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use std::{
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+/// Strategy used to pick the endpoint that serves a request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelectionPolicy {
+    /// Each request starts from the endpoint following the one used by the previous request.
+    RoundRobin,
+    /// Requests stick to the current endpoint and only move to the next one when it fails.
+    Failover,
+}
+
+/// How failed requests are retried.
+///
+/// A request is retried when no endpoint could be reached, or when they all answered with a
+/// server error (5xx) or `429 Too Many Requests`. The delay before the `n`th retry is
+/// `backoff * 2^(n - 1)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt.
+    pub retries: u32,
+    /// Delay before the first retry.
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    fn delay(&self, retry: u32) -> Duration {
+        self.backoff.saturating_mul(2u32.saturating_pow(retry))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy { retries: 2, backoff: Duration::from_millis(250) }
+    }
+}
+
+/// Error returned when a request timed out on every endpoint.
+///
+/// It is distinguishable from other failures with `anyhow::Error::is`, so callers can tell a
+/// stalled server from a refused or failed request.
+#[derive(Debug)]
+pub struct RequestTimeout {
+    /// The method of the request.
+    pub method: String,
+    /// The endpoints that timed out.
+    pub urls: Vec<String>,
+}
+
+impl fmt::Display for RequestTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} timed out on every RPC endpoint: {}", self.method, self.urls.join(", "))
+    }
+}
+
+impl std::error::Error for RequestTimeout {}
+
+// Every endpoint failed during one pass over them.
+struct PassFailure {
+    error: anyhow::Error,
+    retryable: bool,
+}
+
+// A request to one endpoint failed.
+#[derive(Debug)]
+enum EndpointError {
+    // The endpoint answered with an error status.
+    Status(StatusCode),
+    // The endpoint could not be reached, or did not answer in time.
+    Transport(reqwest::Error),
+}
+
+impl fmt::Display for EndpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EndpointError::Status(status) => write!(f, "status code {}", status),
+            EndpointError::Transport(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for EndpointError {}
+
+/// Sends JSON-RPC requests over HTTP to a set of endpoints.
+pub struct RpcTransport {
+    client: reqwest::Client,
+    urls: Vec<String>,
+    policy: SelectionPolicy,
+    retry: RetryPolicy,
+    // Index of the endpoint the next request starts from.
+    cursor: AtomicUsize,
+}
+
+impl RpcTransport {
+    /// Creates a transport sending requests to `urls` through `client`.
+    ///
+    /// Request timeouts and connection pooling are settings of the `client`.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - The HTTP client requests are sent with.
+    /// * `urls` - The URLs of the JSON-RPC servers. Must not be empty.
+    /// * `policy` - The endpoint selection policy.
+    /// * `retry` - How requests are retried when every endpoint fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `urls` is empty.
+    pub fn new(
+        client: reqwest::Client,
+        urls: Vec<String>,
+        policy: SelectionPolicy,
+        retry: RetryPolicy,
+    ) -> Result<Self> {
+        if urls.is_empty() {
+            return Err(anyhow!("RpcTransport requires at least one URL"));
+        }
+        Ok(RpcTransport { client, urls, policy, retry, cursor: AtomicUsize::new(0) })
+    }
+
+    /// The URLs of the JSON-RPC servers.
+    pub fn urls(&self) -> &[String] {
+        &self.urls
+    }
+
+    /// Sends a JSON-RPC request and returns the decoded response body.
+    ///
+    /// Endpoints are tried in turn, starting from the one selected by the policy, until one of
+    /// them answers. If none does, the whole pass is retried according to the retry policy.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if every attempt failed, if an endpoint answered with a client error
+    /// status, or if the response body is not JSON. JSON-RPC errors are part of the returned
+    /// body and are left to the caller.
+    pub async fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let request = json!({
+            "method": method,
+            "params": params,
+            "id": 1,
+            "jsonrpc": "2.0"
+        });
+        self.send(method, &request).await
+    }
+
+    /// Sends the `calls` as one JSON-RPC batch and returns the decoded response body.
+    ///
+    /// The request of each call has its index in `calls` as id. Endpoints are selected and
+    /// retried as with `call`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if every attempt failed, as with `call`. The responses to the calls,
+    /// including JSON-RPC errors, are part of the returned body and are left to the caller.
+    pub async fn call_batch(&self, calls: &[(&str, Value)]) -> Result<Value> {
+        let requests = calls
+            .iter()
+            .enumerate()
+            .map(|(id, (method, params))| {
+                json!({
+                    "method": method,
+                    "params": params,
+                    "id": id,
+                    "jsonrpc": "2.0"
+                })
+            })
+            .collect::<Vec<_>>();
+        let method = calls.first().map_or("batch", |(method, _)| method);
+        self.send(method, &Value::Array(requests)).await
+    }
+
+    // Sends `request`, retrying according to the retry policy.
+    async fn send(&self, method: &str, request: &Value) -> Result<Value> {
+        let mut retry = 0;
+        loop {
+            match self.call_once(method, request).await {
+                Ok(value) => return Ok(value),
+                Err(failure) if failure.retryable && retry < self.retry.retries => {
+                    let delay = self.retry.delay(retry);
+                    log::debug!("{} failed, retrying in {:?}: {}", method, delay, failure.error);
+                    tokio::time::sleep(delay).await;
+                    retry += 1;
+                },
+                Err(failure) => return Err(failure.error),
+            }
+        }
+    }
+
+    /// Sends a JSON-RPC request to every endpoint, without retries.
+    ///
+    /// # Returns
+    ///
+    /// The URL of each endpoint with its decoded response body, or the error it failed with.
+    pub async fn call_each(&self, method: &str, params: Value) -> Vec<(&str, Result<Value>)> {
+        let request = json!({
+            "method": method,
+            "params": params,
+            "id": 1,
+            "jsonrpc": "2.0"
+        });
+        let mut responses = Vec::with_capacity(self.urls.len());
+        for url in &self.urls {
+            let response = match self.post(url, &request).await {
+                Ok(resp) => resp.json().await.map_err(anyhow::Error::from),
+                Err(e) => Err(e.into()),
+            };
+            responses.push((url.as_str(), response));
+        }
+        responses
+    }
+
+    // Sends `request` to the endpoint at `url`, failing on an error status.
+    async fn post(
+        &self,
+        url: &str,
+        request: &Value,
+    ) -> std::result::Result<reqwest::Response, EndpointError> {
+        let resp =
+            self.client.post(url).json(request).send().await.map_err(EndpointError::Transport)?;
+        let status = resp.status();
+        if status.is_client_error() || status.is_server_error() {
+            return Err(EndpointError::Status(status));
+        }
+        Ok(resp)
+    }
+
+    // Tries each endpoint once.
+    async fn call_once(
+        &self,
+        method: &str,
+        request: &Value,
+    ) -> std::result::Result<Value, PassFailure> {
+        let len = self.urls.len();
+        let start = match self.policy {
+            SelectionPolicy::RoundRobin => self.cursor.fetch_add(1, Ordering::Relaxed) % len,
+            SelectionPolicy::Failover => self.cursor.load(Ordering::Relaxed) % len,
+        };
+
+        let mut failures = Vec::new();
+        let mut retryable = true;
+        let mut timed_out = true;
+        for offset in 0..len {
+            let index = (start + offset) % len;
+            let url = &self.urls[index];
+            match self.post(url, request).await {
+                Ok(resp) => {
+                    if self.policy == SelectionPolicy::Failover {
+                        self.cursor.store(index, Ordering::Relaxed);
+                    }
+                    return resp
+                        .json()
+                        .await
+                        .map_err(|e| PassFailure { error: e.into(), retryable: false });
+                },
+                Err(e) => {
+                    log::warn!("RPC endpoint {} failed: {}", url, e);
+                    retryable &= is_retryable(&e);
+                    timed_out &= matches!(&e, EndpointError::Transport(e) if e.is_timeout());
+                    failures.push(format!("{}: {}", url, e));
+                },
+            }
+        }
+
+        let error = if timed_out {
+            RequestTimeout { method: method.to_string(), urls: self.urls.clone() }.into()
+        } else {
+            anyhow!("All RPC endpoints failed: {}", failures.join("; "))
+        };
+        Err(PassFailure { error, retryable })
+    }
+}
+
+// Whether the request may succeed if sent again later.
+fn is_retryable(error: &EndpointError) -> bool {
+    match error {
+        EndpointError::Status(status) =>
+            status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS,
+        EndpointError::Transport(_) => true,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::{
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+        sync::{atomic::AtomicUsize, mpsc, Arc},
+    };
+
+    const RESULT: &str = r#"{"jsonrpc":"2.0","id":1,"result":true}"#;
+
+    // Serves one canned `(status, body)` response per connection, in order, and returns the URL
+    // of the server.
+    pub(crate) fn stub_server(responses: Vec<(u16, &'static str)>) -> String {
+        recording_server(responses).0
+    }
+
+    // Serves responses like `stub_server`, and also returns the receiver of the requests it
+    // received, headers included.
+    pub(crate) fn recording_server(
+        responses: Vec<(u16, &'static str)>,
+    ) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (requests_tx, requests_rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for (status, body) in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                if let Some(request) = read_request(&mut stream) {
+                    let _ = requests_tx.send(request);
+                }
+                let response = format!(
+                    "HTTP/1.1 {} Stub\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        (url, requests_rx)
+    }
+
+    // Answers every request with `body` and keeps connections open, and returns the URL of the
+    // server with the number of connections it accepted.
+    pub(crate) fn keep_alive_server(body: &'static str) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let connections = Arc::new(AtomicUsize::new(0));
+        let accepted = connections.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                accepted.fetch_add(1, Ordering::SeqCst);
+                std::thread::spawn(move || {
+                    while read_request(&mut stream).is_some() {
+                        let response = format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                             Content-Length: {}\r\n\r\n{}",
+                            body.len(),
+                            body
+                        );
+                        stream.write_all(response.as_bytes()).unwrap();
+                    }
+                });
+            }
+        });
+        (url, connections)
+    }
+
+    // Accepts connections without ever answering them, and returns the URL of the server.
+    pub(crate) fn silent_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let mut open = Vec::new();
+            for stream in listener.incoming() {
+                open.push(stream.unwrap());
+            }
+        });
+        url
+    }
+
+    // Reads one request from `stream`, or `None` if the connection was closed before one was
+    // received.
+    fn read_request(stream: &mut TcpStream) -> Option<String> {
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+        loop {
+            let n = stream.read(&mut buf).unwrap_or(0);
+            request.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&request).to_ascii_lowercase();
+            let Some(end) = text.find("\r\n\r\n") else {
+                if n == 0 {
+                    return None;
+                }
+                continue;
+            };
+            let length = text
+                .lines()
+                .find_map(|line| line.strip_prefix("content-length:"))
+                .map_or(0, |value| value.trim().parse::<usize>().unwrap());
+            if n == 0 || request.len() >= end + 4 + length {
+                return Some(String::from_utf8_lossy(&request).into_owned());
+            }
+        }
+    }
+
+    fn transport(url: String, retries: u32) -> RpcTransport {
+        RpcTransport::new(
+            reqwest::Client::new(),
+            vec![url],
+            SelectionPolicy::Failover,
+            RetryPolicy { retries, backoff: Duration::from_millis(1) },
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn retries_until_success() {
+        let url = stub_server(vec![(503, ""), (503, ""), (200, RESULT)]);
+        let value = transport(url, 2).call("das_health", json!([])).await.unwrap();
+        assert_eq!(value["result"], json!(true));
+    }
+
+    #[tokio::test]
+    async fn fails_once_retries_are_exhausted() {
+        let url = stub_server(vec![(503, ""), (503, ""), (200, RESULT)]);
+        let error = transport(url, 1).call("das_health", json!([])).await.unwrap_err();
+        assert!(error.to_string().contains("503"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let url = stub_server(vec![(400, ""), (200, RESULT)]);
+        assert!(transport(url, 2).call("das_health", json!([])).await.is_err());
+    }
+
+    #[test]
+    fn empty_url_lists_are_rejected() {
+        let client = reqwest::Client::new();
+        let retry = RetryPolicy::default();
+        assert!(RpcTransport::new(client, vec![], SelectionPolicy::Failover, retry).is_err());
+    }
+}
@@ -0,0 +1,168 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Splitting of metadata too large for a single `new_metadata` call.
+//!
+//! Each chunk is stored by the `task` pallet as its own metadata entry, under consecutive nonces.
+//! To reassemble the metadata, read the entries from the nonce of the first chunk, decode them as
+//! `MetadataChunk`s and pass them to `join_metadata`. The pallet itself does not reassemble them.
+
+use anyhow::{anyhow, Result};
+use codec::{Decode, Encode};
+
+/// Maximum length of the metadata stored by a single `new_metadata` call, the `MaxMetadataLen`
+/// of the runtime.
+pub const MAX_METADATA_LEN: usize = 1024;
+
+/// Maximum number of chunks the metadata can be split into.
+pub const MAX_CHUNKS: usize = u16::MAX as usize;
+
+/// Maximum length of the data of a single chunk.
+///
+/// The encoded chunk holds its index and total as `u16`s and the compact length of its data.
+pub const MAX_CHUNK_DATA_LEN: usize = MAX_METADATA_LEN - 4 - 2;
+
+/// Maximum total length of metadata submitted in chunks, just under 64 MiB.
+pub const MAX_CHUNKED_METADATA_LEN: usize = MAX_CHUNKS * MAX_CHUNK_DATA_LEN;
+
+/// A part of metadata split by `split_metadata`.
+#[derive(Encode, Decode, Clone, Debug, PartialEq, Eq)]
+pub struct MetadataChunk {
+	/// Position of the chunk, starting at 0.
+	pub index: u16,
+	/// Total number of chunks.
+	pub total: u16,
+	/// The bytes of the metadata held by this chunk.
+	pub data: Vec<u8>,
+}
+
+/// Split metadata into chunks that each fit in a single `new_metadata` call.
+///
+/// Returns an error if the metadata is longer than `MAX_CHUNKED_METADATA_LEN`.
+pub fn split_metadata(metadata: &[u8]) -> Result<Vec<MetadataChunk>> {
+	if metadata.len() > MAX_CHUNKED_METADATA_LEN {
+		return Err(anyhow!(
+			"Metadata of {} bytes exceeds the maximum of {} bytes",
+			metadata.len(),
+			MAX_CHUNKED_METADATA_LEN
+		))
+	}
+
+	let parts = if metadata.is_empty() {
+		vec![metadata]
+	} else {
+		metadata.chunks(MAX_CHUNK_DATA_LEN).collect::<Vec<_>>()
+	};
+	let total = parts.len() as u16;
+
+	let chunks = parts
+		.into_iter()
+		.enumerate()
+		.map(|(index, data)| MetadataChunk { index: index as u16, total, data: data.to_vec() })
+		.collect::<Vec<_>>();
+	Ok(chunks)
+}
+
+/// Reassemble metadata from its chunks, given in any order.
+///
+/// Returns an error if a chunk is missing or duplicated, or if the chunks disagree on the total.
+pub fn join_metadata(mut chunks: Vec<MetadataChunk>) -> Result<Vec<u8>> {
+	let total = chunks.first().map(|chunk| chunk.total).ok_or_else(|| anyhow!("No chunks"))?;
+	if chunks.len() != total as usize || chunks.iter().any(|chunk| chunk.total != total) {
+		return Err(anyhow!("Inconsistent number of chunks"))
+	}
+
+	chunks.sort_by_key(|chunk| chunk.index);
+	let mut metadata = Vec::new();
+	for (expected, chunk) in chunks.into_iter().enumerate() {
+		if chunk.index as usize != expected {
+			return Err(anyhow!("Missing chunk {}", expected))
+		}
+		metadata.extend_from_slice(&chunk.data);
+	}
+	Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn metadata(len: usize) -> Vec<u8> {
+		(0..len).map(|i| i as u8).collect()
+	}
+
+	fn round_trip(len: usize) -> Vec<MetadataChunk> {
+		let metadata = metadata(len);
+		let chunks = split_metadata(&metadata).unwrap();
+		assert!(chunks.iter().all(|chunk| chunk.encoded_size() <= MAX_METADATA_LEN));
+		assert_eq!(join_metadata(chunks.clone()).unwrap(), metadata);
+		chunks
+	}
+
+	#[test]
+	fn metadata_filling_one_chunk_round_trips() {
+		let chunks = round_trip(MAX_CHUNK_DATA_LEN);
+		assert_eq!(chunks.len(), 1);
+		assert_eq!(chunks[0].encoded_size(), MAX_METADATA_LEN);
+	}
+
+	#[test]
+	fn metadata_one_byte_over_a_chunk_round_trips() {
+		let chunks = round_trip(MAX_CHUNK_DATA_LEN + 1);
+		assert_eq!(chunks.len(), 2);
+		assert_eq!(chunks[1].data.len(), 1);
+	}
+
+	#[test]
+	fn empty_metadata_round_trips() {
+		let chunks = round_trip(0);
+		assert_eq!(chunks, vec![MetadataChunk { index: 0, total: 1, data: vec![] }]);
+	}
+
+	#[test]
+	fn metadata_over_the_maximum_is_rejected() {
+		assert!(split_metadata(&vec![0; MAX_CHUNKED_METADATA_LEN + 1]).is_err());
+	}
+
+	#[test]
+	fn shuffled_chunks_are_joined_in_order() {
+		let metadata = metadata(3 * MAX_CHUNK_DATA_LEN + 10);
+		let mut chunks = split_metadata(&metadata).unwrap();
+		chunks.swap(0, 3);
+		chunks.swap(1, 2);
+		assert_eq!(join_metadata(chunks).unwrap(), metadata);
+	}
+
+	#[test]
+	fn missing_chunks_are_rejected() {
+		let mut chunks = split_metadata(&metadata(3 * MAX_CHUNK_DATA_LEN)).unwrap();
+		chunks.remove(1);
+		assert!(join_metadata(chunks).is_err());
+		assert!(join_metadata(vec![]).is_err());
+	}
+
+	#[test]
+	fn duplicated_chunks_are_rejected() {
+		let mut chunks = split_metadata(&metadata(3 * MAX_CHUNK_DATA_LEN)).unwrap();
+		chunks[2] = chunks[0].clone();
+		assert!(join_metadata(chunks).is_err());
+	}
+
+	#[test]
+	fn chunks_with_inconsistent_totals_are_rejected() {
+		let mut chunks = split_metadata(&metadata(3 * MAX_CHUNK_DATA_LEN)).unwrap();
+		chunks[1].total = 4;
+		assert!(join_metadata(chunks).is_err());
+	}
+}
@@ -0,0 +1,155 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compatibility of the runtime of a node with the metadata compiled into the `redot` module.
+
+use std::fmt;
+use subxt::Metadata;
+
+/// The metadata the `redot` module is generated from.
+pub(crate) const COMPILED_METADATA: &[u8] = include_bytes!("../redoxt_metadata.scale");
+
+// The pallet whose calls and storage the client uses.
+const TASK_PALLET: &str = "Task";
+
+/// Error returned when the runtime of the node changed the `task` pallet items this crate was
+/// compiled against, so that calling or reading them would fail to encode or decode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncompatibleMetadata {
+	/// The mismatched items, such as `call Task::new_metadata` or `storage Task::Metadata`.
+	pub mismatched: Vec<String>,
+}
+
+impl fmt::Display for IncompatibleMetadata {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"The runtime metadata of the node does not match the compiled metadata: {}",
+			self.mismatched.join(", ")
+		)
+	}
+}
+
+impl std::error::Error for IncompatibleMetadata {}
+
+/// Compare the calls and storage entries of the `task` pallet in the `compiled` metadata with
+/// the ones of the `node` metadata.
+pub(crate) fn check_task_pallet(
+	compiled: &Metadata,
+	node: &Metadata,
+) -> Result<(), IncompatibleMetadata> {
+	let Some(compiled) = compiled.pallet_by_name(TASK_PALLET) else { return Ok(()) };
+	let Some(node) = node.pallet_by_name(TASK_PALLET) else {
+		return Err(IncompatibleMetadata { mismatched: vec![format!("pallet {}", TASK_PALLET)] })
+	};
+
+	let mut mismatched = Vec::new();
+	for call in compiled.call_variants().unwrap_or_default() {
+		if compiled.call_hash(&call.name) != node.call_hash(&call.name) {
+			mismatched.push(format!("call {}::{}", TASK_PALLET, call.name));
+		}
+	}
+	for entry in compiled.storage().map(|storage| storage.entries()).unwrap_or_default() {
+		if compiled.storage_hash(entry.name()) != node.storage_hash(entry.name()) {
+			mismatched.push(format!("storage {}::{}", TASK_PALLET, entry.name()));
+		}
+	}
+
+	if mismatched.is_empty() {
+		Ok(())
+	} else {
+		Err(IncompatibleMetadata { mismatched })
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::{Decode, Encode};
+	use scale_info::{form::PortableForm, PortableRegistry, TypeDef};
+	use subxt::ext::frame_metadata::{
+		v15::{PalletMetadata, StorageEntryType},
+		RuntimeMetadata, RuntimeMetadataPrefixed,
+	};
+
+	fn compiled() -> Metadata {
+		Metadata::decode(&mut &COMPILED_METADATA[..]).unwrap()
+	}
+
+	// The compiled metadata, with the `Task` pallet and the type registry modified by `change`.
+	fn changed(
+		change: impl FnOnce(&mut PalletMetadata<PortableForm>, &mut PortableRegistry),
+	) -> Metadata {
+		let mut prefixed = RuntimeMetadataPrefixed::decode(&mut &COMPILED_METADATA[..]).unwrap();
+		let RuntimeMetadata::V15(metadata) = &mut prefixed.1 else {
+			panic!("Expected V15 metadata")
+		};
+		let pallet = metadata.pallets.iter_mut().find(|pallet| pallet.name == TASK_PALLET).unwrap();
+		change(pallet, &mut metadata.types);
+		Metadata::decode(&mut &prefixed.encode()[..]).unwrap()
+	}
+
+	#[test]
+	fn compiled_metadata_is_compatible_with_itself() {
+		assert_eq!(check_task_pallet(&compiled(), &compiled()), Ok(()));
+	}
+
+	#[test]
+	fn missing_task_pallet_is_reported() {
+		let mut node = compiled();
+		node.retain(|pallet| pallet != TASK_PALLET, |_| true);
+
+		let error = check_task_pallet(&compiled(), &node).unwrap_err();
+		assert_eq!(error.mismatched, vec!["pallet Task".to_string()]);
+		assert!(error.to_string().contains("pallet Task"));
+	}
+
+	#[test]
+	fn changed_call_is_reported() {
+		let mut name = String::new();
+		let node = changed(|pallet, types| {
+			let calls = pallet.calls.as_ref().unwrap().ty.id;
+			let ty = types.types.iter_mut().find(|ty| ty.id == calls).unwrap();
+			let TypeDef::Variant(variants) = &mut ty.ty.type_def else {
+				panic!("Expected an enum")
+			};
+			// Drop an argument of the first call taking any.
+			let call = variants.variants.iter_mut().find(|call| !call.fields.is_empty()).unwrap();
+			call.fields.pop();
+			name = call.name.clone();
+		});
+
+		let error = check_task_pallet(&compiled(), &node).unwrap_err();
+		assert!(error.mismatched.contains(&format!("call Task::{}", name)), "{}", error);
+	}
+
+	#[test]
+	fn changed_storage_entry_is_reported() {
+		let mut name = String::new();
+		let node = changed(|pallet, _| {
+			// Store the call enum instead of the value of the first entry.
+			let other = pallet.calls.as_ref().unwrap().ty;
+			let entry = &mut pallet.storage.as_mut().unwrap().entries[0];
+			entry.ty = match &entry.ty {
+				StorageEntryType::Plain(_) => StorageEntryType::Plain(other),
+				StorageEntryType::Map { hashers, key, .. } =>
+					StorageEntryType::Map { hashers: hashers.clone(), key: *key, value: other },
+			};
+			name = entry.name.clone();
+		});
+
+		let error = check_task_pallet(&compiled(), &node).unwrap_err();
+		assert_eq!(error.mismatched, vec![format!("storage Task::{}", name)]);
+	}
+}
@@ -0,0 +1,151 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{tls, RedotConfig};
+use anyhow::{anyhow, Context, Result};
+use jsonrpsee::ws_client::WsClientBuilder;
+use std::{
+	fmt,
+	sync::Arc,
+	time::{Duration, Instant},
+};
+use subxt::OnlineClient;
+use tokio_rustls::rustls::ClientConfig;
+
+/// Default time allowed to connect to the node.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default time allowed for the node to answer a request.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Error returned once the connection to the node is lost.
+///
+/// See `Client::reconnect`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disconnected {
+	/// The URL of the node.
+	pub url: String,
+}
+
+impl fmt::Display for Disconnected {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "Lost the connection to {}", self.url)
+	}
+}
+
+impl std::error::Error for Disconnected {}
+
+/// Connect to the node at `url`.
+///
+/// Connecting includes opening the websocket and fetching the metadata and runtime version of
+/// the node, which must all complete within `connect_timeout`. Every later request fails if the
+/// node does not answer it within `request_timeout`.
+///
+/// `wss://` URLs are verified with `tls` when given, and with the system roots otherwise.
+pub(crate) async fn connect(
+	url: &str,
+	tls: Option<&Arc<ClientConfig>>,
+	connect_timeout: Duration,
+	request_timeout: Duration,
+) -> Result<OnlineClient<RedotConfig>> {
+	let started = Instant::now();
+	let connecting = async {
+		let rpc = match tls {
+			Some(config) if url.starts_with("wss://") =>
+				tls::ws_client(url, config.clone(), request_timeout).await?,
+			_ => WsClientBuilder::default()
+				.connection_timeout(connect_timeout)
+				.request_timeout(request_timeout)
+				.build(url)
+				.await?,
+		};
+		Ok::<_, anyhow::Error>(OnlineClient::<RedotConfig>::from_rpc_client(Arc::new(rpc)).await?)
+	};
+	tokio::time::timeout(connect_timeout, connecting)
+		.await
+		.map_err(|elapsed| {
+			let message = format!("Failed to connect to {} within {:?}", url, started.elapsed());
+			anyhow::Error::new(elapsed).context(message)
+		})?
+		.with_context(|| format!("Failed to connect to {}", url))
+}
+
+/// Connect to the first of `urls` that accepts the connection, trying them in order from
+/// index `start` and wrapping around. See `connect`.
+///
+/// Returns the index of the connected URL with the client. If no URL connects, the error lists
+/// each URL with the reason it failed.
+pub(crate) async fn connect_any(
+	urls: &[String],
+	start: usize,
+	tls: Option<&Arc<ClientConfig>>,
+	connect_timeout: Duration,
+	request_timeout: Duration,
+) -> Result<(usize, OnlineClient<RedotConfig>)> {
+	let mut failures = Vec::new();
+	for offset in 0..urls.len() {
+		let index = (start + offset) % urls.len();
+		match connect(&urls[index], tls, connect_timeout, request_timeout).await {
+			Ok(api) => return Ok((index, api)),
+			Err(e) => {
+				::log::warn!("{:#}", e);
+				failures.push(format!("{:#}", e));
+			},
+		}
+	}
+	Err(anyhow!("No node URL could be connected to: [{}]", failures.join("; ")))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn disconnected_is_found_behind_the_transport_error() {
+		let reset = std::io::Error::from(std::io::ErrorKind::ConnectionReset);
+		let url = "ws://127.0.0.1:9944".to_string();
+		let error = anyhow::Error::from(subxt::Error::Io(reset)).context(Disconnected { url });
+		assert!(error.is::<Disconnected>());
+		assert!(crate::is_transient_error(&error));
+	}
+
+	// A URL nothing listens on, so connecting to it is refused right away.
+	fn closed_url() -> String {
+		let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+		format!("ws://{}", listener.local_addr().unwrap())
+	}
+
+	#[tokio::test]
+	async fn every_failed_url_is_reported() {
+		let urls = vec![closed_url(), closed_url()];
+		let timeout = Duration::from_secs(5);
+		let error = connect_any(&urls, 1, None, timeout, timeout).await.unwrap_err().to_string();
+		for url in &urls {
+			assert!(error.contains(url.as_str()), "{}", error);
+		}
+		assert!(connect_any(&[], 0, None, timeout, timeout).await.is_err());
+	}
+
+	#[tokio::test]
+	async fn connect_timeout_fires() {
+		// A non-routable address, so the connection attempt hangs until the timeout.
+		let url = "ws://10.255.255.1:9944";
+		let timeout = Duration::from_millis(200);
+
+		let started = Instant::now();
+		let error = connect(url, None, timeout, DEFAULT_REQUEST_TIMEOUT).await.unwrap_err();
+		assert!(started.elapsed() < timeout + Duration::from_secs(1));
+		assert!(error.to_string().contains(url), "{}", error);
+	}
+}
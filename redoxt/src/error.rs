@@ -0,0 +1,302 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The error type of the public API of the client.
+
+use crate::{
+	AlreadySubmitted, Disconnected, ExtrinsicFailed, IncompatibleMetadata, SubmissionFailed,
+};
+use jsonrpsee::core::Error as JsonRpcError;
+use std::fmt;
+use subxt::error::RpcError;
+
+/// Error returned by `Client`, `ClientSync` and `ClientBuilder`.
+///
+/// The variant tells what kind of failure happened, so callers can decide how to react, for
+/// example retrying on `Timeout` but giving up on `Submission`. Each variant holds the
+/// underlying error with its context, which `downcast_ref` and `is` look into, as with
+/// `anyhow::Error`.
+///
+/// `RedoxtError` converts into `anyhow::Error` with `?`, like any other error.
+#[derive(Debug)]
+pub enum RedoxtError {
+	/// The node could not be reached, or the connection to it was lost.
+	Connection(anyhow::Error),
+	/// The node did not answer in time.
+	Timeout(anyhow::Error),
+	/// The extrinsic was rejected, left the transaction pool, or its call failed to dispatch.
+	Submission(anyhow::Error),
+	/// A value read from or sent to the node could not be encoded or decoded.
+	Decode(anyhow::Error),
+	/// The metadata of the node does not match the calls and storage the client uses.
+	Metadata(anyhow::Error),
+	/// Any other failure, such as an invalid argument.
+	Other(anyhow::Error),
+}
+
+impl RedoxtError {
+	/// Return the underlying error.
+	pub fn inner(&self) -> &anyhow::Error {
+		match self {
+			RedoxtError::Connection(e) |
+			RedoxtError::Timeout(e) |
+			RedoxtError::Submission(e) |
+			RedoxtError::Decode(e) |
+			RedoxtError::Metadata(e) |
+			RedoxtError::Other(e) => e,
+		}
+	}
+
+	/// Return the underlying error, dropping its kind.
+	pub fn into_inner(self) -> anyhow::Error {
+		match self {
+			RedoxtError::Connection(e) |
+			RedoxtError::Timeout(e) |
+			RedoxtError::Submission(e) |
+			RedoxtError::Decode(e) |
+			RedoxtError::Metadata(e) |
+			RedoxtError::Other(e) => e,
+		}
+	}
+
+	/// Whether the underlying error is, or has the context of, an error of type `E`.
+	pub fn is<E>(&self) -> bool
+	where
+		E: fmt::Display + fmt::Debug + Send + Sync + 'static,
+	{
+		self.inner().is::<E>()
+	}
+
+	/// Return the underlying error as an `E`, if it is one or has the context of one.
+	pub fn downcast_ref<E>(&self) -> Option<&E>
+	where
+		E: fmt::Display + fmt::Debug + Send + Sync + 'static,
+	{
+		self.inner().downcast_ref::<E>()
+	}
+
+	/// Whether the same call may succeed if made again, after a `Connection` or a `Timeout`
+	/// error.
+	pub fn is_transient(&self) -> bool {
+		matches!(self, RedoxtError::Connection(_) | RedoxtError::Timeout(_))
+	}
+
+	/// Wrap the underlying error with additional context, keeping its kind.
+	pub fn context<C>(self, context: C) -> Self
+	where
+		C: fmt::Display + Send + Sync + 'static,
+	{
+		match self {
+			RedoxtError::Connection(e) => RedoxtError::Connection(e.context(context)),
+			RedoxtError::Timeout(e) => RedoxtError::Timeout(e.context(context)),
+			RedoxtError::Submission(e) => RedoxtError::Submission(e.context(context)),
+			RedoxtError::Decode(e) => RedoxtError::Decode(e.context(context)),
+			RedoxtError::Metadata(e) => RedoxtError::Metadata(e.context(context)),
+			RedoxtError::Other(e) => RedoxtError::Other(e.context(context)),
+		}
+	}
+}
+
+impl fmt::Display for RedoxtError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt::Display::fmt(self.inner(), f)
+	}
+}
+
+impl std::error::Error for RedoxtError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		self.inner().source()
+	}
+}
+
+impl From<anyhow::Error> for RedoxtError {
+	fn from(error: anyhow::Error) -> Self {
+		// A `RedoxtError` converted into `anyhow::Error` and back keeps its kind.
+		let error = match error.downcast::<RedoxtError>() {
+			Ok(error) => return error,
+			Err(error) => error,
+		};
+		if has::<Disconnected>(&error) {
+			return RedoxtError::Connection(error)
+		}
+		if has::<tokio::time::error::Elapsed>(&error) {
+			return RedoxtError::Timeout(error)
+		}
+		if has::<IncompatibleMetadata>(&error) {
+			return RedoxtError::Metadata(error)
+		}
+		if has::<SubmissionFailed>(&error) ||
+			has::<ExtrinsicFailed>(&error) ||
+			has::<AlreadySubmitted>(&error)
+		{
+			return RedoxtError::Submission(error)
+		}
+		let subxt_error = error
+			.downcast_ref::<subxt::Error>()
+			.or_else(|| error.chain().find_map(|cause| cause.downcast_ref::<subxt::Error>()));
+		match subxt_error {
+			Some(subxt::Error::Io(_)) => RedoxtError::Connection(error),
+			Some(subxt::Error::Rpc(RpcError::ClientError(e))) =>
+				match e.downcast_ref::<JsonRpcError>() {
+					Some(e) => from_json_rpc(e, error),
+					None => RedoxtError::Other(error),
+				},
+			Some(subxt::Error::Rpc(RpcError::SubscriptionDropped)) =>
+				RedoxtError::Connection(error),
+			Some(subxt::Error::Rpc(RpcError::RequestRejected(_))) |
+			Some(subxt::Error::Runtime(_)) |
+			Some(subxt::Error::Transaction(_)) => RedoxtError::Submission(error),
+			Some(subxt::Error::Metadata(_)) => RedoxtError::Metadata(error),
+			Some(subxt::Error::Codec(_)) |
+			Some(subxt::Error::Decode(_)) |
+			Some(subxt::Error::Encode(_)) => RedoxtError::Decode(error),
+			Some(_) => RedoxtError::Other(error),
+			None => {
+				let json_rpc_error = error
+					.downcast_ref::<JsonRpcError>()
+					.or_else(|| error.chain().find_map(|cause| cause.downcast_ref()));
+				match json_rpc_error {
+					Some(e) => from_json_rpc(e, error),
+					None if has::<codec::Error>(&error) => RedoxtError::Decode(error),
+					None => RedoxtError::Other(error),
+				}
+			},
+		}
+	}
+}
+
+// Classify `error`, caused by the JSON-RPC client error `e`.
+fn from_json_rpc(e: &JsonRpcError, error: anyhow::Error) -> RedoxtError {
+	match e {
+		JsonRpcError::RequestTimeout => RedoxtError::Timeout(error),
+		JsonRpcError::Transport(_) | JsonRpcError::RestartNeeded(_) =>
+			RedoxtError::Connection(error),
+		_ => RedoxtError::Other(error),
+	}
+}
+
+// Classify a failure to connect to a node, which is a `Connection` error unless it is known to
+// be of another kind, such as a `Timeout`.
+pub(crate) fn connection_error(error: anyhow::Error) -> RedoxtError {
+	match RedoxtError::from(error) {
+		RedoxtError::Other(error) => RedoxtError::Connection(error),
+		error => error,
+	}
+}
+
+impl From<subxt::Error> for RedoxtError {
+	fn from(error: subxt::Error) -> Self {
+		anyhow::Error::from(error).into()
+	}
+}
+
+impl From<codec::Error> for RedoxtError {
+	fn from(error: codec::Error) -> Self {
+		anyhow::Error::from(error).into()
+	}
+}
+
+// Whether `error` is, has the context of, or was caused by an error of type `E`.
+fn has<E>(error: &anyhow::Error) -> bool
+where
+	E: std::error::Error + Send + Sync + 'static,
+{
+	error.is::<E>() || error.chain().any(|cause| cause.is::<E>())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use anyhow::anyhow;
+	use std::time::Duration;
+
+	#[test]
+	fn connection_failures() {
+		let url = "ws://127.0.0.1:9944".to_string();
+		let error: RedoxtError = anyhow!("Subscription ended").context(Disconnected { url }).into();
+		assert!(matches!(error, RedoxtError::Connection(_)));
+		assert!(error.is::<Disconnected>());
+		assert!(error.is_transient());
+
+		let reset = std::io::Error::from(std::io::ErrorKind::ConnectionReset);
+		let error = RedoxtError::from(subxt::Error::Io(reset));
+		assert!(matches!(error, RedoxtError::Connection(_)));
+
+		let dropped = subxt::Error::Rpc(RpcError::SubscriptionDropped);
+		assert!(matches!(RedoxtError::from(dropped), RedoxtError::Connection(_)));
+	}
+
+	#[tokio::test]
+	async fn timeouts() {
+		let elapsed =
+			tokio::time::timeout(Duration::ZERO, std::future::pending::<()>()).await.unwrap_err();
+		let error: RedoxtError =
+			anyhow::Error::new(elapsed).context("Failed to connect to ws://node").into();
+		assert!(matches!(error, RedoxtError::Timeout(_)));
+		assert_eq!(error.to_string(), "Failed to connect to ws://node");
+
+		let timeout = RpcError::ClientError(Box::new(JsonRpcError::RequestTimeout));
+		let error = RedoxtError::from(subxt::Error::Rpc(timeout));
+		assert!(matches!(error, RedoxtError::Timeout(_)));
+		assert!(error.is_transient());
+	}
+
+	#[test]
+	fn submission_failures() {
+		let error = RedoxtError::from(anyhow::Error::from(SubmissionFailed::Dropped));
+		assert!(matches!(error, RedoxtError::Submission(_)));
+		assert!(!error.is_transient());
+		assert_eq!(error.downcast_ref::<SubmissionFailed>(), Some(&SubmissionFailed::Dropped));
+
+		let error = anyhow::Error::from(AlreadySubmitted { id: 1, nonce: 2 });
+		assert!(matches!(RedoxtError::from(error), RedoxtError::Submission(_)));
+
+		let rejected = subxt::Error::Rpc(RpcError::RequestRejected("Invalid signature".into()));
+		assert!(matches!(RedoxtError::from(rejected), RedoxtError::Submission(_)));
+	}
+
+	#[test]
+	fn decode_and_metadata_failures() {
+		let error = RedoxtError::from(codec::Error::from("Not enough data"));
+		assert!(matches!(error, RedoxtError::Decode(_)));
+
+		let error = anyhow::Error::from(codec::Error::from("Not enough data")).context("Bad fee");
+		assert!(matches!(RedoxtError::from(error), RedoxtError::Decode(_)));
+
+		let mismatched = vec!["pallet Task".to_string()];
+		let error = anyhow::Error::from(IncompatibleMetadata { mismatched });
+		assert!(matches!(RedoxtError::from(error), RedoxtError::Metadata(_)));
+	}
+
+	#[test]
+	fn other_failures_keep_their_message() {
+		let error = RedoxtError::from(anyhow!("No signer at index 3"));
+		assert!(matches!(error, RedoxtError::Other(_)));
+		assert_eq!(error.to_string(), "No signer at index 3");
+
+		let error = anyhow::Error::from(error);
+		assert_eq!(error.to_string(), "No signer at index 3");
+	}
+
+	#[test]
+	fn kind_survives_a_round_trip_through_anyhow() {
+		let error = RedoxtError::Submission(anyhow!("Rejected")).context("Failed to submit");
+		let error = RedoxtError::from(anyhow::Error::from(error));
+		assert!(matches!(error, RedoxtError::Submission(_)));
+		assert_eq!(error.to_string(), "Failed to submit");
+
+		let error = connection_error(anyhow!("No node URL could be connected to: []"));
+		assert!(matches!(error, RedoxtError::Connection(_)));
+	}
+}
@@ -13,9 +13,126 @@
 // limitations under the License.
 
 use crate::Client;
+use anyhow::{anyhow, ensure, Context, Result};
+use codec::Encode;
+use frost_ed25519::VerifyingKey;
+use sp_runtime::KeyTypeId;
+use std::{path::Path, str::FromStr};
+use subxt::error::RpcError;
+use subxt_signer::{sr25519::Keypair, SecretUri};
 
 pub use primitive_types::H256;
 
+/// Tag prepended to metadata messages before signing when domain separation is enabled.
+///
+/// It keeps a signature over metadata from being valid for another message type that happens to
+/// share the same bytes. It matches `pallet_task::METADATA_DOMAIN_TAG`: the `task` pallet accepts
+/// a signature over the message with or without the tag.
+pub const METADATA_DOMAIN_TAG: &[u8] = b"REDOT_METADATA_V1";
+
+/// Prepends `METADATA_DOMAIN_TAG` to a metadata message.
+pub fn domain_separated_message(message: &[u8]) -> Vec<u8> {
+	let mut msg = Vec::with_capacity(METADATA_DOMAIN_TAG.len() + message.len());
+	msg.extend_from_slice(METADATA_DOMAIN_TAG);
+	msg.extend_from_slice(message);
+	msg
+}
+
+/// Builds the message whose signature the `task` pallet verifies for metadata submitted with
+/// `ClientSync::submit_metadata`.
+///
+/// The extrinsic carries `metadata.encode()` as a bounded vector, and the pallet verifies the
+/// encoding of that vector followed by the SCALE-encoded task `id` and `nonce`.
+pub fn metadata_signing_message<T: Encode + ?Sized>(metadata: &T, id: u32, nonce: u32) -> Vec<u8> {
+	let mut msg = metadata.encode().encode();
+	msg.extend_from_slice(&id.encode());
+	msg.extend_from_slice(&nonce.encode());
+	msg
+}
+
+/// Encodes a group verifying key into the bytes stored on chain by the `task` pallet.
+///
+/// Use it, and `verifying_key_from_chain_bytes`, whenever the local key is compared with the
+/// registered one, so both sides go through the same conversion.
+pub fn verifying_key_to_chain_bytes(key: &VerifyingKey) -> [u8; 32] {
+	key.serialize()
+}
+
+/// Decodes a group verifying key from the bytes stored on chain by the `task` pallet.
+///
+/// Fails if `bytes` is not 32 bytes long or is not a valid key.
+pub fn verifying_key_from_chain_bytes(bytes: &[u8]) -> Result<VerifyingKey> {
+	let bytes: [u8; 32] = bytes
+		.try_into()
+		.map_err(|_| anyhow!("Verifying key must be 32 bytes, got {}", bytes.len()))?;
+	VerifyingKey::deserialize(bytes).map_err(|e| anyhow!("Invalid verifying key: {}", e))
+}
+
+/// Builds an sr25519 keypair from a secret URI.
+///
+/// The URI is a mnemonic phrase, a hex seed or a dev account name such as `//Alice`, followed by
+/// optional derivation junctions and a password: `<phrase>//hard/soft///password`. The secret is
+/// left out of the returned errors.
+pub fn keypair_from_secret_uri(uri: &str) -> Result<Keypair> {
+	let uri = SecretUri::from_str(uri).map_err(|e| anyhow!("Invalid secret URI: {:?}", e))?;
+	Keypair::from_uri(&uri).map_err(|e| anyhow!("Invalid signer key: {:?}", e))
+}
+
+/// Builds an sr25519 keypair from a BIP-39 mnemonic phrase, protected by `password` if any.
+///
+/// Derivation paths are not accepted here; use `keypair_from_secret_uri` for them.
+pub fn keypair_from_mnemonic(phrase: &str, password: Option<&str>) -> Result<Keypair> {
+	ensure!(
+		!phrase.contains('/'),
+		"Mnemonic phrase must not contain a derivation path, use a secret URI instead"
+	);
+	match password {
+		Some(password) => keypair_from_secret_uri(&format!("{}///{}", phrase.trim(), password)),
+		None => keypair_from_secret_uri(phrase.trim()),
+	}
+}
+
+/// Builds the sr25519 keypair of `public` stored in a Substrate keystore directory.
+///
+/// The keystore is the directory written by a node's `--keystore-path`, where each key is a file
+/// named after the hex-encoded key type and public key, holding the secret URI as a JSON string.
+/// `sp_keystore` only signs with the keys it holds, so the file is read directly to rebuild the
+/// keypair. Like the node, `password` replaces the password of the stored secret URI.
+pub fn keypair_from_keystore(
+	path: &Path,
+	key_type: KeyTypeId,
+	public: &[u8; 32],
+	password: Option<&str>,
+) -> Result<Keypair> {
+	let file = path.join(format!("{}{}", hex::encode(key_type.0), hex::encode(public)));
+	let content = std::fs::read_to_string(&file).with_context(|| {
+		format!(
+			"No {:?} key 0x{} in keystore {}",
+			key_type,
+			hex::encode(public),
+			path.display()
+		)
+	})?;
+	let secret: String = serde_json::from_str(&content)
+		.with_context(|| format!("Keystore file {} is not a JSON string", file.display()))?;
+
+	let uri = match password {
+		Some(password) => {
+			let phrase = secret.find("///").map_or(secret.as_str(), |at| &secret[..at]);
+			format!("{}///{}", phrase, password)
+		},
+		None => secret,
+	};
+	let keypair = keypair_from_secret_uri(&uri)
+		.with_context(|| format!("Invalid secret in keystore file {}", file.display()))?;
+	ensure!(
+		&keypair.public_key().0 == public,
+		"Key in keystore file {} does not match its public key, check the keystore password",
+		file.display()
+	);
+	Ok(keypair)
+}
+
 /// Waits for two block confirmations using a client subscription.
 ///
 /// # Arguments
@@ -26,13 +143,139 @@ pub use primitive_types::H256;
 ///
 /// - `Result<(), Box<dyn std::error::Error>>`: A result indicating success or failure.
 pub async fn wait_for_block(client: &Client) -> Result<(), Box<dyn std::error::Error>> {
-	let mut sub = client.api.rpc().subscribe_all_block_headers().await?;
+	let mut sub = client.api().rpc().subscribe_all_block_headers().await?;
 	sub.next().await;
 	sub.next().await;
 
 	Ok(())
 }
 
+/// Returns whether an error returned by a submission is transient, such as a dropped
+/// connection, so that submitting the same extrinsic again may succeed.
+///
+/// Errors reported by the node about the extrinsic itself, such as a bad signature, are
+/// permanent.
+pub fn is_transient_error(error: &anyhow::Error) -> bool {
+	match error.downcast_ref::<subxt::Error>() {
+		Some(subxt::Error::Io(_)) => true,
+		Some(subxt::Error::Rpc(RpcError::SubscriptionDropped)) => true,
+		Some(subxt::Error::Rpc(RpcError::ClientError(e))) => matches!(
+			e.downcast_ref::<jsonrpsee::core::Error>(),
+			Some(
+				jsonrpsee::core::Error::Transport(_) |
+					jsonrpsee::core::Error::RestartNeeded(_) |
+					jsonrpsee::core::Error::RequestTimeout
+			)
+		),
+		_ => false,
+	}
+}
+
+/// Returns whether an error shows that the connection to the node is lost, so that every later
+/// call over it fails until the client connects again. See `Client::reconnect`.
+///
+/// A request that timed out leaves the connection usable.
+pub fn is_connection_lost(error: &anyhow::Error) -> bool {
+	match error.downcast_ref::<subxt::Error>() {
+		Some(subxt::Error::Io(_)) => true,
+		Some(subxt::Error::Rpc(RpcError::ClientError(e))) => matches!(
+			e.downcast_ref::<jsonrpsee::core::Error>(),
+			Some(jsonrpsee::core::Error::Transport(_) | jsonrpsee::core::Error::RestartNeeded(_))
+		),
+		_ => false,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::redot::runtime_types::bounded_collections::weak_bounded_vec::WeakBoundedVec;
+
+	// Compressed Edwards y-coordinate of the ed25519 base point.
+	fn base_point() -> [u8; 32] {
+		let mut bytes = [0x66; 32];
+		bytes[0] = 0x58;
+		bytes
+	}
+
+	#[test]
+	fn metadata_signing_message_matches_the_pallet() {
+		let metadata = vec![0xaau8, 0xbb];
+
+		// What the pallet rebuilds from the `new_metadata` arguments.
+		let stored = WeakBoundedVec(metadata.encode());
+		let mut expected = stored.encode();
+		expected.extend_from_slice(&1u32.encode());
+		expected.extend_from_slice(&7u32.encode());
+
+		assert_eq!(metadata_signing_message(&metadata, 1, 7), expected);
+		assert_eq!(expected, vec![0x0c, 0x08, 0xaa, 0xbb, 1, 0, 0, 0, 7, 0, 0, 0]);
+	}
+
+	#[test]
+	fn verifying_key_round_trips_through_chain_bytes() {
+		let key = verifying_key_from_chain_bytes(&base_point()).unwrap();
+		assert_eq!(verifying_key_to_chain_bytes(&key), base_point());
+		let decoded = verifying_key_from_chain_bytes(&verifying_key_to_chain_bytes(&key)).unwrap();
+		assert_eq!(decoded, key);
+	}
+
+	const DEV_PHRASE: &str =
+		"bottom drive obey lake curtain smoke basket hold race lonely fit walk";
+
+	#[test]
+	fn known_mnemonic_gives_known_account() {
+		let keypair = keypair_from_mnemonic(DEV_PHRASE, None).unwrap();
+		assert_eq!(
+			hex::encode(keypair.public_key().0),
+			"46ebddef8cd9bb167dc30878d7113b7e168e6f0646beffd77d69d39bad76b47a"
+		);
+
+		let alice = subxt_signer::sr25519::dev::alice().public_key().to_account_id();
+		let derived = keypair_from_secret_uri(&format!("{}//Alice", DEV_PHRASE)).unwrap();
+		assert_eq!(derived.public_key().to_account_id(), alice);
+		let named = keypair_from_secret_uri("//Alice").unwrap();
+		assert_eq!(named.public_key().to_account_id(), alice);
+	}
+
+	#[test]
+	fn password_changes_the_account() {
+		let plain = keypair_from_mnemonic(DEV_PHRASE, None).unwrap();
+		let protected = keypair_from_mnemonic(DEV_PHRASE, Some("secret")).unwrap();
+		assert_ne!(plain.public_key().0, protected.public_key().0);
+	}
+
+	#[test]
+	fn invalid_secrets_are_rejected() {
+		assert!(keypair_from_mnemonic("not a valid phrase", None).is_err());
+		assert!(keypair_from_mnemonic(&format!("{}//Alice", DEV_PHRASE), None).is_err());
+		assert!(keypair_from_secret_uri("0x1234").is_err());
+	}
+
+	#[test]
+	fn keypair_is_read_from_a_keystore() {
+		let dir = std::env::temp_dir().join(format!("redoxt-keystore-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		let key_type = KeyTypeId(*b"redo");
+		let alice = subxt_signer::sr25519::dev::alice().public_key();
+		let file = dir.join(format!("{}{}", hex::encode(key_type.0), hex::encode(alice.0)));
+		std::fs::write(&file, "\"//Alice\"").unwrap();
+
+		let keypair = keypair_from_keystore(&dir, key_type, &alice.0, None).unwrap();
+		assert_eq!(keypair.public_key().to_account_id(), alice.to_account_id());
+		assert!(keypair_from_keystore(&dir, key_type, &alice.0, Some("wrong")).is_err());
+		assert!(keypair_from_keystore(&dir, key_type, &[0; 32], None).is_err());
+
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn malformed_chain_bytes_are_rejected() {
+		assert!(verifying_key_from_chain_bytes(&base_point()[..31]).is_err());
+		assert!(verifying_key_from_chain_bytes(&[0xff; 32]).is_err());
+	}
+}
+
 /// Information messages used across the module.
 pub mod info_msg {
 	pub const START_EXAMPLE: &str = "🌟 Start";
@@ -12,15 +12,36 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use anyhow::Result;
-use codec::Encode;
+use anyhow::{anyhow, Context, Result};
+use codec::{Compact, Decode, Encode};
 use frost_ed25519::{Signature as DkgSignature, VerifyingKey};
+use futures::{
+	stream::{self, BoxStream},
+	StreamExt,
+};
 use redot::runtime_types::bounded_collections::weak_bounded_vec::WeakBoundedVec;
+pub use sp_runtime::KeyTypeId;
+use std::{
+	collections::{HashMap, HashSet},
+	fmt,
+	path::{Path, PathBuf},
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc, Mutex, RwLock,
+	},
+	time::Duration,
+};
+use tokio::task::JoinHandle;
+use tokio_rustls::rustls::ClientConfig;
 pub use subxt::PolkadotConfig as RedotConfig;
 use subxt::{
-	ext::scale_encode::EncodeAsType,
+	blocks::Block,
+	dynamic::Value,
+	ext::{scale_encode::EncodeAsType, scale_value::Composite},
+	rpc::{rpc_params, types::Bytes},
 	utils::{AccountId32, MultiAddress, MultiSignature},
-	OnlineClient,
+	tx::{SubmittableExtrinsic, TxPayload, TxProgress, TxStatus},
+	Metadata, OnlineClient,
 };
 use subxt_signer::sr25519::{
 	dev::{self},
@@ -37,6 +58,38 @@ pub use crate::log::init_logger;
 mod helper;
 pub use helper::*;
 
+mod tls;
+pub use tls::Certificate;
+
+pub mod chunk;
+use chunk::{split_metadata, MAX_METADATA_LEN};
+
+mod retry;
+pub use retry::{retry, retry_with_recovery, RetryConfig};
+
+mod connect;
+use connect::{connect, connect_any};
+pub use connect::{Disconnected, DEFAULT_CONNECT_TIMEOUT, DEFAULT_REQUEST_TIMEOUT};
+
+mod compat;
+pub use compat::IncompatibleMetadata;
+
+mod error;
+use error::connection_error;
+pub use error::RedoxtError;
+
+mod params;
+pub use params::SubmitParams;
+
+mod progress;
+pub use progress::{SubmitStatus, TxProgressStream};
+
+mod outcome;
+pub use outcome::{
+	BatchItemOutcome, BatchMode, DispatchClass, DryRunOutcome, ExtrinsicFailed, ExtrinsicOutcome,
+	FeeDetails, Finality, SubmissionFailed, SubmitReceipt,
+};
+
 /// Configuration enum for Melo blockchain.
 pub enum MeloConfig {}
 
@@ -46,21 +99,918 @@ pub type AccountId = AccountId32;
 pub type AccountIndex = u32;
 pub type Address = MultiAddress<AccountId, AccountIndex>;
 
+/// A block of the chain, as yielded by `Client::subscribe_blocks`.
+pub type RedotBlock = Block<RedotConfig, OnlineClient<RedotConfig>>;
+
+/// Events emitted by the `task` pallet.
+pub type TaskEvent = redot::task::Event;
+
+/// A `task` pallet event, with the finalized block that emitted it.
+#[derive(Debug)]
+pub struct TaskEventRecord {
+	/// Number of the block.
+	pub block_number: u32,
+	/// Hash of the block.
+	pub block_hash: H256,
+	/// The decoded event.
+	pub event: TaskEvent,
+}
+
 /// Client structure containing the API for blockchain interactions and a signer for transactions.
 pub struct Client {
-	pub api: OnlineClient<RedotConfig>,
+	// The connection to the node, replaced by `reconnect`.
+	api: RwLock<OnlineClient<RedotConfig>>,
 	pub signer: Keypair,
+	// How to connect to the node again, see `reconnect`. `current` is the index of the URL of
+	// the current connection.
+	urls: Vec<String>,
+	current: AtomicUsize,
+	connect_timeout: Duration,
+	request_timeout: Duration,
+	// The TLS configuration of `wss://` URLs, `None` to use the system roots.
+	tls: Option<Arc<ClientConfig>>,
+	// Metadata supplied at runtime, set again on each connection.
+	metadata: Option<Metadata>,
+	// The nonce of the next extrinsic of `signer`, once it is known.
+	next_nonce: Mutex<Option<u64>>,
+	// How reads and submissions failing with a transient error are retried.
+	retry: RetryConfig,
+	// Whether `submit_metadata` dry-runs its extrinsic first.
+	validate_before_submit: bool,
+	// The `(id, nonce)` pairs of the metadata confirmed in a block, when idempotency is enabled.
+	submitted_metadata: Option<Mutex<HashSet<(u32, u32)>>>,
+	// The signers used by `submit_metadata_as`.
+	signers: Vec<ManagedSigner>,
 }
 
+// A signer of the pool used by `submit_metadata_as`, with the nonce of its next extrinsic once
+// it is known.
+struct ManagedSigner {
+	signer: Keypair,
+	next_nonce: Mutex<Option<u64>>,
+}
+
+/// Error returned when metadata with the same `(id, nonce)` was already confirmed in a block by
+/// this client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlreadySubmitted {
+	/// The task ID of the metadata.
+	pub id: u32,
+	/// The nonce of the metadata.
+	pub nonce: u32,
+}
+
+impl fmt::Display for AlreadySubmitted {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "Metadata {} of task {} was already submitted", self.nonce, self.id)
+	}
+}
+
+impl std::error::Error for AlreadySubmitted {}
+
 impl Client {
+	/// Build a client from the environment. See `ClientBuilder::from_env`.
+	pub async fn from_env() -> Result<Self, RedoxtError> {
+		ClientBuilder::from_env()?.build().await
+	}
+
+	/// Connect to a node using metadata supplied at runtime instead of the node's own metadata.
+	///
+	/// This allows operators to follow a runtime upgrade by providing the new metadata (for
+	/// example, read from a file) without recompiling. The metadata compiled into the `redot`
+	/// module is still used for the typed calls.
+	///
+	/// # Arguments
+	///
+	/// - `url`: The URL of the node.
+	/// - `metadata_bytes`: SCALE-encoded runtime metadata, as produced by `subxt metadata`.
+	/// - `signer`: The signer used for transactions.
+	pub async fn from_url_with_metadata(
+		url: &str,
+		metadata_bytes: &[u8],
+		signer: Keypair,
+	) -> Result<Self, RedoxtError> {
+		let metadata =
+			Metadata::decode(&mut &metadata_bytes[..]).context("Failed to decode metadata")?;
+		let api = connect(url, None, DEFAULT_CONNECT_TIMEOUT, DEFAULT_REQUEST_TIMEOUT)
+			.await
+			.map_err(connection_error)?;
+		api.set_metadata(metadata.clone());
+		let mut client = Client::with_api(api, signer, vec![url.to_string()], 0);
+		client.metadata = Some(metadata);
+		Ok(client)
+	}
+
+	// Create a client over `api`, connected to `urls[current]`, with the default settings.
+	fn with_api(
+		api: OnlineClient<RedotConfig>,
+		signer: Keypair,
+		urls: Vec<String>,
+		current: usize,
+	) -> Self {
+		Client {
+			api: RwLock::new(api),
+			signer,
+			urls,
+			current: AtomicUsize::new(current),
+			connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+			request_timeout: DEFAULT_REQUEST_TIMEOUT,
+			tls: None,
+			metadata: None,
+			next_nonce: Mutex::new(None),
+			retry: RetryConfig::default(),
+			validate_before_submit: false,
+			submitted_metadata: None,
+			signers: Vec::new(),
+		}
+	}
+
+	/// Return the URL of the node the client is connected to.
+	pub fn url(&self) -> &str {
+		&self.urls[self.current.load(Ordering::Relaxed)]
+	}
+
+	/// Return the API client of the current connection.
+	///
+	/// The returned client keeps using the connection it was taken from after a `reconnect`, so
+	/// take it again for each call rather than holding on to it.
+	pub fn api(&self) -> OnlineClient<RedotConfig> {
+		self.api.read().unwrap_or_else(|e| e.into_inner()).clone()
+	}
+
+	/// Connect to a node again, for example after a `Disconnected` error.
+	///
+	/// The URLs the client was built with are tried in turn, starting from the one following
+	/// the current URL, which is tried last. The new connection uses the timeouts and runtime
+	/// metadata the client was built with, and replaces the API client. Since only a shared
+	/// reference is needed, a client shared between tasks can be reconnected by any of them.
+	/// Subscriptions made over the previous connection are not restored, so callers subscribe
+	/// again. The transaction nonce is read again on the next submission.
+	pub async fn reconnect(&self) -> Result<(), RedoxtError> {
+		let (current, api) = connect_any(
+			&self.urls,
+			self.current.load(Ordering::Relaxed) + 1,
+			self.tls.as_ref(),
+			self.connect_timeout,
+			self.request_timeout,
+		)
+		.await
+		.map_err(connection_error)?;
+		if let Some(metadata) = &self.metadata {
+			api.set_metadata(metadata.clone());
+		}
+		*self.api.write().unwrap_or_else(|e| e.into_inner()) = api;
+		self.current.store(current, Ordering::Relaxed);
+		self.forget_nonce();
+		::log::info!("Reconnected to {}", self.url());
+		Ok(())
+	}
+
+	/// Check the connection to the node every `interval` in the background.
+	///
+	/// The returned task completes with a `Disconnected` error once the node stops answering
+	/// because the connection is lost, and runs until aborted otherwise. Other failures, such
+	/// as a node refusing the health request, are ignored.
+	pub fn spawn_keepalive(&self, interval: Duration) -> JoinHandle<Disconnected> {
+		let api = self.api();
+		let url = self.url().to_string();
+		tokio::spawn(async move {
+			loop {
+				tokio::time::sleep(interval).await;
+				if let Err(e) = api.rpc().system_health().await {
+					let e = anyhow::Error::from(e);
+					if is_transient_error(&e) {
+						::log::warn!("Lost the connection to {}: {:?}", url, e);
+						return Disconnected { url }
+					}
+				}
+			}
+		})
+	}
+
+	/// Subscribe to the best blocks with `Finality::InBlock`, or to the finalized blocks with
+	/// `Finality::Finalized`.
+	///
+	/// When the connection is lost, the subscription yields a `RedoxtError::Connection` error
+	/// holding a `Disconnected` error, then ends. Use `is::<Disconnected>()` on the error to tell
+	/// it apart, and `reconnect` before subscribing again.
+	pub async fn subscribe_blocks(
+		&self,
+		finality: Finality,
+	) -> Result<BoxStream<'static, Result<RedotBlock, RedoxtError>>, RedoxtError> {
+		let blocks = match finality {
+			Finality::InBlock => self.api().blocks().subscribe_best().await?.boxed(),
+			Finality::Finalized => self.api().blocks().subscribe_finalized().await?.boxed(),
+		};
+		let url = self.url().to_string();
+		let ended = Disconnected { url: url.clone() };
+		let blocks = blocks
+			.map(move |block| {
+				block.map_err(|e| {
+					let e = anyhow::Error::from(e);
+					if is_transient_error(&e) {
+						RedoxtError::from(e.context(Disconnected { url: url.clone() }))
+					} else {
+						RedoxtError::from(e)
+					}
+				})
+			})
+			.chain(stream::once(async move { Err(RedoxtError::Connection(ended.into())) }));
+		Ok(blocks.boxed())
+	}
+
+	/// Subscribe to the events of the `task` pallet, such as `NewKey` or `MetadataAdded`, emitted
+	/// by finalized blocks.
+	///
+	/// Events are yielded in block order, and in emission order within a block. Like
+	/// `subscribe_blocks`, the stream yields a `Disconnected` error, then ends, when the
+	/// connection is lost.
+	pub async fn subscribe_task_events(
+		&self,
+	) -> Result<BoxStream<'static, Result<TaskEventRecord, RedoxtError>>, RedoxtError> {
+		let blocks = self.subscribe_blocks(Finality::Finalized).await?;
+		let records = blocks
+			.then(|block| async move {
+				let block = block?;
+				let block_number = block.header().number;
+				let block_hash = block.hash();
+				let mut records = Vec::new();
+				for event in block.events().await?.iter() {
+					let event = event?;
+					if event.pallet_name() != "Task" {
+						continue
+					}
+					if let redot::Event::Task(event) = event.as_root_event::<redot::Event>()? {
+						records.push(Ok(TaskEventRecord { block_number, block_hash, event }));
+					}
+				}
+				Ok::<_, RedoxtError>(records)
+			})
+			.flat_map(|records| stream::iter(records.unwrap_or_else(|e| vec![Err(e)])));
+		Ok(records.boxed())
+	}
+
+	/// Return the account that signs and pays for the submitted extrinsics.
+	pub fn account_id(&self) -> AccountId {
+		self.signer.public_key().to_account_id()
+	}
+
+	/// Check that the signer is the `expected` account.
+	///
+	/// The builder defaults to the `//Alice` development key. Calling this at startup prevents
+	/// submitting to a live chain from a development account by mistake.
+	pub fn expect_account(&self, expected: &AccountId) -> Result<(), RedoxtError> {
+		let account = self.account_id();
+		if &account != expected {
+			return Err(RedoxtError::Other(anyhow!(
+				"Signer account {} does not match the expected account {}",
+				account,
+				expected
+			)))
+		}
+		Ok(())
+	}
+
 	/// Update the signer for the client.
+	///
+	/// The nonce of the new signer is read from the chain on its first submission.
 	pub fn set_signer(&mut self, signer: Keypair) {
 		self.signer = signer;
+		self.forget_nonce();
+	}
+
+	/// Add a signer to the pool used by `submit_metadata_as`, returning its index.
+	///
+	/// Each signer of the pool tracks the nonce of its next extrinsic, so submissions from
+	/// different signers run in parallel without nonce contention. The nonce is read from the
+	/// chain and the transaction pool on first use, and again after a failed submission. The
+	/// accounts of the pool must not submit from elsewhere, or their tracked nonces go stale.
+	pub fn add_signer(&mut self, signer: Keypair) -> usize {
+		self.signers.push(ManagedSigner { signer, next_nonce: Mutex::new(None) });
+		self.signers.len() - 1
+	}
+
+	/// Return the number of signers in the pool used by `submit_metadata_as`.
+	pub fn signer_count(&self) -> usize {
+		self.signers.len()
 	}
 
 	/// Update the API client.
 	pub fn set_client(&mut self, api: OnlineClient<RedotConfig>) {
-		self.api = api;
+		*self.api.get_mut().unwrap_or_else(|e| e.into_inner()) = api;
+	}
+
+	/// Set how reads and submissions failing with a transient error are retried.
+	///
+	/// Storage reads are retried freely. A submission is only retried when the node has
+	/// neither the extrinsic in its pool nor included it, so an extrinsic is never sent twice.
+	/// After a lost connection, the client connects again before retrying, see `reconnect`.
+	pub fn set_retry(&mut self, retry: RetryConfig) {
+		self.retry = retry;
+	}
+
+	// Run `op` like `retry`, connecting to a node again before retrying after a lost connection,
+	// since every call over it keeps failing.
+	async fn retry_reconnecting<T, F, Fut>(&self, op: F) -> Result<T>
+	where
+		F: FnMut() -> Fut,
+		Fut: std::future::Future<Output = Result<T>>,
+	{
+		retry_with_recovery(&self.retry, op, || async {
+			self.reconnect().await.map_err(RedoxtError::into_inner)
+		})
+		.await
+	}
+
+	/// Enable or disable the dry run of metadata before `submit_metadata` submits it.
+	///
+	/// A submission the dry run rejects fails without reaching the transaction pool. See
+	/// `dry_run`.
+	pub fn set_validate_before_submit(&mut self, enabled: bool) {
+		self.validate_before_submit = enabled;
+	}
+
+	/// Enable or disable the refusal to submit metadata twice.
+	///
+	/// When enabled, the `(id, nonce)` of metadata confirmed in a block is remembered, and
+	/// submitting metadata with the same `(id, nonce)` again fails with `AlreadySubmitted`
+	/// without building an extrinsic. This protects callers that retry after an ambiguous
+	/// failure. The pairs are kept in memory only, so this guards within the lifetime of the
+	/// process, not across restarts.
+	pub fn set_idempotent_metadata(&mut self, enabled: bool) {
+		self.submitted_metadata = enabled.then(|| Mutex::new(HashSet::new()));
+	}
+
+	// Fails with `AlreadySubmitted` if the metadata `(id, nonce)` was confirmed in a block.
+	fn ensure_not_submitted(&self, id: u32, nonce: u32) -> Result<()> {
+		if let Some(submitted) = &self.submitted_metadata {
+			let submitted = submitted.lock().unwrap_or_else(|e| e.into_inner());
+			if submitted.contains(&(id, nonce)) {
+				return Err(AlreadySubmitted { id, nonce }.into())
+			}
+		}
+		Ok(())
+	}
+
+	// Remembers that the metadata `(id, nonce)` was confirmed in a block.
+	fn record_submitted(&self, id: u32, nonce: u32) {
+		if let Some(submitted) = &self.submitted_metadata {
+			submitted.lock().unwrap_or_else(|e| e.into_inner()).insert((id, nonce));
+		}
+	}
+
+	/// Read the verifying key registered in the `task` pallet, if any.
+	pub async fn verifying_key(&self) -> Result<Option<[u8; 32]>, RedoxtError> {
+		Ok(self.retry_reconnecting(|| async move {
+			let address = redot::storage().task().verifying_key();
+			Ok(self.api().storage().at_latest().await?.fetch(&address).await?)
+		})
+		.await?)
+	}
+
+	/// Read the verifying key registered in the `task` pallet by `new_key` or `rotate_key`,
+	/// decoded, if any.
+	///
+	/// The pallet holds a single key for all tasks. Stored bytes that are not a valid key fail
+	/// with a `RedoxtError::Decode` error.
+	pub async fn task_key(&self) -> Result<Option<VerifyingKey>, RedoxtError> {
+		let key = self.verifying_key().await?;
+		key.map(|bytes| verifying_key_from_chain_bytes(&bytes)).transpose().map_err(|e| {
+			RedoxtError::Decode(e.context("The verifying key stored in the task pallet is invalid"))
+		})
+	}
+
+	/// Read the metadata stored for task `id` and `nonce` at the latest block, if any.
+	///
+	/// The returned bytes are the SCALE encoding of the submitted metadata, so they decode into
+	/// the type it was submitted as.
+	pub async fn task_metadata(&self, id: u32, nonce: u32) -> Result<Option<Vec<u8>>, RedoxtError> {
+		Ok(self.retry_reconnecting(|| async move {
+			let address = redot::storage().task().metadata(id, nonce);
+			let stored = self.api().storage().at_latest().await?.fetch(&address).await?;
+			Ok(stored.map(|metadata| metadata.0))
+		})
+		.await?)
+	}
+
+	/// Read the ID of the last task created in the `task` pallet, if any.
+	pub async fn last_task_id(&self) -> Result<Option<u32>, RedoxtError> {
+		Ok(self.retry_reconnecting(|| async move {
+			let address = redot::storage().task().last_task_id();
+			Ok(self.api().storage().at_latest().await?.fetch(&address).await?)
+		})
+		.await?)
+	}
+
+	/// Read the metadata stored for task `id` and `nonce` at the block `at`, if any.
+	///
+	/// The returned bytes are the SCALE encoding of the submitted metadata.
+	pub async fn stored_metadata_at(
+		&self,
+		id: u32,
+		nonce: u32,
+		at: H256,
+	) -> Result<Option<Vec<u8>>, RedoxtError> {
+		Ok(self.retry_reconnecting(|| async move {
+			let address = redot::storage().task().metadata(id, nonce);
+			let stored = self.api().storage().at(at).fetch(&address).await?;
+			Ok(stored.map(|metadata| metadata.0))
+		})
+		.await?)
+	}
+
+	/// Fetch the current finalized head and its block number.
+	///
+	/// Useful for one-shot queries where keeping a finalized block subscription is not worth it.
+	pub async fn finalized_head(&self) -> Result<(u32, H256), RedoxtError> {
+		Ok(self.retry_reconnecting(|| async move {
+			let hash = self.api().rpc().finalized_head().await?;
+			let header = self
+				.api()
+				.rpc()
+				.header(Some(hash))
+				.await?
+				.with_context(|| format!("Finalized header {:?} not found", hash))?;
+			Ok((header.number, hash))
+		})
+		.await?)
+	}
+
+	/// Read the next metadata nonce of a task from the chain.
+	///
+	/// This is one past the highest nonce stored for `id`, or `0` if the task has no metadata.
+	pub async fn next_metadata_nonce(&self, id: u32) -> Result<u32, RedoxtError> {
+		Ok(self.retry_reconnecting(|| self.fetch_next_metadata_nonce(id)).await?)
+	}
+
+	// A single attempt of `next_metadata_nonce`.
+	async fn fetch_next_metadata_nonce(&self, id: u32) -> Result<u32> {
+		const PAGE_SIZE: u32 = 512;
+
+		let prefix = self.storage_key("Task", "Metadata", &id)?;
+		let storage = self.api().storage().at_latest().await?;
+
+		let mut next_nonce = 0;
+		let mut start_key: Option<Vec<u8>> = None;
+		loop {
+			let keys = storage.fetch_keys(&prefix, PAGE_SIZE, start_key.as_deref()).await?;
+			// The nonce is the last key, hashed with `Twox64Concat`, so its raw bytes come last.
+			for key in keys.iter() {
+				let nonce_bytes = key.0.len().checked_sub(4).map(|at| &key.0[at..]);
+				if let Some(mut nonce_bytes) = nonce_bytes {
+					let nonce = u32::decode(&mut nonce_bytes)?;
+					next_nonce = next_nonce.max(nonce.saturating_add(1));
+				}
+			}
+			if (keys.len() as u32) < PAGE_SIZE {
+				break
+			}
+			start_key = keys.last().map(|key| key.0.clone());
+		}
+
+		Ok(next_nonce)
+	}
+
+	/// Read the transaction nonce of the signer from the chain and the transaction pool again.
+	///
+	/// The client tracks the nonce of the signer locally, so extrinsics submitted back to back
+	/// get consecutive nonces without waiting for the previous ones to be included. A failed
+	/// submission already resets the tracked nonce. Call this after the signer submitted from
+	/// elsewhere, or after an extrinsic was dropped from the pool.
+	pub async fn refresh_nonce(&self) -> Result<u64, RedoxtError> {
+		let nonce = self.sync_nonce_from_pool().await?;
+		*self.next_nonce.lock().unwrap_or_else(|e| e.into_inner()) = Some(nonce);
+		Ok(nonce)
+	}
+
+	// Makes the next submission read the nonce of the signer again.
+	fn forget_nonce(&self) {
+		*self.next_nonce.lock().unwrap_or_else(|e| e.into_inner()) = None;
+	}
+
+	/// Sign a call with the tracked nonce of the signer and submit it, without waiting for its
+	/// inclusion.
+	///
+	/// Every extrinsic the client signs with `signer` goes through this. See `refresh_nonce`.
+	pub async fn submit_with_nonce<Call: TxPayload>(
+		&self,
+		call: &Call,
+	) -> Result<TxProgress<RedotConfig, OnlineClient<RedotConfig>>, RedoxtError> {
+		self.submit_with_params(call, &SubmitParams::default()).await
+	}
+
+	/// Submit a call like `submit_with_nonce`, with the mortality and tip of `params`.
+	///
+	/// A mortal extrinsic is valid from the latest block, read from the node, for the number of
+	/// blocks given by `params.mortality`.
+	pub async fn submit_with_params<Call: TxPayload>(
+		&self,
+		call: &Call,
+		params: &SubmitParams,
+	) -> Result<TxProgress<RedotConfig, OnlineClient<RedotConfig>>, RedoxtError> {
+		let latest = match params.mortality {
+			Some(_) => {
+				let block = self.api().blocks().at_latest().await?;
+				Some((u64::from(block.header().number), block.hash()))
+			},
+			None => None,
+		};
+		let other_params = params.extrinsic_params(latest);
+		let account_nonce = self.reserve_nonce(&self.account_id(), &self.next_nonce).await?;
+		let submitted = async {
+			let mut tx = self.api().tx().create_signed_with_nonce(
+				call,
+				&self.signer,
+				account_nonce,
+				other_params,
+			)?;
+			let mut attempt = 1;
+			loop {
+				match tx.submit_and_watch().await {
+					Ok(progress) => return Ok(progress),
+					Err(e) => {
+						let e = anyhow::Error::from(e);
+						if attempt >= self.retry.max_attempts || !is_transient_error(&e) {
+							return Err(e)
+						}
+						// The same extrinsic is sent again, over a new connection if the
+						// previous one was lost, since every call over it keeps failing.
+						if is_connection_lost(&e) {
+							self.reconnect().await.map_err(RedoxtError::into_inner)?;
+							let encoded = tx.encoded().to_vec();
+							tx = SubmittableExtrinsic::from_bytes(self.api(), encoded);
+						}
+						if self.reached_node(tx.encoded(), account_nonce).await? {
+							return Err(e)
+						}
+						let delay = self.retry.delay(attempt);
+						::log::debug!("Submission failed, retrying in {:?}: {:?}", delay, e);
+						tokio::time::sleep(delay).await;
+						attempt += 1;
+					},
+				}
+			}
+		}
+		.await;
+		if submitted.is_err() {
+			// Whether the nonce was used is unknown, so it is read again on the next use.
+			self.forget_nonce();
+		}
+		Ok(submitted?)
+	}
+
+	// Whether the node included the extrinsic `encoded`, signed with `account_nonce`, or holds it
+	// in its pool.
+	async fn reached_node(&self, encoded: &[u8], account_nonce: u64) -> Result<bool> {
+		if self.api().tx().account_nonce(&self.account_id()).await? > account_nonce {
+			return Ok(true)
+		}
+		let pending: Vec<Bytes> =
+			self.api().rpc().request("author_pendingExtrinsics", rpc_params![]).await?;
+		Ok(pending.iter().any(|extrinsic| extrinsic.0 == encoded))
+	}
+
+	/// Compute the next safe transaction nonce of the signer.
+	///
+	/// The on-chain nonce misses extrinsics still in the transaction pool, so when other
+	/// processes submit from the same account it can collide with theirs. This adds the number
+	/// of pending extrinsics signed by the account to the on-chain nonce.
+	pub async fn sync_nonce_from_pool(&self) -> Result<u64, RedoxtError> {
+		Ok(self.pool_aware_nonce(&self.account_id()).await?)
+	}
+
+	// Compute the next safe transaction nonce of `account_id`, counting its extrinsics still
+	// in the transaction pool.
+	async fn pool_aware_nonce(&self, account_id: &AccountId) -> Result<u64> {
+		let on_chain_nonce = self.api().tx().account_nonce(account_id).await?;
+		let pending: Vec<Bytes> =
+			self.api().rpc().request("author_pendingExtrinsics", rpc_params![]).await?;
+		let pending_count = pending
+			.iter()
+			.filter(|extrinsic| extrinsic_signer(&extrinsic.0).as_ref() == Some(account_id))
+			.count();
+		Ok(on_chain_nonce + pending_count as u64)
+	}
+
+	/// Submit metadata like `ClientSync::submit_metadata`, signed by the signer of the pool at
+	/// `signer_index` instead of the main signer.
+	///
+	/// See `add_signer`. For example, submitting even blocks from one signer and odd blocks from
+	/// another doubles the throughput, as the two accounts do not share a transaction nonce.
+	pub async fn submit_metadata_as<T: Encode + Sync>(
+		&self,
+		signer_index: usize,
+		metadata: &T,
+		id: u32,
+		nonce: u32,
+		sign: &DkgSignature,
+	) -> Result<(), RedoxtError> {
+		let managed = self
+			.signers
+			.get(signer_index)
+			.with_context(|| format!("No signer at index {}", signer_index))?;
+		self.ensure_not_submitted(id, nonce)?;
+
+		let metadata_bytes = WeakBoundedVec(metadata.encode());
+		let submit_metadata_tx =
+			redot::tx().task().new_metadata(id, nonce, metadata_bytes, sign.serialize());
+
+		let account_id = managed.signer.public_key().to_account_id();
+		let account_nonce = self.reserve_nonce(&account_id, &managed.next_nonce).await?;
+		let result = async {
+			let progress = self
+				.api()
+				.tx()
+				.create_signed_with_nonce(
+					&submit_metadata_tx,
+					&managed.signer,
+					account_nonce,
+					Default::default(),
+				)?
+				.submit_and_watch()
+				.await?;
+			Ok::<_, RedoxtError>(self.wait_for_outcome(progress).await?.into_result()?)
+		}
+		.await;
+
+		match result {
+			Ok(_) => {
+				self.record_submitted(id, nonce);
+				Ok(())
+			},
+			Err(e) => {
+				// Whether the nonce was used is unknown, so it is read again on the next use.
+				*managed.next_nonce.lock().unwrap_or_else(|e| e.into_inner()) = None;
+				Err(e)
+			},
+		}
+	}
+
+	/// Submit metadata like `ClientSync::submit_metadata`, with the mortality and tip of
+	/// `params`.
+	pub async fn submit_metadata_with_params<T: Encode + Sync>(
+		&self,
+		metadata: &T,
+		id: u32,
+		nonce: u32,
+		sign: &DkgSignature,
+		finality: Finality,
+		params: &SubmitParams,
+	) -> Result<SubmitReceipt, RedoxtError> {
+		self.ensure_not_submitted(id, nonce)?;
+		let metadata_bytes = metadata.encode();
+
+		let metadata_bytes = WeakBoundedVec(metadata_bytes);
+
+		let submit_metadata_tx =
+			redot::tx().task().new_metadata(id, nonce, metadata_bytes, sign.serialize());
+
+		if self.validate_before_submit {
+			self.dry_run(&submit_metadata_tx)
+				.await?
+				.into_result()
+				.map_err(RedoxtError::Submission)?;
+		}
+		let outcome =
+			self.submit_and_check_with_params(&submit_metadata_tx, finality, params).await?;
+		let receipt = self.receipt(outcome).await?;
+		self.record_submitted(id, nonce);
+
+		Ok(receipt)
+	}
+
+	/// Submit metadata like `ClientSync::submit_metadata`, returning as soon as the transaction
+	/// pool accepted the extrinsic instead of waiting for its inclusion.
+	///
+	/// The extrinsic is signed with the tracked nonce of the signer, so metadata submitted one
+	/// call after the other is included in call order even while earlier submissions are still
+	/// pending. Pass the returned progress to `wait_for_outcome`.
+	/// Metadata submitted this way is not remembered by `set_idempotent_metadata`.
+	pub async fn submit_metadata_in_order<T: Encode + Sync>(
+		&self,
+		metadata: &T,
+		id: u32,
+		nonce: u32,
+		sign: &DkgSignature,
+	) -> Result<TxProgress<RedotConfig, OnlineClient<RedotConfig>>, RedoxtError> {
+		self.ensure_not_submitted(id, nonce)?;
+		let metadata_bytes = WeakBoundedVec(metadata.encode());
+		let submit_metadata_tx =
+			redot::tx().task().new_metadata(id, nonce, metadata_bytes, sign.serialize());
+		self.submit_with_nonce(&submit_metadata_tx).await
+	}
+
+	// Reserve the next transaction nonce of `account_id` tracked in `next_nonce`, reading it on
+	// first use.
+	async fn reserve_nonce(
+		&self,
+		account_id: &AccountId,
+		next_nonce: &Mutex<Option<u64>>,
+	) -> Result<u64> {
+		let known = next_nonce.lock().unwrap_or_else(|e| e.into_inner()).is_some();
+		let synced = if known { None } else { Some(self.pool_aware_nonce(account_id).await?) };
+
+		let mut next_nonce = next_nonce.lock().unwrap_or_else(|e| e.into_inner());
+		// Another submission may have reserved a nonce while this one was reading it.
+		let nonce = next_nonce.or(synced).unwrap_or_default();
+		*next_nonce = Some(nonce + 1);
+		Ok(nonce)
+	}
+
+	/// Sign and submit a call, and return the stream of its statuses, for callers that follow
+	/// the extrinsic themselves instead of waiting with `wait_for_finality`.
+	///
+	/// The extrinsic is signed with the tracked nonce of the signer, like every submission. See
+	/// `TxProgressStream` for the order of the statuses.
+	pub async fn submit_and_watch<Call: TxPayload>(
+		&self,
+		call: &Call,
+	) -> Result<TxProgressStream, RedoxtError> {
+		Ok(TxProgressStream::new(self.submit_with_nonce(call).await?))
+	}
+
+	/// Sign and submit a call built at runtime, without typed wrappers.
+	///
+	/// This covers calls added to the runtime after the metadata of this crate was generated.
+	/// The call is checked against the metadata of the connected node, and `fields` are its
+	/// arguments in order.
+	///
+	/// Returns the hash of the submitted extrinsic, without waiting for its inclusion.
+	pub async fn submit_dynamic(
+		&self,
+		pallet: &str,
+		call: &str,
+		fields: Vec<Value>,
+	) -> Result<H256, RedoxtError> {
+		let payload = subxt::dynamic::tx(pallet, call, Composite::unnamed(fields));
+		let progress = self
+			.submit_with_nonce(&payload)
+			.await
+			.map_err(|e| e.context(format!("Failed to submit {}::{}", pallet, call)))?;
+		Ok(progress.extrinsic_hash())
+	}
+
+	/// Wait until a submitted extrinsic is included in a block and read whether its call
+	/// succeeded.
+	///
+	/// Inclusion alone does not mean success: the call may still fail to dispatch. The outcome
+	/// tells both cases apart, with the decoded dispatch error on failure.
+	pub async fn wait_for_outcome(
+		&self,
+		progress: TxProgress<RedotConfig, OnlineClient<RedotConfig>>,
+	) -> Result<ExtrinsicOutcome, RedoxtError> {
+		self.wait_for_finality(progress, Finality::InBlock).await
+	}
+
+	/// Wait until a submitted extrinsic reaches `finality` and read whether its call succeeded.
+	///
+	/// Status transitions are logged. A retracted block is not an error while waiting for
+	/// finalization, as the extrinsic returns to the pool and may be included again.
+	///
+	/// # Errors
+	///
+	/// Fails with a `RedoxtError::Submission` error holding a `SubmissionFailed` error if the
+	/// extrinsic is dropped, invalid or usurped, if its block is not finalized in time, or if the
+	/// status subscription ends first.
+	pub async fn wait_for_finality(
+		&self,
+		mut progress: TxProgress<RedotConfig, OnlineClient<RedotConfig>>,
+		finality: Finality,
+	) -> Result<ExtrinsicOutcome, RedoxtError> {
+		let failed = |failed: SubmissionFailed| RedoxtError::Submission(failed.into());
+		let extrinsic_hash = progress.extrinsic_hash();
+		while let Some(status) = progress.next().await {
+			let (in_block, finalized) = match status? {
+				TxStatus::Future | TxStatus::Ready => continue,
+				TxStatus::Broadcast(peers) => {
+					let peers = peers.len();
+					::log::debug!("Extrinsic {:?} broadcast to {} peers", extrinsic_hash, peers);
+					continue
+				},
+				TxStatus::InBlock(in_block) => (in_block, false),
+				TxStatus::Finalized(in_block) => (in_block, true),
+				TxStatus::Retracted(block_hash) => {
+					::log::warn!(
+						"Block {:?} including extrinsic {:?} was retracted",
+						block_hash,
+						extrinsic_hash
+					);
+					continue
+				},
+				TxStatus::FinalityTimeout(block_hash) =>
+					return Err(failed(SubmissionFailed::FinalityTimeout(block_hash))),
+				TxStatus::Usurped(hash) => return Err(failed(SubmissionFailed::Usurped(hash))),
+				TxStatus::Dropped => return Err(failed(SubmissionFailed::Dropped)),
+				TxStatus::Invalid => return Err(failed(SubmissionFailed::Invalid)),
+			};
+			::log::debug!(
+				"Extrinsic {:?} {} block {:?}",
+				extrinsic_hash,
+				if finalized { "finalized in" } else { "included in" },
+				in_block.block_hash()
+			);
+			if finality.is_reached(finalized) {
+				let events = in_block.fetch_events().await?;
+				return Ok(ExtrinsicOutcome::from_events(&events, self.api().metadata())?)
+			}
+		}
+		Err(failed(SubmissionFailed::StatusStreamEnded))
+	}
+
+	/// Check whether a call would be accepted and dispatched successfully, without submitting it.
+	///
+	/// The call is signed with the nonce the next submission would use and applied to the
+	/// state of the latest block with `system_dryRun`. While earlier extrinsics of the signer
+	/// are still pending, the nonce is ahead of that state and the extrinsic is reported
+	/// `Invalid`. `system_dryRun` is an unsafe RPC method, which public nodes usually refuse.
+	pub async fn dry_run<Call: TxPayload>(
+		&self,
+		call: &Call,
+	) -> Result<DryRunOutcome, RedoxtError> {
+		let tx = self.sign_without_reserving(call).await?;
+		Ok(tx.dry_run(None).await?.into())
+	}
+
+	/// Estimate the fee of submitting a call signed by the signer, without submitting it.
+	///
+	/// The estimate comes from `TransactionPaymentApi_query_info` at the latest block, so it
+	/// changes with the fee multiplier of the chain.
+	pub async fn estimate_fee<Call: TxPayload>(
+		&self,
+		call: &Call,
+	) -> Result<FeeDetails, RedoxtError> {
+		let tx = self.sign_without_reserving(call).await?;
+		let mut params = tx.encoded().to_vec();
+		(tx.encoded().len() as u32).encode_to(&mut params);
+		let info = self
+			.api()
+			.rpc()
+			.state_call("TransactionPaymentApi_query_info", Some(&params), None)
+			.await?;
+		Ok(FeeDetails::from_query_info(&info)?)
+	}
+
+	// Sign a call with the nonce the next submission would use, without reserving it.
+	async fn sign_without_reserving<Call: TxPayload>(
+		&self,
+		call: &Call,
+	) -> Result<SubmittableExtrinsic<RedotConfig, OnlineClient<RedotConfig>>> {
+		let known = *self.next_nonce.lock().unwrap_or_else(|e| e.into_inner());
+		let account_nonce = match known {
+			Some(nonce) => nonce,
+			None => self.sync_nonce_from_pool().await?,
+		};
+		Ok(self.api().tx().create_signed_with_nonce(
+			call,
+			&self.signer,
+			account_nonce,
+			Default::default(),
+		)?)
+	}
+
+	/// Return the receipt of a successfully dispatched extrinsic, or a `RedoxtError::Submission`
+	/// error holding its `ExtrinsicFailed` error.
+	///
+	/// The number of the including block is read from the node.
+	pub async fn receipt(&self, outcome: ExtrinsicOutcome) -> Result<SubmitReceipt, RedoxtError> {
+		let (block_hash, tx_hash) = match outcome {
+			ExtrinsicOutcome::Success { block_hash, extrinsic_hash } =>
+				(block_hash, extrinsic_hash),
+			ExtrinsicOutcome::Failed(failed) => return Err(RedoxtError::Submission(failed.into())),
+		};
+		let header = self
+			.api()
+			.rpc()
+			.header(Some(block_hash))
+			.await?
+			.with_context(|| format!("Block {:?} not found", block_hash))?;
+		Ok(SubmitReceipt { tx_hash, block_hash, block_number: header.number })
+	}
+
+	/// Sign and submit a call, then wait until it reaches `finality` and read its outcome. See
+	/// `wait_for_finality`.
+	pub async fn submit_and_check<Call: TxPayload>(
+		&self,
+		call: &Call,
+		finality: Finality,
+	) -> Result<ExtrinsicOutcome, RedoxtError> {
+		self.submit_and_check_with_params(call, finality, &SubmitParams::default()).await
+	}
+
+	/// Submit a call like `submit_and_check`, with the mortality and tip of `params`.
+	pub async fn submit_and_check_with_params<Call: TxPayload>(
+		&self,
+		call: &Call,
+		finality: Finality,
+		params: &SubmitParams,
+	) -> Result<ExtrinsicOutcome, RedoxtError> {
+		let progress = self.submit_with_params(call, params).await?;
+		let outcome = self.wait_for_finality(progress, finality).await;
+		if matches!(&outcome, Err(e) if e.is::<SubmissionFailed>()) {
+			// The extrinsic left the pool, so its nonce may not have been used.
+			self.forget_nonce();
+		}
+		outcome
 	}
 
 	/// Get the storage key for a given pallet and entry.
@@ -69,40 +1019,153 @@ impl Client {
 		pallet_name: &str,
 		entry_name: &str,
 		key: &impl EncodeAsType,
-	) -> Result<Vec<u8>> {
+	) -> Result<Vec<u8>, RedoxtError> {
 		let address = subxt::dynamic::storage(pallet_name, entry_name, vec![key]);
-		Ok(self.api.storage().address_bytes(&address)?)
+		Ok(self.api().storage().address_bytes(&address)?)
+	}
+
+	/// Read several storage entries at the latest block, in a single round trip.
+	///
+	/// Each address is a `(pallet, entry, keys)` triple, where `keys` is empty for plain
+	/// entries. All values are read at the same block, so they are consistent with each other.
+	///
+	/// Returns the raw SCALE-encoded values in the order of `addresses`, `None` for entries
+	/// holding no value.
+	pub async fn storage_values_at(
+		&self,
+		addresses: Vec<(&str, &str, Vec<Value>)>,
+	) -> Result<Vec<Option<Vec<u8>>>, RedoxtError> {
+		let keys = addresses
+			.into_iter()
+			.map(|(pallet_name, entry_name, keys)| {
+				let address = subxt::dynamic::storage(pallet_name, entry_name, keys);
+				Ok(self.api().storage().address_bytes(&address)?)
+			})
+			.collect::<Result<Vec<_>>>()?;
+
+		let at = self.api().rpc().block_hash(None).await?.context("Latest block not found")?;
+		let change_sets = self
+			.api()
+			.rpc()
+			.query_storage_at(keys.iter().map(|key| key.as_slice()), Some(at))
+			.await?;
+		let values: HashMap<Vec<u8>, Option<Vec<u8>>> = change_sets
+			.into_iter()
+			.flat_map(|change_set| change_set.changes)
+			.map(|(key, data)| (key.0, data.map(|data| data.0)))
+			.collect();
+
+		Ok(keys.iter().map(|key| values.get(key).cloned().flatten()).collect())
 	}
 }
 
 #[async_trait::async_trait]
 pub trait ClientSync {
-	/// Submit a new key to the blockchain.
-	async fn new_key(&self, key: &VerifyingKey) -> Result<()>;
+	/// Submit a new key to the blockchain and wait until the extrinsic reaches `finality`.
+	///
+	/// Returns where the extrinsic landed, like the other methods returning a `SubmitReceipt`.
+	///
+	/// Like every method of this trait, this waits at least until the extrinsic is included in a
+	/// block and fails with an `ExtrinsicFailed` error if its call failed to dispatch. Methods
+	/// taking a `Finality` fail with a `SubmissionFailed` error if the extrinsic leaves the pool
+	/// before reaching it.
+	async fn new_key(
+		&self,
+		key: &VerifyingKey,
+		finality: Finality,
+	) -> Result<SubmitReceipt, RedoxtError>;
+
+	/// Submit a new key to the blockchain and wait until it is successfully included in a block.
+	async fn register_key(&self, key: &VerifyingKey) -> Result<(), RedoxtError>;
 
-	/// Submit metadata to the blockchain.
+	/// Submit metadata to the blockchain and wait until the extrinsic reaches `finality`.
 	async fn submit_metadata<T: Encode + std::marker::Sync>(
 		&self,
 		metadata: &T,
 		id: u32,
 		nonce: u32,
 		sign: &DkgSignature,
-	) -> Result<()>;
+		finality: Finality,
+	) -> Result<SubmitReceipt, RedoxtError>;
 
-	/// Rotate the key for the validator.
-	async fn rotate_key(&self, key: &VerifyingKey, sign: &DkgSignature) -> Result<()>;
+	/// Submit metadata to the blockchain and return the `task` pallet events it emitted.
+	///
+	/// Waits until the extrinsic is included in a block.
+	async fn submit_metadata_with_events<T: Encode + std::marker::Sync>(
+		&self,
+		metadata: &T,
+		id: u32,
+		nonce: u32,
+		sign: &DkgSignature,
+	) -> Result<Vec<TaskEvent>, RedoxtError>;
+
+	/// Submit metadata that may exceed `chunk::MAX_METADATA_LEN`, split into chunks.
+	///
+	/// Each chunk is submitted with its own `new_metadata` call, using consecutive nonces starting
+	/// at `nonce`. `sign` is called with the message of each chunk, which is what the `task`
+	/// pallet verifies: the stored metadata (the SCALE-encoded chunk) encoded with its length
+	/// prefix, followed by `id` and the chunk's nonce. All chunks
+	/// are signed first, then submitted together, and this waits until they are all included in a
+	/// block. See the `chunk` module for reassembly.
+	///
+	/// Returns the nonce following the last chunk.
+	async fn submit_metadata_chunked<F, Fut>(
+		&self,
+		metadata: &[u8],
+		id: u32,
+		nonce: u32,
+		sign: F,
+	) -> Result<u32, RedoxtError>
+	where
+		F: Fn(Vec<u8>) -> Fut + Send + Sync,
+		Fut: std::future::Future<Output = Result<DkgSignature>> + Send;
+
+	/// Submit several metadata with a single `utility` batch extrinsic, to save the fees and
+	/// nonces of one extrinsic per metadata, for example when catching up.
+	///
+	/// Each item is `(metadata, id, nonce, signature)`, as for `submit_metadata`. `mode` decides
+	/// what a failing item does to the others: with `BatchMode::Atomic` it rolls back the whole
+	/// batch, with `BatchMode::BestEffort` the items before it stay stored and the ones after it
+	/// are not dispatched. Waits until the extrinsic is included in a block.
+	///
+	/// Fails if `items` is empty, if the metadata of an item is longer than
+	/// `chunk::MAX_METADATA_LEN` once encoded, or if the runtime has no `utility` pallet.
+	///
+	/// Returns the outcome of each item, in order.
+	async fn submit_metadata_batch<T: Encode + std::marker::Sync>(
+		&self,
+		items: &[(T, u32, u32, DkgSignature)],
+		mode: BatchMode,
+	) -> Result<Vec<BatchItemOutcome>, RedoxtError>;
+
+	/// Rotate the key for the validator and wait until the extrinsic reaches `finality`.
+	async fn rotate_key(
+		&self,
+		key: &VerifyingKey,
+		sign: &DkgSignature,
+		finality: Finality,
+	) -> Result<SubmitReceipt, RedoxtError>;
 }
 
 #[async_trait::async_trait]
 impl ClientSync for Client {
-	async fn new_key(&self, key: &VerifyingKey) -> Result<()> {
-		let key_bytes = key.serialize();
+	async fn new_key(
+		&self,
+		key: &VerifyingKey,
+		finality: Finality,
+	) -> Result<SubmitReceipt, RedoxtError> {
+		let key_bytes = verifying_key_to_chain_bytes(key);
 		let new_key_tx = redot::tx().task().new_key(key_bytes);
 
-		self.api
-			.tx()
-			.sign_and_submit_then_watch_default(&new_key_tx, &self.signer)
-			.await?;
+		let outcome = self.submit_and_check(&new_key_tx, finality).await?;
+		self.receipt(outcome).await
+	}
+
+	async fn register_key(&self, key: &VerifyingKey) -> Result<(), RedoxtError> {
+		let key_bytes = verifying_key_to_chain_bytes(key);
+		let new_key_tx = redot::tx().task().new_key(key_bytes);
+
+		self.submit_and_check(&new_key_tx, Finality::InBlock).await?.into_result()?;
 		Ok(())
 	}
 
@@ -112,58 +1175,405 @@ impl ClientSync for Client {
 		id: u32,
 		nonce: u32,
 		sign: &DkgSignature,
-	) -> Result<()> {
-		let metadata_bytes = metadata.encode();
+		finality: Finality,
+	) -> Result<SubmitReceipt, RedoxtError> {
+		let params = SubmitParams::default();
+		self.submit_metadata_with_params(metadata, id, nonce, sign, finality, &params).await
+	}
 
-		let metadata_bytes = WeakBoundedVec(metadata_bytes);
+	async fn submit_metadata_with_events<T: Encode + std::marker::Sync>(
+		&self,
+		metadata: &T,
+		id: u32,
+		nonce: u32,
+		sign: &DkgSignature,
+	) -> Result<Vec<TaskEvent>, RedoxtError> {
+		self.ensure_not_submitted(id, nonce)?;
+		let metadata_bytes = WeakBoundedVec(metadata.encode());
 
 		let submit_metadata_tx =
 			redot::tx().task().new_metadata(id, nonce, metadata_bytes, sign.serialize());
 
-		self.api
-			.tx()
-			.sign_and_submit_then_watch_default(&submit_metadata_tx, &self.signer)
+		let events = self
+			.submit_with_nonce(&submit_metadata_tx)
+			.await?
+			.wait_for_in_block()
+			.await?
+			.fetch_events()
 			.await?;
+		ExtrinsicOutcome::from_events(&events, self.api().metadata())?.into_result()?;
+		self.record_submitted(id, nonce);
 
-		Ok(())
+		let mut task_events = Vec::new();
+		for event in events.iter() {
+			let event = event?;
+			if let redot::Event::Task(task_event) = event.as_root_event::<redot::Event>()? {
+				task_events.push(task_event);
+			}
+		}
+
+		Ok(task_events)
 	}
 
-	async fn rotate_key(&self, key: &VerifyingKey, sign: &DkgSignature) -> Result<()> {
-		let key_bytes = key.serialize();
+	async fn submit_metadata_chunked<F, Fut>(
+		&self,
+		metadata: &[u8],
+		id: u32,
+		nonce: u32,
+		sign: F,
+	) -> Result<u32, RedoxtError>
+	where
+		F: Fn(Vec<u8>) -> Fut + Send + Sync,
+		Fut: std::future::Future<Output = Result<DkgSignature>> + Send,
+	{
+		let chunks = split_metadata(metadata)?;
+
+		let mut txs = Vec::with_capacity(chunks.len());
+		for (offset, chunk) in chunks.iter().enumerate() {
+			let chunk_nonce = nonce
+				.checked_add(offset as u32)
+				.ok_or_else(|| anyhow!("Nonce overflow"))?;
+			self.ensure_not_submitted(id, chunk_nonce)?;
+			let signature = sign(metadata_signing_message(chunk, id, chunk_nonce)).await?;
+			let chunk_bytes = chunk.encode();
+
+			txs.push(redot::tx().task().new_metadata(
+				id,
+				chunk_nonce,
+				WeakBoundedVec(chunk_bytes),
+				signature.serialize(),
+			));
+		}
+
+		let mut progresses = Vec::with_capacity(txs.len());
+		for tx in txs.iter() {
+			progresses.push(self.submit_with_nonce(tx).await?);
+		}
+		for (offset, progress) in progresses.into_iter().enumerate() {
+			self.wait_for_outcome(progress).await?.into_result()?;
+			self.record_submitted(id, nonce + offset as u32);
+		}
+
+		Ok(nonce + chunks.len() as u32)
+	}
+
+	async fn submit_metadata_batch<T: Encode + std::marker::Sync>(
+		&self,
+		items: &[(T, u32, u32, DkgSignature)],
+		mode: BatchMode,
+	) -> Result<Vec<BatchItemOutcome>, RedoxtError> {
+		if items.is_empty() {
+			return Err(RedoxtError::Other(anyhow!("Cannot submit an empty metadata batch")))
+		}
+		let metadata = self.api().metadata();
+		if metadata.pallet_by_name("Utility").is_none() {
+			return Err(RedoxtError::Metadata(anyhow!(
+				"The runtime has no utility pallet, metadata cannot be submitted in a batch"
+			)))
+		}
+
+		let mut calls = Vec::with_capacity(items.len());
+		for (item, id, nonce, sign) in items {
+			self.ensure_not_submitted(*id, *nonce)?;
+			let metadata_bytes = item.encode();
+			if metadata_bytes.len() > MAX_METADATA_LEN {
+				return Err(RedoxtError::Other(anyhow!(
+					"Metadata {} of task {} is {} bytes long, more than {}",
+					nonce,
+					id,
+					metadata_bytes.len(),
+					MAX_METADATA_LEN
+				)))
+			}
+			let new_metadata = Value::named_variant(
+				"new_metadata",
+				[
+					("id", Value::u128(*id as u128)),
+					("nonce", Value::u128(*nonce as u128)),
+					("metadata", Value::from_bytes(metadata_bytes)),
+					("signature_bytes", Value::from_bytes(sign.serialize())),
+				],
+			);
+			calls.push(Value::unnamed_variant("Task", [new_metadata]));
+		}
+
+		let call = match mode {
+			BatchMode::BestEffort => "batch",
+			BatchMode::Atomic => "batch_all",
+		};
+		let payload = subxt::dynamic::tx(
+			"Utility",
+			call,
+			Composite::unnamed(vec![Value::unnamed_composite(calls)]),
+		);
+		let events = self
+			.submit_with_nonce(&payload)
+			.await?
+			.wait_for_in_block()
+			.await?
+			.fetch_events()
+			.await?;
+
+		let outcomes = BatchItemOutcome::from_events(&events, items.len(), metadata)?;
+		for ((_, id, nonce, _), outcome) in items.iter().zip(outcomes.iter()) {
+			if outcome.is_completed() {
+				self.record_submitted(*id, *nonce);
+			}
+		}
+		Ok(outcomes)
+	}
+
+	async fn rotate_key(
+		&self,
+		key: &VerifyingKey,
+		sign: &DkgSignature,
+		finality: Finality,
+	) -> Result<SubmitReceipt, RedoxtError> {
+		let key_bytes = verifying_key_to_chain_bytes(key);
 		let sign_bytes = sign.serialize();
 
 		let rotate_key_tx = redot::tx().task().rotate_key(key_bytes, sign_bytes);
 
-		self.api
-			.tx()
-			.sign_and_submit_then_watch_default(&rotate_key_tx, &self.signer)
-			.await?;
+		let outcome = self.submit_and_check(&rotate_key_tx, finality).await?;
+		self.receipt(outcome).await
+	}
+}
 
-		Ok(())
+/// Environment variable holding the URL of the node, read by `ClientBuilder::from_env`.
+pub const URL_ENV: &str = "REDOXT_URL";
+
+/// Environment variable holding the secret URI of the signer, read by
+/// `ClientBuilder::from_env`.
+pub const SIGNER_URI_ENV: &str = "REDOXT_SIGNER_URI";
+
+// Read the environment variable `name`, `None` if it is unset or empty.
+fn env_var(name: &str) -> Result<Option<String>> {
+	match std::env::var(name) {
+		Ok(value) if value.is_empty() => Ok(None),
+		Ok(value) => Ok(Some(value)),
+		Err(std::env::VarError::NotPresent) => Ok(None),
+		Err(std::env::VarError::NotUnicode(_)) => Err(anyhow!("{} is not valid unicode", name)),
 	}
 }
 
 /// A builder pattern for creating a `Client` instance.
 pub struct ClientBuilder {
-	pub url: String,
+	/// URLs of the nodes, tried in order until one connects.
+	pub urls: Vec<String>,
 	pub signer: Keypair,
+	/// Optional path of a SCALE-encoded metadata file loaded at runtime.
+	pub metadata_path: Option<PathBuf>,
+	/// Root certificates trusted for `wss://` URLs. Empty to use the system roots.
+	pub tls_roots: Vec<Certificate>,
+	/// Whether the built client refuses to submit the same metadata twice.
+	pub idempotent_metadata: bool,
+	/// Whether the built client dry-runs metadata before submitting it.
+	pub validate_before_submit: bool,
+	/// How the built client retries calls failing with a transient error.
+	pub retry: RetryConfig,
+	/// Time allowed to connect to the node when building the client.
+	pub connect_timeout: Duration,
+	/// Time allowed for the node to answer each request of the built client.
+	pub request_timeout: Duration,
+	/// Whether `build` checks that the node runtime matches the compiled metadata.
+	pub verify_metadata: bool,
 }
 
 impl ClientBuilder {
 	/// Constructor for `ClientBuilder`.
 	pub fn new(url: &str, signer: Keypair) -> Self {
-		Self { url: url.to_string(), signer }
+		Self {
+			urls: vec![url.to_string()],
+			signer,
+			metadata_path: None,
+			tls_roots: Vec::new(),
+			idempotent_metadata: false,
+			validate_before_submit: false,
+			retry: RetryConfig::default(),
+			connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+			request_timeout: DEFAULT_REQUEST_TIMEOUT,
+			verify_metadata: true,
+		}
+	}
+
+	/// Create a builder from the environment, to keep the URL and the secret of the signer out
+	/// of code and command lines.
+	///
+	/// The URL is read from `REDOXT_URL`, and defaults to `ws://127.0.0.1:9944`. The signer is
+	/// read as a secret URI from `REDOXT_SIGNER_URI`, and defaults to the `//Alice` development
+	/// key. Unset and empty variables take the default. Other settings keep their defaults.
+	///
+	/// Fails, naming the variable, if the URL is not a `ws://` or `wss://` URL or the secret URI
+	/// is invalid. The secret itself is left out of the error.
+	pub fn from_env() -> Result<Self, RedoxtError> {
+		let mut builder = Self::default();
+		if let Some(url) = env_var(URL_ENV)? {
+			if !url.starts_with("ws://") && !url.starts_with("wss://") {
+				return Err(RedoxtError::Other(anyhow!(
+					"{} must be a ws:// or wss:// URL, got {}",
+					URL_ENV,
+					url
+				)))
+			}
+			builder = builder.set_url(&url);
+		}
+		if let Some(uri) = env_var(SIGNER_URI_ENV)? {
+			builder.signer = keypair_from_secret_uri(&uri)
+				.with_context(|| format!("Invalid signer in {}", SIGNER_URI_ENV))?;
+		}
+		Ok(builder)
+	}
+
+	/// Create a builder signing with the key of a secret URI, such as
+	/// `<phrase>//hard/soft///password`. See `keypair_from_secret_uri`.
+	pub fn from_secret_uri(url: &str, uri: &str) -> Result<Self, RedoxtError> {
+		Ok(Self::new(url, keypair_from_secret_uri(uri)?))
+	}
+
+	/// Create a builder signing with the key of a mnemonic phrase. See `keypair_from_mnemonic`.
+	pub fn from_mnemonic(
+		url: &str,
+		phrase: &str,
+		password: Option<&str>,
+	) -> Result<Self, RedoxtError> {
+		Ok(Self::new(url, keypair_from_mnemonic(phrase, password)?))
+	}
+
+	/// Create a builder signing with the sr25519 key `public` of type `key_type`, read from the
+	/// Substrate keystore directory at `path`. See `keypair_from_keystore`.
+	pub fn from_keystore(
+		url: &str,
+		path: impl AsRef<Path>,
+		key_type: KeyTypeId,
+		public: &[u8; 32],
+		password: Option<&str>,
+	) -> Result<Self, RedoxtError> {
+		Ok(Self::new(url, keypair_from_keystore(path.as_ref(), key_type, public, password)?))
 	}
 
 	/// Asynchronously build and return a `Client` instance.
-	pub async fn build(&self) -> Result<Client> {
-		let api = OnlineClient::<RedotConfig>::from_url(&self.url).await?;
-		Ok(Client { api, signer: self.signer.clone() })
+	///
+	/// The URLs are tried in order until one connects. If none does, the error lists why each
+	/// of them failed.
+	///
+	/// Unless disabled with `with_verify_metadata`, the metadata of the node is then compared
+	/// with the metadata this crate was compiled against, and an `IncompatibleMetadata` error is
+	/// returned if any call or storage entry of the `task` pallet changed. When `metadata_path`
+	/// is set, the metadata read from it replaces the one of the node first, so it is the
+	/// supplied metadata that is checked, as it is the one calls are encoded with.
+	pub async fn build(&self) -> Result<Client, RedoxtError> {
+		let tls = if self.tls_roots.is_empty() {
+			None
+		} else {
+			Some(tls::client_config(&self.tls_roots)?)
+		};
+
+		let (current, api) =
+			connect_any(&self.urls, 0, tls.as_ref(), self.connect_timeout, self.request_timeout)
+				.await
+				.map_err(connection_error)?;
+		let metadata = if let Some(path) = &self.metadata_path {
+			let metadata_bytes = std::fs::read(path)
+				.with_context(|| format!("Failed to read metadata from {}", path.display()))?;
+			let metadata =
+				Metadata::decode(&mut &metadata_bytes[..]).context("Failed to decode metadata")?;
+			api.set_metadata(metadata.clone());
+			Some(metadata)
+		} else {
+			None
+		};
+		if self.verify_metadata {
+			let compiled = Metadata::decode(&mut &compat::COMPILED_METADATA[..])
+				.context("Failed to decode the compiled metadata")?;
+			compat::check_task_pallet(&compiled, &api.metadata())
+				.map_err(|e| RedoxtError::Metadata(e.into()))?;
+		}
+		let mut client = Client::with_api(api, self.signer.clone(), self.urls.clone(), current);
+		client.connect_timeout = self.connect_timeout;
+		client.request_timeout = self.request_timeout;
+		client.tls = tls;
+		client.metadata = metadata;
+		client.set_idempotent_metadata(self.idempotent_metadata);
+		client.set_validate_before_submit(self.validate_before_submit);
+		client.set_retry(self.retry.clone());
+		client.refresh_nonce().await?;
+		Ok(client)
 	}
 
-	/// Set the URL for the API client.
+	/// Set the URL for the API client, replacing any other URL.
 	pub fn set_url(mut self, url: &str) -> Self {
-		self.url = url.to_string();
+		self.urls = vec![url.to_string()];
+		self
+	}
+
+	/// Add a URL to try when the previous ones fail to connect.
+	pub fn add_url(mut self, url: &str) -> Self {
+		self.urls.push(url.to_string());
+		self
+	}
+
+	/// Set the URLs to try, in order, replacing the current ones.
+	pub fn set_urls(mut self, urls: Vec<String>) -> Self {
+		self.urls = urls;
+		self
+	}
+
+	/// Trust the given root certificates, for example a private CA, when connecting to a
+	/// `wss://` URL.
+	///
+	/// The certificates replace the system roots for the connections of the built client only,
+	/// reconnections included. They are ignored for `ws://` URLs, which do not use TLS.
+	pub fn with_tls_roots(mut self, certs: Vec<Certificate>) -> Self {
+		self.tls_roots = certs;
+		self
+	}
+
+	/// Refuse to submit metadata whose `(id, nonce)` was already confirmed in a block.
+	///
+	/// See `Client::set_idempotent_metadata`.
+	pub fn with_idempotent_metadata(mut self, enabled: bool) -> Self {
+		self.idempotent_metadata = enabled;
+		self
+	}
+
+	/// Dry-run metadata before submitting it.
+	///
+	/// See `Client::set_validate_before_submit`.
+	pub fn with_validate_before_submit(mut self, enabled: bool) -> Self {
+		self.validate_before_submit = enabled;
+		self
+	}
+
+	/// Retry calls failing with a transient error according to `retry`.
+	///
+	/// See `Client::set_retry`.
+	pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+		self.retry = retry;
+		self
+	}
+
+	/// Check that the runtime of the node, or the metadata read from `metadata_path` when set,
+	/// matches the compiled metadata when building the client. Enabled by default.
+	pub fn with_verify_metadata(mut self, enabled: bool) -> Self {
+		self.verify_metadata = enabled;
+		self
+	}
+
+	/// Set the time allowed to connect to the node, after which `build` fails.
+	pub fn set_connect_timeout(mut self, timeout: Duration) -> Self {
+		self.connect_timeout = timeout;
+		self
+	}
+
+	/// Set the time allowed for the node to answer each request.
+	pub fn set_request_timeout(mut self, timeout: Duration) -> Self {
+		self.request_timeout = timeout;
+		self
+	}
+
+	/// Load the metadata from the given file instead of fetching it from the node.
+	pub fn set_metadata_path(mut self, path: impl AsRef<Path>) -> Self {
+		self.metadata_path = Some(path.as_ref().to_path_buf());
 		self
 	}
 }
@@ -171,6 +1581,356 @@ impl ClientBuilder {
 // Default implementation for `ClientBuilder`.
 impl Default for ClientBuilder {
 	fn default() -> Self {
-		Self { url: "ws://127.0.0.1:9944".to_owned(), signer: dev::alice() }
+		Self {
+			urls: vec!["ws://127.0.0.1:9944".to_owned()],
+			signer: dev::alice(),
+			metadata_path: None,
+			tls_roots: Vec::new(),
+			idempotent_metadata: false,
+			validate_before_submit: false,
+			retry: RetryConfig::default(),
+			connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+			request_timeout: DEFAULT_REQUEST_TIMEOUT,
+			verify_metadata: true,
+		}
+	}
+}
+
+// Decode the signer of an encoded extrinsic, if it is signed by an account ID.
+fn extrinsic_signer(extrinsic: &[u8]) -> Option<AccountId> {
+	let mut input = extrinsic;
+	let _length = Compact::<u32>::decode(&mut input).ok()?;
+	let version = u8::decode(&mut input).ok()?;
+	// The top bit of the version byte marks signed extrinsics.
+	if version & 0b1000_0000 == 0 {
+		return None
+	}
+	match Address::decode(&mut input).ok()? {
+		MultiAddress::Id(account_id) => Some(account_id),
+		_ => None,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use frost_ed25519::keys::{IdentifierList, KeyPackage, PublicKeyPackage};
+	use std::{collections::BTreeMap, sync::MutexGuard};
+
+	// Tests changing environment variables hold this lock, as the environment is shared by the
+	// threads running the tests.
+	static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+	// Sets environment variables, and restores them when dropped.
+	struct EnvGuard {
+		saved: Vec<(&'static str, Option<String>)>,
+		_lock: MutexGuard<'static, ()>,
+	}
+
+	impl EnvGuard {
+		fn set(vars: &[(&'static str, Option<&str>)]) -> Self {
+			let lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+			let mut saved = Vec::new();
+			for (name, value) in vars {
+				saved.push((*name, std::env::var(name).ok()));
+				match value {
+					Some(value) => std::env::set_var(name, value),
+					None => std::env::remove_var(name),
+				}
+			}
+			EnvGuard { saved, _lock: lock }
+		}
+	}
+
+	impl Drop for EnvGuard {
+		fn drop(&mut self) {
+			for (name, value) in &self.saved {
+				match value {
+					Some(value) => std::env::set_var(name, value),
+					None => std::env::remove_var(name),
+				}
+			}
+		}
+	}
+
+	fn account(signer: &Keypair) -> AccountId {
+		signer.public_key().to_account_id()
+	}
+
+	// A 2-of-2 FROST key split by a trusted dealer, signing what the `task` pallet verifies.
+	struct DealerKey {
+		key_packages: BTreeMap<frost_ed25519::Identifier, KeyPackage>,
+		public_key_package: PublicKeyPackage,
+	}
+
+	impl DealerKey {
+		fn new() -> Self {
+			let (shares, public_key_package) = frost_ed25519::keys::generate_with_dealer(
+				2,
+				2,
+				IdentifierList::Default,
+				&mut rand::thread_rng(),
+			)
+			.unwrap();
+			let key_packages = shares
+				.into_iter()
+				.map(|(id, share)| (id, KeyPackage::try_from(share).unwrap()))
+				.collect();
+			DealerKey { key_packages, public_key_package }
+		}
+
+		fn verifying_key(&self) -> VerifyingKey {
+			*self.public_key_package.verifying_key()
+		}
+
+		fn sign(&self, message: &[u8]) -> DkgSignature {
+			let mut rng = rand::thread_rng();
+			let mut nonces = BTreeMap::new();
+			let mut commitments = BTreeMap::new();
+			for (id, key_package) in &self.key_packages {
+				let (nonce, commitment) =
+					frost_ed25519::round1::commit(key_package.signing_share(), &mut rng);
+				nonces.insert(*id, nonce);
+				commitments.insert(*id, commitment);
+			}
+			let signing_package = frost_ed25519::SigningPackage::new(commitments, message);
+			let shares = self
+				.key_packages
+				.iter()
+				.map(|(id, key_package)| {
+					let share =
+						frost_ed25519::round2::sign(&signing_package, &nonces[id], key_package);
+					(*id, share.unwrap())
+				})
+				.collect();
+			frost_ed25519::aggregate(&signing_package, &shares, &self.public_key_package).unwrap()
+		}
+	}
+
+	// Connects as Alice to the development node at `REDOXT_URL`, or at `ws://127.0.0.1:9944` if
+	// it is unset, and registers a new dealer key in the `task` pallet.
+	//
+	// The tests using it change the chain state of the node they share, so run them one at a time
+	// with `cargo test -- --ignored --test-threads 1`.
+	async fn dev_node() -> (Client, DealerKey) {
+		let builder = {
+			let _env = EnvGuard::set(&[]);
+			ClientBuilder::from_env().unwrap()
+		};
+		let client = builder.build().await.unwrap();
+		let key = DealerKey::new();
+		client.register_key(&key.verifying_key()).await.unwrap();
+		(client, key)
+	}
+
+	// Returns the ID of a task without metadata yet, one past the last task.
+	async fn new_task_id(client: &Client) -> u32 {
+		client.last_task_id().await.unwrap().unwrap_or(0) + 1
+	}
+
+	#[test]
+	fn from_env_defaults_to_a_local_dev_node() {
+		let _env = EnvGuard::set(&[(URL_ENV, None), (SIGNER_URI_ENV, Some(""))]);
+		let builder = ClientBuilder::from_env().unwrap();
+		assert_eq!(builder.urls, vec!["ws://127.0.0.1:9944".to_string()]);
+		assert_eq!(account(&builder.signer), account(&dev::alice()));
+	}
+
+	#[test]
+	fn from_env_reads_the_url_and_signer() {
+		let _env = EnvGuard::set(&[
+			(URL_ENV, Some("wss://node.example:443")),
+			(SIGNER_URI_ENV, Some("//Bob")),
+		]);
+		let builder = ClientBuilder::from_env().unwrap();
+		assert_eq!(builder.urls, vec!["wss://node.example:443".to_string()]);
+		assert_eq!(account(&builder.signer), account(&dev::bob()));
+	}
+
+	#[test]
+	fn from_env_names_the_invalid_variable() {
+		let env = EnvGuard::set(&[(URL_ENV, None), (SIGNER_URI_ENV, Some("not a mnemonic"))]);
+		let error = ClientBuilder::from_env().err().unwrap().to_string();
+		assert!(error.contains(SIGNER_URI_ENV), "{}", error);
+		assert!(!error.contains("not a mnemonic"), "{}", error);
+		drop(env);
+
+		let url = Some("http://127.0.0.1:9933");
+		let _env = EnvGuard::set(&[(URL_ENV, url), (SIGNER_URI_ENV, None)]);
+		let error = ClientBuilder::from_env().err().unwrap().to_string();
+		assert!(error.contains(URL_ENV), "{}", error);
+	}
+
+	// Needs a development node at `REDOXT_URL`, or at `ws://127.0.0.1:9944` if it is unset.
+	#[tokio::test]
+	#[ignore = "needs a running development node"]
+	async fn build_skips_a_url_that_does_not_connect() {
+		let node = {
+			let _env = EnvGuard::set(&[]);
+			ClientBuilder::from_env().unwrap().urls.remove(0)
+		};
+		// Nothing listens on the first URL once the listener is dropped.
+		let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+		let dead = format!("ws://{}", listener.local_addr().unwrap());
+		drop(listener);
+
+		let client = ClientBuilder::new(&dead, dev::alice())
+			.add_url(&node)
+			.with_verify_metadata(false)
+			.build()
+			.await
+			.unwrap();
+		assert_eq!(client.url(), node);
+	}
+
+	#[tokio::test]
+	#[ignore = "needs a running development node"]
+	async fn metadata_batch_is_stored_with_one_extrinsic() {
+		let (client, key) = dev_node().await;
+		let id = new_task_id(&client).await;
+		let items = (0..3u32)
+			.map(|nonce| {
+				let metadata = format!("batched metadata {}", nonce).into_bytes();
+				let signature = key.sign(&metadata_signing_message(&metadata, id, nonce));
+				(metadata, id, nonce, signature)
+			})
+			.collect::<Vec<_>>();
+
+		let outcomes = client.submit_metadata_batch(&items, BatchMode::Atomic).await.unwrap();
+		assert!(outcomes.iter().all(BatchItemOutcome::is_completed), "{:?}", outcomes);
+		for (metadata, id, nonce, _) in &items {
+			let stored = client.task_metadata(*id, *nonce).await.unwrap();
+			assert_eq!(stored, Some(metadata.encode()));
+		}
+	}
+
+	#[tokio::test]
+	#[ignore = "needs a running development node"]
+	async fn back_to_back_submissions_are_all_accepted() {
+		let (client, key) = dev_node().await;
+		let id = new_task_id(&client).await;
+		let submissions = (0..5u32).map(|nonce| {
+			let client = &client;
+			let metadata = format!("back to back {}", nonce).into_bytes();
+			let signature = key.sign(&metadata_signing_message(&metadata, id, nonce));
+			async move {
+				client.submit_metadata(&metadata, id, nonce, &signature, Finality::InBlock).await
+			}
+		});
+
+		// All five are signed with a locally tracked nonce before any of them is in a block.
+		let receipts = futures::future::try_join_all(submissions).await.unwrap();
+		assert_eq!(receipts.len(), 5);
+		assert_eq!(client.next_metadata_nonce(id).await.unwrap(), 5);
+	}
+
+	#[tokio::test]
+	#[ignore = "needs a running development node"]
+	async fn submit_receipt_points_to_the_block_storing_the_metadata() {
+		let (client, key) = dev_node().await;
+		let id = new_task_id(&client).await;
+		let metadata = b"receipt".to_vec();
+		let signature = key.sign(&metadata_signing_message(&metadata, id, 0));
+
+		let receipt =
+			client.submit_metadata(&metadata, id, 0, &signature, Finality::InBlock).await.unwrap();
+		assert_ne!(receipt.tx_hash, H256::zero());
+		assert_ne!(receipt.block_hash, H256::zero());
+
+		let block = client.api().blocks().at(receipt.block_hash).await.unwrap();
+		assert_eq!(block.header().number, receipt.block_number);
+		let stored = client.stored_metadata_at(id, 0, receipt.block_hash).await.unwrap();
+		assert_eq!(stored, Some(metadata.encode()));
+	}
+
+	#[tokio::test]
+	#[ignore = "needs a running development node"]
+	async fn task_storage_is_read_back_typed() {
+		let (client, key) = dev_node().await;
+		assert_eq!(client.task_key().await.unwrap(), Some(key.verifying_key()));
+
+		let id = new_task_id(&client).await;
+		assert_eq!(client.task_metadata(id, 0).await.unwrap(), None);
+
+		let metadata = (42u32, H256::repeat_byte(7), true);
+		let signature = key.sign(&metadata_signing_message(&metadata, id, 0));
+		client.submit_metadata(&metadata, id, 0, &signature, Finality::InBlock).await.unwrap();
+
+		let stored = client.task_metadata(id, 0).await.unwrap().unwrap();
+		assert_eq!(<(u32, H256, bool)>::decode(&mut &stored[..]).unwrap(), metadata);
+		assert_eq!(client.last_task_id().await.unwrap(), Some(id));
+	}
+
+	#[tokio::test]
+	#[ignore = "needs a running development node"]
+	async fn new_key_event_is_yielded_once_finalized() {
+		let (client, _) = dev_node().await;
+		let mut events = client.subscribe_task_events().await.unwrap();
+		let key = DealerKey::new().verifying_key();
+		let receipt = client.new_key(&key, Finality::InBlock).await.unwrap();
+
+		let expected = verifying_key_to_chain_bytes(&key);
+		loop {
+			let record = events.next().await.unwrap().unwrap();
+			if let TaskEvent::KeyAdded(who, new_key) = record.event {
+				if new_key == expected {
+					assert_eq!(who, client.account_id());
+					assert_eq!(record.block_number, receipt.block_number);
+					break
+				}
+			}
+		}
+	}
+
+	#[tokio::test]
+	#[ignore = "needs a running development node"]
+	async fn submitted_key_round_trips_through_storage() {
+		let (client, _) = dev_node().await;
+		let key = DealerKey::new().verifying_key();
+		client.new_key(&key, Finality::InBlock).await.unwrap();
+
+		assert_eq!(client.verifying_key().await.unwrap(), Some(verifying_key_to_chain_bytes(&key)));
+		assert_eq!(client.task_key().await.unwrap(), Some(key));
+	}
+
+	#[tokio::test]
+	#[ignore = "needs a running development node"]
+	async fn dropped_connection_is_reported_then_reconnected() {
+		let node = {
+			let _env = EnvGuard::set(&[]);
+			ClientBuilder::from_env().unwrap().urls.remove(0)
+		};
+		let node_addr = node.strip_prefix("ws://").expect("a ws:// node URL").to_string();
+
+		// Forwards a single connection to the node, until the task is aborted.
+		let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let proxy_url = format!("ws://{}", listener.local_addr().unwrap());
+		let proxy = tokio::spawn(async move {
+			let (mut inbound, _) = listener.accept().await.unwrap();
+			let mut outbound = tokio::net::TcpStream::connect(node_addr).await.unwrap();
+			let _ = tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await;
+		});
+
+		let client = ClientBuilder::new(&proxy_url, dev::alice())
+			.add_url(&node)
+			.build()
+			.await
+			.unwrap();
+		let mut blocks = client.subscribe_blocks(Finality::InBlock).await.unwrap();
+		blocks.next().await.unwrap().unwrap();
+
+		proxy.abort();
+		let error = loop {
+			if let Err(e) = blocks.next().await.unwrap() {
+				break e
+			}
+		};
+		assert!(matches!(error, RedoxtError::Connection(_)), "{:?}", error);
+		assert!(error.is::<Disconnected>(), "{:?}", error);
+
+		client.reconnect().await.unwrap();
+		assert_eq!(client.url(), node);
+		let mut blocks = client.subscribe_blocks(Finality::InBlock).await.unwrap();
+		blocks.next().await.unwrap().unwrap();
 	}
 }
@@ -0,0 +1,371 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{RedotConfig, H256};
+use anyhow::{anyhow, Result};
+use codec::Decode;
+use serde::Serialize;
+use std::fmt;
+use subxt::{blocks::ExtrinsicEvents, error::DispatchError, rpc::types::DryRunResult, Metadata};
+
+/// Outcome of an extrinsic included in a block.
+#[derive(Debug)]
+pub enum ExtrinsicOutcome {
+	/// The call was dispatched successfully.
+	Success {
+		/// Hash of the block including the extrinsic.
+		block_hash: H256,
+		/// Hash of the extrinsic.
+		extrinsic_hash: H256,
+	},
+	/// The extrinsic was included, but its call failed to dispatch.
+	Failed(ExtrinsicFailed),
+}
+
+impl ExtrinsicOutcome {
+	/// Read the outcome from the `System` events of the extrinsic.
+	pub(crate) fn from_events(
+		events: &ExtrinsicEvents<RedotConfig>,
+		metadata: Metadata,
+	) -> Result<Self> {
+		let block_hash = events.all_events_in_block().block_hash();
+		let extrinsic_hash = events.extrinsic_hash();
+		for event in events.iter() {
+			let event = event?;
+			if event.pallet_name() != "System" {
+				continue
+			}
+			match event.variant_name() {
+				"ExtrinsicSuccess" =>
+					return Ok(ExtrinsicOutcome::Success { block_hash, extrinsic_hash }),
+				"ExtrinsicFailed" => {
+					let error = DispatchError::decode_from(event.field_bytes(), metadata)?;
+					return Ok(ExtrinsicOutcome::Failed(ExtrinsicFailed {
+						block_hash,
+						extrinsic_hash,
+						error,
+					}))
+				},
+				_ => {},
+			}
+		}
+		Err(anyhow!("No dispatch result found for extrinsic {:?}", extrinsic_hash))
+	}
+
+	/// Whether the call was dispatched successfully.
+	pub fn is_success(&self) -> bool {
+		matches!(self, ExtrinsicOutcome::Success { .. })
+	}
+
+	/// Return the hash of the including block, or an [`ExtrinsicFailed`] error if the call
+	/// failed to dispatch.
+	pub fn into_result(self) -> Result<H256> {
+		match self {
+			ExtrinsicOutcome::Success { block_hash, .. } => Ok(block_hash),
+			ExtrinsicOutcome::Failed(failed) => Err(failed.into()),
+		}
+	}
+}
+
+/// Where a successfully dispatched extrinsic landed, to correlate a submission with the chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SubmitReceipt {
+	/// Hash of the extrinsic.
+	pub tx_hash: H256,
+	/// Hash of the block including the extrinsic.
+	pub block_hash: H256,
+	/// Number of the block including the extrinsic.
+	pub block_number: u32,
+}
+
+/// An extrinsic was included in a block, but its call failed to dispatch.
+#[derive(Debug)]
+pub struct ExtrinsicFailed {
+	/// Hash of the block including the extrinsic.
+	pub block_hash: H256,
+	/// Hash of the extrinsic.
+	pub extrinsic_hash: H256,
+	/// The decoded dispatch error.
+	pub error: DispatchError,
+}
+
+impl fmt::Display for ExtrinsicFailed {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"Extrinsic {:?} included in block {:?} failed: {}",
+			self.extrinsic_hash, self.block_hash, self.error
+		)
+	}
+}
+
+impl std::error::Error for ExtrinsicFailed {}
+
+/// Result of dry-running a signed extrinsic against the state of the latest block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DryRunOutcome {
+	/// The extrinsic is valid and its call would dispatch successfully.
+	Success,
+	/// The extrinsic is valid, but its call would fail to dispatch with this error.
+	DispatchFailed(String),
+	/// The extrinsic would be rejected before dispatch, for example for a bad nonce or a payload
+	/// exceeding a bound.
+	Invalid,
+}
+
+impl DryRunOutcome {
+	/// Whether the extrinsic would be accepted and dispatched successfully.
+	pub fn is_success(&self) -> bool {
+		*self == DryRunOutcome::Success
+	}
+
+	/// Return an error describing why the extrinsic would not succeed, if it would not.
+	pub fn into_result(self) -> Result<()> {
+		match self {
+			DryRunOutcome::Success => Ok(()),
+			DryRunOutcome::DispatchFailed(error) =>
+				Err(anyhow!("Dry run: the call would fail to dispatch: {}", error)),
+			DryRunOutcome::Invalid => Err(anyhow!("Dry run: the extrinsic is invalid")),
+		}
+	}
+}
+
+impl From<DryRunResult> for DryRunOutcome {
+	fn from(result: DryRunResult) -> Self {
+		match result {
+			DryRunResult::Success => DryRunOutcome::Success,
+			DryRunResult::DispatchError(error) => DryRunOutcome::DispatchFailed(error.to_string()),
+			DryRunResult::TransactionValidityError => DryRunOutcome::Invalid,
+		}
+	}
+}
+
+/// Class of a call, deciding which part of the block weight it may use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Decode)]
+pub enum DispatchClass {
+	/// An ordinary call.
+	Normal,
+	/// A call for the operation of the network, using a reserved part of the block.
+	Operational,
+	/// A call that is always included, such as an inherent.
+	Mandatory,
+}
+
+/// Estimated fee and weight of an extrinsic, from the `TransactionPaymentApi` of the runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeDetails {
+	/// Fee charged for the extrinsic, without tip, in plancks.
+	pub partial_fee: u128,
+	/// Computation time of the call, in picoseconds.
+	pub ref_time: u64,
+	/// Size of the storage proof of the call, in bytes.
+	pub proof_size: u64,
+	/// Class of the call.
+	pub class: DispatchClass,
+}
+
+// `RuntimeDispatchInfo` as returned by `TransactionPaymentApi_query_info`.
+#[derive(Decode)]
+struct RuntimeDispatchInfo {
+	#[codec(compact)]
+	ref_time: u64,
+	#[codec(compact)]
+	proof_size: u64,
+	class: DispatchClass,
+	partial_fee: u128,
+}
+
+impl FeeDetails {
+	/// Decode the SCALE-encoded result of `TransactionPaymentApi_query_info`.
+	pub(crate) fn from_query_info(mut bytes: &[u8]) -> Result<Self> {
+		let info = RuntimeDispatchInfo::decode(&mut bytes)?;
+		Ok(FeeDetails {
+			partial_fee: info.partial_fee,
+			ref_time: info.ref_time,
+			proof_size: info.proof_size,
+			class: info.class,
+		})
+	}
+}
+
+/// How far a submitted extrinsic must progress before the submission returns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Finality {
+	/// Return once the extrinsic is included in a block. A reorg may still remove that block.
+	#[default]
+	InBlock,
+	/// Return once the block including the extrinsic is finalized.
+	Finalized,
+}
+
+impl Finality {
+	/// Whether an extrinsic included in a block reached this finality, given whether the block
+	/// is finalized.
+	pub(crate) fn is_reached(self, finalized: bool) -> bool {
+		finalized || self == Finality::InBlock
+	}
+}
+
+/// A submitted extrinsic left the transaction pool before reaching the requested finality.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubmissionFailed {
+	/// The extrinsic was dropped from the pool, for example because the pool is full.
+	Dropped,
+	/// The extrinsic is invalid, for example because its nonce is stale.
+	Invalid,
+	/// Another extrinsic with the same sender and nonce was included instead.
+	Usurped(H256),
+	/// The block including the extrinsic was not finalized in time.
+	FinalityTimeout(H256),
+	/// The node stopped reporting the status of the extrinsic.
+	StatusStreamEnded,
+}
+
+impl fmt::Display for SubmissionFailed {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			SubmissionFailed::Dropped => write!(f, "Extrinsic was dropped from the pool"),
+			SubmissionFailed::Invalid => write!(f, "Extrinsic is invalid"),
+			SubmissionFailed::Usurped(hash) =>
+				write!(f, "Extrinsic was usurped by extrinsic {:?}", hash),
+			SubmissionFailed::FinalityTimeout(hash) =>
+				write!(f, "Block {:?} including the extrinsic was not finalized in time", hash),
+			SubmissionFailed::StatusStreamEnded =>
+				write!(f, "Extrinsic status subscription ended before the requested finality"),
+		}
+	}
+}
+
+impl std::error::Error for SubmissionFailed {}
+
+/// How the calls of a `utility` batch are dispatched.
+///
+/// The two modes differ on failure. With `BestEffort`, calls before the failing one stay
+/// applied and the extrinsic itself succeeds, so the per-item outcomes tell which calls landed.
+/// With `Atomic`, the failure of any call fails the whole extrinsic and no call is applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchMode {
+	/// `utility.batch`: calls are dispatched in order until one fails.
+	BestEffort,
+	/// `utility.batch_all`: all calls are applied, or none of them.
+	Atomic,
+}
+
+/// Outcome of one call of a batch.
+#[derive(Debug)]
+pub enum BatchItemOutcome {
+	/// The call was dispatched successfully and its changes are applied.
+	Completed,
+	/// The call failed to dispatch, interrupting the batch.
+	Failed(DispatchError),
+	/// The call was not dispatched, or its changes were rolled back with the whole batch.
+	NotApplied,
+}
+
+impl BatchItemOutcome {
+	/// Read the outcome of each of the `len` calls of a batch from the events of its extrinsic.
+	///
+	/// `utility.batch` reports the index and error of the call that interrupted it with
+	/// `BatchInterrupted`. When `utility.batch_all` fails, the extrinsic fails and every call is
+	/// `NotApplied`.
+	pub fn from_events(
+		events: &ExtrinsicEvents<RedotConfig>,
+		len: usize,
+		metadata: Metadata,
+	) -> Result<Vec<Self>> {
+		if !ExtrinsicOutcome::from_events(events, metadata.clone())?.is_success() {
+			return Ok((0..len).map(|_| BatchItemOutcome::NotApplied).collect())
+		}
+
+		for event in events.iter() {
+			let event = event?;
+			if event.pallet_name() != "Utility" {
+				continue
+			}
+			match event.variant_name() {
+				"BatchCompleted" =>
+					return Ok((0..len).map(|_| BatchItemOutcome::Completed).collect()),
+				"BatchInterrupted" => {
+					let mut fields = event.field_bytes();
+					let index = u32::decode(&mut fields)? as usize;
+					let error = DispatchError::decode_from(fields, metadata)?;
+					let mut outcomes = (0..len)
+						.map(|item| {
+							if item < index {
+								BatchItemOutcome::Completed
+							} else {
+								BatchItemOutcome::NotApplied
+							}
+						})
+						.collect::<Vec<_>>();
+					if let Some(outcome) = outcomes.get_mut(index) {
+						*outcome = BatchItemOutcome::Failed(error);
+					}
+					return Ok(outcomes)
+				},
+				_ => {},
+			}
+		}
+		Err(anyhow!("No batch result found for extrinsic {:?}", events.extrinsic_hash()))
+	}
+
+	/// Whether the changes of the call are applied.
+	pub fn is_completed(&self) -> bool {
+		matches!(self, BatchItemOutcome::Completed)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fee_details_are_decoded_from_query_info() {
+		use codec::{Compact, Encode};
+
+		let mut bytes = Compact(1_250_000_000u64).encode();
+		bytes.extend(Compact(3_593u64).encode());
+		bytes.push(1);
+		bytes.extend(2_000_000_000u128.encode());
+
+		let fee = FeeDetails::from_query_info(&bytes).unwrap();
+		assert_eq!(fee.partial_fee, 2_000_000_000);
+		assert_eq!(fee.ref_time, 1_250_000_000);
+		assert_eq!(fee.proof_size, 3_593);
+		assert_eq!(fee.class, DispatchClass::Operational);
+		assert!(FeeDetails::from_query_info(&bytes[..4]).is_err());
+	}
+
+	#[test]
+	fn dry_run_results_are_mapped() {
+		assert!(DryRunOutcome::from(DryRunResult::Success).into_result().is_ok());
+		let invalid = DryRunOutcome::from(DryRunResult::TransactionValidityError);
+		assert_eq!(invalid, DryRunOutcome::Invalid);
+		let error = invalid.into_result().unwrap_err();
+		assert!(error.to_string().contains("invalid"), "{}", error);
+
+		let failed = DryRunOutcome::DispatchFailed("Task::InvalidNewSignature".into());
+		assert!(!failed.is_success());
+		let error = failed.into_result().unwrap_err();
+		assert!(error.to_string().contains("Task::InvalidNewSignature"), "{}", error);
+	}
+
+	#[test]
+	fn finalized_blocks_reach_every_finality() {
+		assert!(Finality::InBlock.is_reached(false));
+		assert!(Finality::InBlock.is_reached(true));
+		assert!(!Finality::Finalized.is_reached(false));
+		assert!(Finality::Finalized.is_reached(true));
+	}
+}
@@ -0,0 +1,97 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parameters of the extrinsics submitted by the client.
+
+use crate::{RedotConfig, H256};
+use subxt::config::polkadot::{Era, PlainTip, PolkadotExtrinsicParamsBuilder};
+
+/// How an extrinsic is submitted.
+///
+/// The default submits immortal extrinsics without a tip, like the methods taking no
+/// parameters.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubmitParams {
+	/// Number of blocks, counted from the latest block, the extrinsic stays valid for. `None`
+	/// makes it immortal. The period is rounded to a power of two between 4 and 65536.
+	pub mortality: Option<u64>,
+	/// Tip paid to the block author on top of the fee, to raise the priority of the extrinsic.
+	pub tip: u128,
+}
+
+impl SubmitParams {
+	/// Build the extrinsic parameters, with the mortal era starting at the `latest` block
+	/// number and hash.
+	///
+	/// The extrinsic is immortal if `mortality` or `latest` is `None`.
+	pub(crate) fn extrinsic_params(
+		&self,
+		latest: Option<(u64, H256)>,
+	) -> PolkadotExtrinsicParamsBuilder<RedotConfig> {
+		let params = PolkadotExtrinsicParamsBuilder::new().tip(PlainTip::new(self.tip));
+		match (self.mortality, latest) {
+			(Some(period), Some((number, hash))) => params.era(Era::mortal(period, number), hash),
+			_ => params,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{compat::COMPILED_METADATA, redot};
+	use codec::{Compact, Decode, Encode};
+	use subxt::{rpc::types::RuntimeVersion, Metadata, OfflineClient};
+	use subxt_signer::sr25519::dev;
+
+	// Sign a `remark` with `params` and return the signed extensions following the signature:
+	// the era, the nonce and the tip.
+	fn signed_extra(params: &SubmitParams, latest: Option<(u64, H256)>) -> Vec<u8> {
+		let metadata = Metadata::decode(&mut &COMPILED_METADATA[..]).unwrap();
+		let version =
+			RuntimeVersion { spec_version: 1, transaction_version: 1, other: Default::default() };
+		let client = OfflineClient::<RedotConfig>::new(H256::zero(), version, metadata);
+		let call = redot::tx().system().remark(Vec::new());
+		let tx = client
+			.tx()
+			.create_signed_with_nonce(&call, &dev::alice(), 0, params.extrinsic_params(latest))
+			.unwrap();
+
+		let mut encoded = tx.encoded();
+		Compact::<u32>::decode(&mut encoded).unwrap();
+		// The version byte, the `MultiAddress::Id` and the sr25519 `MultiSignature`.
+		encoded[1 + 33 + 65..].to_vec()
+	}
+
+	#[test]
+	fn tip_is_signed_into_the_extrinsic() {
+		let params = SubmitParams { mortality: None, tip: 1_000 };
+		let mut expected = vec![0, 0];
+		Compact(1_000u128).encode_to(&mut expected);
+		assert!(signed_extra(&params, None).starts_with(&expected));
+
+		assert!(signed_extra(&SubmitParams::default(), None).starts_with(&[0, 0, 0]));
+	}
+
+	#[test]
+	fn mortal_era_starts_at_the_latest_block() {
+		let params = SubmitParams { mortality: Some(64), tip: 0 };
+		let era = Era::mortal(64, 100).encode();
+		let latest = Some((100, H256::repeat_byte(1)));
+		assert!(signed_extra(&params, latest).starts_with(&era));
+
+		// Without a known latest block, the extrinsic stays immortal.
+		assert!(signed_extra(&params, None).starts_with(&[0]));
+	}
+}
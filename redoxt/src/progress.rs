@@ -0,0 +1,165 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The progress of a submitted extrinsic, as reported by the node.
+
+use crate::{RedotConfig, RedoxtError, H256};
+use futures::{Stream, StreamExt};
+use std::{
+	pin::Pin,
+	task::{Context, Poll},
+};
+use subxt::{
+	tx::{TxProgress, TxStatus},
+	OnlineClient,
+};
+
+/// A status of a submitted extrinsic.
+///
+/// `Finalized`, `FinalityTimeout`, `Usurped`, `Dropped` and `Invalid` are terminal: the node
+/// reports nothing more about the extrinsic after them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubmitStatus {
+	/// The extrinsic is in the pool, waiting for an earlier nonce of its sender.
+	Future,
+	/// The extrinsic is in the pool, ready to be included.
+	Ready,
+	/// The extrinsic was broadcast to the given peers.
+	Broadcast(Vec<String>),
+	/// The extrinsic was included in the block with the given hash.
+	InBlock(H256),
+	/// The block with the given hash, including the extrinsic, was retracted. The extrinsic
+	/// returns to the pool and may be included again.
+	Retracted(H256),
+	/// The block with the given hash, including the extrinsic, was not finalized in time.
+	FinalityTimeout(H256),
+	/// The block with the given hash, including the extrinsic, was finalized.
+	Finalized(H256),
+	/// The extrinsic with the given hash, from the same sender and with the same nonce, was
+	/// included instead.
+	Usurped(H256),
+	/// The extrinsic was dropped from the pool, for example because the pool is full.
+	Dropped,
+	/// The extrinsic is invalid, for example because its nonce is stale.
+	Invalid,
+}
+
+impl SubmitStatus {
+	/// Whether no status follows this one.
+	pub fn is_terminal(&self) -> bool {
+		matches!(
+			self,
+			SubmitStatus::Finalized(_) |
+				SubmitStatus::FinalityTimeout(_) |
+				SubmitStatus::Usurped(_) |
+				SubmitStatus::Dropped |
+				SubmitStatus::Invalid
+		)
+	}
+}
+
+impl From<TxStatus<RedotConfig, OnlineClient<RedotConfig>>> for SubmitStatus {
+	fn from(status: TxStatus<RedotConfig, OnlineClient<RedotConfig>>) -> Self {
+		match status {
+			TxStatus::Future => SubmitStatus::Future,
+			TxStatus::Ready => SubmitStatus::Ready,
+			TxStatus::Broadcast(peers) => SubmitStatus::Broadcast(peers),
+			TxStatus::InBlock(in_block) => SubmitStatus::InBlock(in_block.block_hash()),
+			TxStatus::Retracted(block_hash) => SubmitStatus::Retracted(block_hash),
+			TxStatus::FinalityTimeout(block_hash) => SubmitStatus::FinalityTimeout(block_hash),
+			TxStatus::Finalized(in_block) => SubmitStatus::Finalized(in_block.block_hash()),
+			TxStatus::Usurped(hash) => SubmitStatus::Usurped(hash),
+			TxStatus::Dropped => SubmitStatus::Dropped,
+			TxStatus::Invalid => SubmitStatus::Invalid,
+		}
+	}
+}
+
+/// The statuses of a submitted extrinsic, returned by `Client::submit_and_watch`.
+///
+/// Statuses are yielded in the order the node reports them. `Future` and `Ready` come first,
+/// `InBlock` may be followed by `Retracted` and then by another `InBlock`, and the stream ends
+/// after a terminal status (see `SubmitStatus::is_terminal`). It may also end, or fail with a
+/// `RedoxtError::Connection` error, when the node stops reporting, for example once the
+/// connection is lost.
+pub struct TxProgressStream {
+	progress: TxProgress<RedotConfig, OnlineClient<RedotConfig>>,
+}
+
+impl TxProgressStream {
+	pub(crate) fn new(progress: TxProgress<RedotConfig, OnlineClient<RedotConfig>>) -> Self {
+		TxProgressStream { progress }
+	}
+
+	/// Return the hash of the extrinsic.
+	pub fn extrinsic_hash(&self) -> H256 {
+		self.progress.extrinsic_hash()
+	}
+
+	/// Return the underlying progress, for example to pass it to `Client::wait_for_finality`.
+	pub fn into_inner(self) -> TxProgress<RedotConfig, OnlineClient<RedotConfig>> {
+		self.progress
+	}
+}
+
+impl Stream for TxProgressStream {
+	type Item = Result<SubmitStatus, RedoxtError>;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		self.progress.poll_next_unpin(cx).map(|status| {
+			status.map(|status| status.map(SubmitStatus::from).map_err(RedoxtError::from))
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	type Status = TxStatus<RedotConfig, OnlineClient<RedotConfig>>;
+
+	#[test]
+	fn statuses_are_converted() {
+		let peers = vec!["12D3KooW".to_string()];
+		assert_eq!(
+			SubmitStatus::from(Status::Broadcast(peers.clone())),
+			SubmitStatus::Broadcast(peers)
+		);
+		let hash = H256::repeat_byte(1);
+		assert_eq!(SubmitStatus::from(Status::Usurped(hash)), SubmitStatus::Usurped(hash));
+		assert_eq!(SubmitStatus::from(Status::Dropped), SubmitStatus::Dropped);
+	}
+
+	#[test]
+	fn only_final_statuses_are_terminal() {
+		let hash = H256::repeat_byte(1);
+		let terminal = [
+			SubmitStatus::Finalized(hash),
+			SubmitStatus::FinalityTimeout(hash),
+			SubmitStatus::Usurped(hash),
+			SubmitStatus::Dropped,
+			SubmitStatus::Invalid,
+		];
+		assert!(terminal.iter().all(SubmitStatus::is_terminal));
+
+		let pending = [
+			SubmitStatus::Future,
+			SubmitStatus::Ready,
+			SubmitStatus::Broadcast(Vec::new()),
+			SubmitStatus::InBlock(hash),
+			SubmitStatus::Retracted(hash),
+		];
+		assert!(!pending.iter().any(SubmitStatus::is_terminal));
+	}
+}
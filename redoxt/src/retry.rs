@@ -0,0 +1,208 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{is_connection_lost, is_transient_error};
+use anyhow::Result;
+use std::{future::Future, time::Duration};
+
+/// How calls failing with a transient error, such as a dropped connection, are retried.
+///
+/// The delay before the `n`th retry is `base_delay * 2^(n - 1)`, capped at `max_delay`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryConfig {
+	/// Maximum number of attempts, including the first one. `1` disables retries.
+	pub max_attempts: u32,
+	/// Delay before the first retry.
+	pub base_delay: Duration,
+	/// Upper bound of the delay between two attempts.
+	pub max_delay: Duration,
+}
+
+impl RetryConfig {
+	// Delay before the retry following `attempt`, counted from 1.
+	pub(crate) fn delay(&self, attempt: u32) -> Duration {
+		let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+		self.base_delay.saturating_mul(factor).min(self.max_delay)
+	}
+}
+
+impl Default for RetryConfig {
+	fn default() -> Self {
+		RetryConfig {
+			max_attempts: 4,
+			base_delay: Duration::from_millis(500),
+			max_delay: Duration::from_secs(10),
+		}
+	}
+}
+
+/// Run `op` until it succeeds, fails with an error that is not transient, or `config` allows no
+/// more attempts. See `is_transient_error`.
+///
+/// `op` is called once per attempt, so it must be safe to repeat. Every call made over a lost
+/// connection fails the same way, so `op` should reach the node over a new connection on each
+/// attempt, or use `retry_with_recovery`.
+pub async fn retry<T, F, Fut>(config: &RetryConfig, op: F) -> Result<T>
+where
+	F: FnMut() -> Fut,
+	Fut: Future<Output = Result<T>>,
+{
+	retry_with_recovery(config, op, || async { Ok(()) }).await
+}
+
+/// Run `op` like `retry`, calling `recover` before each retry following a lost connection, for
+/// example to connect to the node again. See `is_connection_lost`.
+///
+/// A failed recovery counts as a failed attempt.
+pub async fn retry_with_recovery<T, F, Fut, R, RFut>(
+	config: &RetryConfig,
+	mut op: F,
+	mut recover: R,
+) -> Result<T>
+where
+	F: FnMut() -> Fut,
+	Fut: Future<Output = Result<T>>,
+	R: FnMut() -> RFut,
+	RFut: Future<Output = Result<()>>,
+{
+	let mut attempt = 1;
+	let mut connection_lost = false;
+	loop {
+		let result = if connection_lost {
+			match recover().await {
+				Ok(()) => {
+					connection_lost = false;
+					op().await
+				},
+				Err(e) => Err(e),
+			}
+		} else {
+			op().await
+		};
+		match result {
+			Err(e) if attempt < config.max_attempts && is_transient_error(&e) => {
+				// A recovery that failed is tried again before the next attempt.
+				connection_lost = connection_lost || is_connection_lost(&e);
+				let delay = config.delay(attempt);
+				::log::debug!("Attempt {} failed, retrying in {:?}: {:?}", attempt, delay, e);
+				tokio::time::sleep(delay).await;
+				attempt += 1;
+			},
+			result => return result,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use anyhow::anyhow;
+	use std::cell::Cell;
+
+	fn config(max_attempts: u32) -> RetryConfig {
+		RetryConfig {
+			max_attempts,
+			base_delay: Duration::from_millis(1),
+			max_delay: Duration::from_millis(2),
+		}
+	}
+
+	// A transport that drops the connection `failures` times before answering.
+	fn flaky_transport(failures: u32, calls: &Cell<u32>) -> impl Future<Output = Result<u32>> {
+		calls.set(calls.get() + 1);
+		let call = calls.get();
+		async move {
+			if call <= failures {
+				let reset = std::io::Error::from(std::io::ErrorKind::ConnectionReset);
+				return Err(subxt::Error::Io(reset).into())
+			}
+			Ok(call)
+		}
+	}
+
+	#[tokio::test]
+	async fn retries_transient_errors_until_success() {
+		let calls = Cell::new(0);
+		let result = retry(&config(3), || flaky_transport(2, &calls)).await.unwrap();
+		assert_eq!(result, 3);
+	}
+
+	#[tokio::test]
+	async fn gives_up_after_max_attempts() {
+		let calls = Cell::new(0);
+		assert!(retry(&config(2), || flaky_transport(2, &calls)).await.is_err());
+		assert_eq!(calls.get(), 2);
+	}
+
+	// A transport whose connection is dropped until `reconnects` is incremented.
+	fn dead_transport(
+		reconnects: &Cell<u32>,
+		calls: &Cell<u32>,
+	) -> impl Future<Output = Result<u32>> {
+		calls.set(calls.get() + 1);
+		let connected = reconnects.get() > 0;
+		async move {
+			if !connected {
+				let reset = std::io::Error::from(std::io::ErrorKind::ConnectionReset);
+				return Err(subxt::Error::Io(reset).into())
+			}
+			Ok(1)
+		}
+	}
+
+	#[tokio::test]
+	async fn lost_connections_are_recovered_before_retrying() {
+		let (reconnects, calls) = (Cell::new(0), Cell::new(0));
+		assert!(retry(&config(3), || dead_transport(&reconnects, &calls)).await.is_err());
+		assert_eq!(calls.get(), 3);
+
+		calls.set(0);
+		let result = retry_with_recovery(
+			&config(3),
+			|| dead_transport(&reconnects, &calls),
+			|| {
+				reconnects.set(reconnects.get() + 1);
+				async { Ok(()) }
+			},
+		)
+		.await
+		.unwrap();
+		assert_eq!(result, 1);
+		assert_eq!((calls.get(), reconnects.get()), (2, 1));
+	}
+
+	#[tokio::test]
+	async fn permanent_errors_are_not_retried() {
+		let calls = Cell::new(0);
+		let result: Result<()> = retry(&config(3), || {
+			calls.set(calls.get() + 1);
+			async { Err(anyhow!("Invalid transaction")) }
+		})
+		.await;
+		assert!(result.is_err());
+		assert_eq!(calls.get(), 1);
+	}
+
+	#[test]
+	fn delay_doubles_up_to_the_maximum() {
+		let config = RetryConfig {
+			max_attempts: 5,
+			base_delay: Duration::from_millis(100),
+			max_delay: Duration::from_millis(300),
+		};
+		assert_eq!(config.delay(1), Duration::from_millis(100));
+		assert_eq!(config.delay(2), Duration::from_millis(200));
+		assert_eq!(config.delay(3), Duration::from_millis(300));
+	}
+}